@@ -0,0 +1,417 @@
+use std::cell::RefCell;
+
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use rxing::BarcodeFormat;
+
+use crate::{
+    detector::{Detection, Format, add_on_required, format_from_name, scan_formats, stream_region},
+    error::Error,
+    options, structured_append, validator,
+};
+
+/// How the stream scanner chooses between detection backends.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Policy {
+    /// Use `BarcodeDetector` when the browser supports one of the requested
+    /// formats, rxing otherwise.
+    NativeFirst,
+    RxingOnly,
+    /// Run both on every frame and report whether they agree.
+    BothAndCompare,
+}
+
+#[derive(Clone)]
+struct BackendOptions {
+    policy: Policy,
+    /// Restricts the formats requested from `BarcodeDetector`; by default
+    /// every active scan format it can decode in full is requested.
+    formats: Option<Vec<Format>>,
+}
+
+impl Default for BackendOptions {
+    fn default() -> Self {
+        BackendOptions {
+            policy: Policy::NativeFirst,
+            formats: None,
+        }
+    }
+}
+
+/// A `BarcodeDetector` together with the formats it was created for.
+struct NativeDetector {
+    detector: Object,
+    formats: Vec<Format>,
+}
+
+enum NativeState {
+    Unknown,
+    /// Negotiated for `requested`; `detector` is `None` if the browser has
+    /// no detector for any of them.
+    Negotiated {
+        requested: Vec<Format>,
+        detector: Option<NativeDetector>,
+    },
+}
+
+/// The backend the stream scanner uses for the current session.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StreamMode {
+    Rxing,
+    Native,
+    Compare,
+}
+
+thread_local! {
+    static BACKEND_OPTIONS: RefCell<Option<BackendOptions>> = const { RefCell::new(None) };
+    static NATIVE: RefCell<NativeState> = const { RefCell::new(NativeState::Unknown) };
+}
+
+fn current_options() -> BackendOptions {
+    BACKEND_OPTIONS.with(|slot| slot.borrow().clone().unwrap_or_default())
+}
+
+pub fn set_detection_backend(options: &JsValue) -> Result<(), Error> {
+    let obj = options::as_object(options)?;
+    let defaults = BackendOptions::default();
+
+    let policy = match options::get_string(&obj, "policy")?.as_deref() {
+        Some("native-first") => Policy::NativeFirst,
+        Some("rxing-only") => Policy::RxingOnly,
+        Some("both-and-compare") => Policy::BothAndCompare,
+        Some(_) => return Err(Error::InvalidOptions),
+        None => defaults.policy,
+    };
+
+    let formats = match options::get(&obj, "formats") {
+        Some(value) => {
+            let arr = value
+                .dyn_into::<Array>()
+                .map_err(|_| Error::InvalidOptions)?;
+            let formats = arr
+                .iter()
+                .map(|f| {
+                    f.as_string()
+                        .and_then(|name| format_from_name(&name))
+                        .map(Format::Rxing)
+                        .ok_or(Error::InvalidOptions)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(formats)
+        }
+        None => defaults.formats,
+    };
+
+    BACKEND_OPTIONS.with(|slot| *slot.borrow_mut() = Some(BackendOptions { policy, formats }));
+    // The detector is rebuilt for the new format list on the next session.
+    NATIVE.with(|native| *native.borrow_mut() = NativeState::Unknown);
+
+    Ok(())
+}
+
+/// Returns true if `format` is requested from `BarcodeDetector`. Other
+/// formats are always decoded with rxing.
+fn native_capable(format: &Format) -> bool {
+    match format {
+        // Hits that may belong to a structured append sequence are
+        // confirmed with rxing, see `needs_rxing_confirmation`.
+        Format::Rxing(BarcodeFormat::QR_CODE) => true,
+        // Native results carry no add-on, so a required one needs rxing.
+        Format::Rxing(BarcodeFormat::UPC_A) => !add_on_required(),
+        // The DataBar composite flag and GS1 element strings are only read
+        // by rxing; Pharmacode has no native reader.
+        _ => false,
+    }
+}
+
+/// Returns true if a native hit may lack what only rxing reads: the
+/// structured append header of a QR Code, which native results do not
+/// expose. The frame is then decoded again with rxing for that format.
+pub fn needs_rxing_confirmation(detection: &Detection) -> bool {
+    detection.format == Format::Rxing(BarcodeFormat::QR_CODE) && structured_append::enabled()
+}
+
+/// The active scan formats that are requested from `BarcodeDetector`.
+fn requested_native_formats(opts: &BackendOptions) -> Vec<Format> {
+    scan_formats()
+        .into_iter()
+        .filter(native_capable)
+        .filter(|format| {
+            opts.formats
+                .as_ref()
+                .is_none_or(|allowed| allowed.contains(format))
+        })
+        .collect()
+}
+
+async fn create_native_detector(requested: &[Format]) -> Option<NativeDetector> {
+    if requested.is_empty() {
+        return None;
+    }
+
+    let ctor = Reflect::get(&js_sys::global(), &JsValue::from_str("BarcodeDetector"))
+        .ok()?
+        .dyn_into::<Function>()
+        .ok()?;
+
+    let get_supported = Reflect::get(&ctor, &JsValue::from_str("getSupportedFormats"))
+        .ok()?
+        .dyn_into::<Function>()
+        .ok()?;
+    let promise: Promise = get_supported.call0(&ctor).ok()?.dyn_into().ok()?;
+    let supported = JsFuture::from(promise).await.ok()?;
+    let supported: Vec<String> = Array::from(&supported)
+        .iter()
+        .filter_map(|f| f.as_string())
+        .collect();
+
+    let formats: Vec<Format> = requested
+        .iter()
+        .filter(|f| supported.iter().any(|name| name == f.name()))
+        .copied()
+        .collect();
+    if formats.is_empty() {
+        return None;
+    }
+
+    let names: Array = formats
+        .iter()
+        .map(|f| JsValue::from_str(f.name()))
+        .collect();
+    let detector_options = Object::new();
+    Reflect::set(&detector_options, &JsValue::from_str("formats"), &names).ok()?;
+    let detector = Reflect::construct(&ctor, &Array::of1(&detector_options))
+        .ok()?
+        .dyn_into::<Object>()
+        .ok()?;
+
+    Some(NativeDetector { detector, formats })
+}
+
+/// Negotiates the native detector for the active scan formats, again
+/// whenever they changed since the last session. Must be awaited before
+/// `stream_mode` is consulted.
+pub async fn prepare() {
+    let opts = current_options();
+    if opts.policy == Policy::RxingOnly {
+        return;
+    }
+
+    let requested = requested_native_formats(&opts);
+    let negotiated = NATIVE.with(|native| match &*native.borrow() {
+        NativeState::Negotiated {
+            requested: previous,
+            ..
+        } => *previous == requested,
+        NativeState::Unknown => false,
+    });
+    if negotiated {
+        return;
+    }
+
+    let detector = create_native_detector(&requested).await;
+    NATIVE.with(|native| {
+        *native.borrow_mut() = NativeState::Negotiated {
+            requested,
+            detector,
+        }
+    });
+}
+
+/// Returns the active scan formats the native detector does not decode,
+/// which are tried with rxing when it finds nothing in a frame.
+pub fn rxing_fallback_formats() -> Vec<Format> {
+    let native = NATIVE.with(|native| match &*native.borrow() {
+        NativeState::Negotiated {
+            detector: Some(detector),
+            ..
+        } => detector.formats.clone(),
+        _ => Vec::new(),
+    });
+
+    scan_formats()
+        .into_iter()
+        .filter(|format| !native.contains(format))
+        .collect()
+}
+
+pub fn stream_mode() -> StreamMode {
+    let native_ready = NATIVE.with(|native| {
+        matches!(
+            *native.borrow(),
+            NativeState::Negotiated {
+                detector: Some(_),
+                ..
+            }
+        )
+    });
+
+    match current_options().policy {
+        Policy::NativeFirst if native_ready => StreamMode::Native,
+        Policy::BothAndCompare if native_ready => StreamMode::Compare,
+        _ => StreamMode::Rxing,
+    }
+}
+
+/// Returns whether results should say which backend produced them.
+pub fn tags_results() -> bool {
+    current_options().policy != Policy::RxingOnly
+}
+
+fn point_from_js(point: JsValue) -> Option<(f32, f32)> {
+    let coord = |key: &str| {
+        Reflect::get(&point, &JsValue::from_str(key))
+            .ok()
+            .and_then(|v| v.as_f64())
+    };
+
+    Some((coord("x")? as f32, coord("y")? as f32))
+}
+
+/// Normalizes a `DetectedBarcode` into a `Detection`, applying validation.
+///
+/// Barcodes outside the stream region searched by rxing, or of a format
+/// left to rxing, are skipped so both backends report the same results.
+fn detection_from_js(
+    barcode: &JsValue,
+    formats: &[Format],
+    region: (u32, u32, u32, u32),
+) -> Option<Detection> {
+    let get = |key: &str| Reflect::get(barcode, &JsValue::from_str(key)).ok();
+
+    let format = Format::Rxing(format_from_name(&get("format")?.as_string()?)?);
+    if !formats.contains(&format) {
+        return None;
+    }
+
+    let points: Vec<(f32, f32)> = get("cornerPoints")
+        .map(|points| {
+            Array::from(&points)
+                .iter()
+                .filter_map(point_from_js)
+                .collect()
+        })
+        .unwrap_or_default();
+    let (x, y, width, height) = region;
+    let in_region = |(px, py): &(f32, f32)| {
+        (x as f32..=(x + width) as f32).contains(px)
+            && (y as f32..=(y + height) as f32).contains(py)
+    };
+    if points.is_empty() || !points.iter().all(in_region) {
+        return None;
+    }
+
+    let raw_value = get("rawValue")?.as_string()?;
+    let value = validator::validate(&format, &raw_value)?;
+
+    Some(Detection {
+        value,
+        format,
+        points,
+        structured_append: None,
+        linked_composite: false,
+        add_on: None,
+    })
+}
+
+/// Runs `BarcodeDetector.detect` on `source`, a `width` by `height` frame.
+pub async fn detect_native(source: &JsValue, width: u32, height: u32) -> Result<Detection, Error> {
+    let (detector, formats) = NATIVE.with(|native| match &*native.borrow() {
+        NativeState::Negotiated {
+            detector: Some(native),
+            ..
+        } => Ok((native.detector.clone(), native.formats.clone())),
+        _ => Err(Error::Internal),
+    })?;
+    let region = stream_region(width, height);
+
+    let detect = Reflect::get(&detector, &JsValue::from_str("detect"))
+        .ok()
+        .and_then(|f| f.dyn_into::<Function>().ok())
+        .ok_or(Error::Internal)?;
+    let promise: Promise = detect
+        .call1(&detector, source)
+        .ok()
+        .and_then(|p| p.dyn_into().ok())
+        .ok_or(Error::Internal)?;
+    let barcodes = JsFuture::from(promise).await.map_err(|_| Error::Internal)?;
+
+    Array::from(&barcodes)
+        .iter()
+        .find_map(|barcode| detection_from_js(&barcode, &formats, region))
+        .ok_or(Error::NotDetected)
+}
+
+/// Combines the results of both backends. The rxing result wins when both
+/// decoded a value; the tags say which backend was used and whether they
+/// agreed.
+pub fn compare(
+    native: Result<Detection, Error>,
+    rxing: Result<Detection, Error>,
+) -> (Result<Detection, Error>, Vec<(&'static str, JsValue)>) {
+    match (native, rxing) {
+        (Ok(native), Ok(rxing)) => {
+            let agree = native.value == rxing.value && native.format == rxing.format;
+            (
+                Ok(rxing),
+                vec![
+                    ("backend", JsValue::from_str("rxing")),
+                    ("backendsAgree", JsValue::from_bool(agree)),
+                ],
+            )
+        }
+        (Ok(native), Err(_)) => (
+            Ok(native),
+            vec![
+                ("backend", JsValue::from_str("native")),
+                ("backendsAgree", JsValue::FALSE),
+            ],
+        ),
+        (Err(_), Ok(rxing)) => (
+            Ok(rxing),
+            vec![
+                ("backend", JsValue::from_str("rxing")),
+                ("backendsAgree", JsValue::FALSE),
+            ],
+        ),
+        (Err(_), Err(error)) => (Err(error), Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection(format: BarcodeFormat) -> Detection {
+        Detection {
+            value: "value".into(),
+            format: Format::Rxing(format),
+            points: Vec::new(),
+            structured_append: None,
+            linked_composite: false,
+            add_on: None,
+        }
+    }
+
+    #[test]
+    fn qr_codes_and_upc_a_go_native_by_default() {
+        assert!(native_capable(&Format::Rxing(BarcodeFormat::QR_CODE)));
+        assert!(native_capable(&Format::Rxing(BarcodeFormat::UPC_A)));
+        for format in [
+            BarcodeFormat::UPC_E,
+            BarcodeFormat::RSS_14,
+            BarcodeFormat::RSS_EXPANDED,
+        ] {
+            assert!(!native_capable(&Format::Rxing(format)), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn only_qr_hits_are_confirmed_while_reassembly_is_on() {
+        assert!(structured_append::enabled());
+        assert!(needs_rxing_confirmation(&detection(BarcodeFormat::QR_CODE)));
+        assert!(!needs_rxing_confirmation(&detection(BarcodeFormat::UPC_A)));
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    rc::Rc,
+};
+
+use js_sys::Uint8Array;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{JsFuture, spawn_local};
+use web_sys::{File, FileList};
+
+use crate::{
+    detector::{Detection, detect_from_blob, filter_result},
+    error::Error,
+    event::{invoke_on_batch_complete, invoke_on_detect_tagged, invoke_on_stop},
+    options, pdf, reader,
+};
+
+const DEFAULT_CONCURRENCY: u32 = 2;
+const MAX_CONCURRENCY: u32 = 8;
+
+#[derive(Clone, Copy)]
+struct BatchOptions {
+    enabled: bool,
+    concurrency: u32,
+}
+
+thread_local! {
+    static BATCH_OPTIONS: Cell<BatchOptions> = const {
+        Cell::new(BatchOptions {
+            enabled: false,
+            concurrency: DEFAULT_CONCURRENCY,
+        })
+    };
+}
+
+pub fn set_batch_options(options: &JsValue) -> Result<(), Error> {
+    let obj = options::as_object(options)?;
+
+    let enabled = options::get_bool(&obj, "enabled")?.unwrap_or(true);
+    let concurrency = options::get_u32(&obj, "concurrency")?.unwrap_or(DEFAULT_CONCURRENCY);
+    if !(1..=MAX_CONCURRENCY).contains(&concurrency) {
+        return Err(Error::InvalidOptions);
+    }
+
+    BATCH_OPTIONS.with(|opts| {
+        opts.set(BatchOptions {
+            enabled,
+            concurrency,
+        })
+    });
+    reader::set_multiple(enabled);
+
+    Ok(())
+}
+
+pub fn batch_enabled() -> bool {
+    BATCH_OPTIONS.with(|opts| opts.get().enabled)
+}
+
+/// Scans one file, returning a single result for an image or one result per
+/// scanned page, tagged with its 1-based number, for a PDF.
+async fn scan_file(file: &File) -> Vec<(Option<u32>, Result<Detection, Error>)> {
+    if file.type_() == pdf::PDF_MIME {
+        let bytes = match JsFuture::from(file.array_buffer()).await {
+            Ok(buffer) => Uint8Array::new(&buffer).to_vec(),
+            Err(_) => return vec![(None, Err(Error::FileReadFailed))],
+        };
+        return match pdf::scan_pages(&bytes) {
+            Ok(pages) => pages
+                .into_iter()
+                .map(|(page, result)| (Some(page), result))
+                .collect(),
+            Err(e) => vec![(None, Err(e))],
+        };
+    }
+
+    if !reader::is_image_file(file) {
+        return vec![(None, Err(Error::InvalidMime))];
+    }
+
+    vec![(None, filter_result(detect_from_blob(file).await))]
+}
+
+/// Scans every file in `files`, reporting one `on_detect` per image or PDF
+/// page followed by `on_batch_complete` and `on_stop`. A PDF counts as
+/// detected when any of its pages is.
+///
+/// Decoding runs on the main thread, so `concurrency` bounds how many files
+/// are being read at once rather than decoded in parallel.
+pub fn scan_files(files: &FileList) {
+    let queue: VecDeque<(u32, File)> = (0..files.length())
+        .filter_map(|i| files.get(i).map(|file| (i, file)))
+        .collect();
+
+    let total = queue.len() as u32;
+    let workers = BATCH_OPTIONS.with(|opts| opts.get().concurrency).min(total);
+
+    let queue = Rc::new(RefCell::new(queue));
+    let active_workers = Rc::new(Cell::new(workers));
+    let detected = Rc::new(Cell::new(0u32));
+
+    for _ in 0..workers {
+        let queue = queue.clone();
+        let active_workers = active_workers.clone();
+        let detected = detected.clone();
+
+        spawn_local(async move {
+            loop {
+                let next = queue.borrow_mut().pop_front();
+                let Some((index, file)) = next else {
+                    break;
+                };
+
+                let results = scan_file(&file).await;
+                if results.iter().any(|(_, result)| result.is_ok()) {
+                    detected.set(detected.get() + 1);
+                }
+
+                for (page, result) in results {
+                    let mut tags = vec![
+                        ("fileName", JsValue::from_str(&file.name())),
+                        ("index", JsValue::from_f64(index as f64)),
+                    ];
+                    if let Some(page) = page {
+                        tags.push(("page", JsValue::from_f64(page as f64)));
+                    }
+                    invoke_on_detect_tagged(result.as_ref(), &tags);
+                }
+            }
+
+            active_workers.set(active_workers.get() - 1);
+            if active_workers.get() == 0 {
+                invoke_on_batch_complete(total, detected.get());
+                invoke_on_stop();
+            }
+        });
+    }
+}
@@ -0,0 +1,383 @@
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::JsValue;
+
+use crate::{crypto::base64_encode, error::Error};
+
+/// Deepest nesting of arrays, maps and tags accepted by the parser.
+const MAX_DEPTH: usize = 32;
+
+/// A decoded CBOR (RFC 8949) data item.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Integer(i128),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Tag(u64, Box<Value>),
+    Bool(bool),
+    Null,
+    Float(f64),
+}
+
+impl Value {
+    /// Returns the value of the integer `key` in a map.
+    pub fn get(&self, key: i128) -> Option<&Value> {
+        match self.untagged() {
+            Value::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| k.as_integer() == Some(key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Strips any tags wrapped around the value.
+    pub fn untagged(&self) -> &Value {
+        match self {
+            Value::Tag(_, inner) => inner.untagged(),
+            other => other,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i128> {
+        match self.untagged() {
+            Value::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.untagged() {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self.untagged() {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Converts the value to a JSON-compatible JS value: maps become objects
+    /// keyed by their text or integer keys, byte strings become base64
+    /// strings, and tags are dropped.
+    pub fn to_js(&self) -> JsValue {
+        match self {
+            Value::Integer(n) => JsValue::from_f64(*n as f64),
+            Value::Bytes(bytes) => JsValue::from_str(&base64_encode(bytes)),
+            Value::Text(text) => JsValue::from_str(text),
+            Value::Array(items) => items.iter().map(Value::to_js).collect::<Array>().into(),
+            Value::Map(entries) => {
+                let obj = Object::new();
+                for (key, value) in entries {
+                    let key = match key.untagged() {
+                        Value::Text(text) => text.clone(),
+                        Value::Integer(n) => n.to_string(),
+                        _ => continue,
+                    };
+                    let _ = Reflect::set(&obj, &JsValue::from_str(&key), &value.to_js());
+                }
+                obj.into()
+            }
+            Value::Tag(_, inner) => inner.to_js(),
+            Value::Bool(b) => JsValue::from_bool(*b),
+            Value::Null => JsValue::NULL,
+            Value::Float(f) => JsValue::from_f64(*f),
+        }
+    }
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or(Error::PayloadDecodeFailed)?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn uint(&mut self, len: usize) -> Result<u64, Error> {
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |acc, b| (acc << 8) | *b as u64))
+    }
+
+    /// Reads the argument of a data item head. Indefinite lengths are not
+    /// used by COSE and are rejected.
+    fn argument(&mut self, info: u8) -> Result<u64, Error> {
+        match info {
+            0..=23 => Ok(info as u64),
+            24 => self.uint(1),
+            25 => self.uint(2),
+            26 => self.uint(4),
+            27 => self.uint(8),
+            _ => Err(Error::PayloadDecodeFailed),
+        }
+    }
+
+    /// Reads a length, which cannot exceed the remaining input.
+    fn length(&mut self, info: u8) -> Result<usize, Error> {
+        let len = self.argument(info)?;
+        if len > (self.data.len() - self.pos) as u64 {
+            return Err(Error::PayloadDecodeFailed);
+        }
+        Ok(len as usize)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::PayloadDecodeFailed);
+        }
+
+        let head = self.take(1)?[0];
+        let (major, info) = (head >> 5, head & 0x1f);
+        match major {
+            0 => Ok(Value::Integer(self.argument(info)? as i128)),
+            1 => Ok(Value::Integer(-1 - self.argument(info)? as i128)),
+            2 => {
+                let len = self.length(info)?;
+                Ok(Value::Bytes(self.take(len)?.to_vec()))
+            }
+            3 => {
+                let len = self.length(info)?;
+                String::from_utf8(self.take(len)?.to_vec())
+                    .map(Value::Text)
+                    .map_err(|_| Error::PayloadDecodeFailed)
+            }
+            4 => {
+                let len = self.length(info)?;
+                (0..len)
+                    .map(|_| self.value(depth + 1))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Array)
+            }
+            5 => {
+                let len = self.length(info)?;
+                (0..len)
+                    .map(|_| Ok((self.value(depth + 1)?, self.value(depth + 1)?)))
+                    .collect::<Result<_, _>>()
+                    .map(Value::Map)
+            }
+            6 => {
+                let tag = self.argument(info)?;
+                Ok(Value::Tag(tag, Box::new(self.value(depth + 1)?)))
+            }
+            _ => match info {
+                20 => Ok(Value::Bool(false)),
+                21 => Ok(Value::Bool(true)),
+                22 | 23 => Ok(Value::Null),
+                25 => Ok(Value::Float(half_to_f64(self.uint(2)? as u16))),
+                26 => Ok(Value::Float(f32::from_bits(self.uint(4)? as u32) as f64)),
+                27 => Ok(Value::Float(f64::from_bits(self.uint(8)?))),
+                _ => Err(Error::PayloadDecodeFailed),
+            },
+        }
+    }
+}
+
+fn half_to_f64(half: u16) -> f64 {
+    let exponent = (half >> 10) & 0x1f;
+    let mantissa = (half & 0x3ff) as f64;
+    let magnitude = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (1024.0 + mantissa) * 2f64.powi(exponent as i32 - 25),
+    };
+    if half & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Parses a single CBOR data item spanning all of `data`.
+pub fn parse(data: &[u8]) -> Result<Value, Error> {
+    let mut parser = Parser { data, pos: 0 };
+    let value = parser.value(0)?;
+    if parser.pos != data.len() {
+        return Err(Error::PayloadDecodeFailed);
+    }
+    Ok(value)
+}
+
+/// Appends a data item head with major type `major` and argument `arg`.
+pub fn write_head(out: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+    match arg {
+        0..=23 => out.push(major | arg as u8),
+        24..=0xff => out.extend([major | 24, arg as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend((arg as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend((arg as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend(arg.to_be_bytes());
+        }
+    }
+}
+
+/// Appends a byte string.
+pub fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_head(out, 2, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Appends a text string.
+pub fn write_text(out: &mut Vec<u8>, text: &str) {
+    write_head(out, 3, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn parses_the_rfc_8949_examples() {
+        let integer = |text| parse(&hex(text)).unwrap().as_integer();
+        assert_eq!(integer("17"), Some(23));
+        assert_eq!(integer("1818"), Some(24));
+        assert_eq!(integer("1903e8"), Some(1000));
+        assert_eq!(integer("1bffffffffffffffff"), Some(u64::MAX as i128));
+        assert_eq!(integer("3863"), Some(-100));
+        assert_eq!(integer("3bffffffffffffffff"), Some(-(1 << 64)));
+        assert_eq!(integer("c11a514b67b0"), Some(1363896240));
+
+        let float = |text| match parse(&hex(text)).unwrap() {
+            Value::Float(f) => f,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(float("f93c00"), 1.0);
+        assert_eq!(float("f9c400"), -4.0);
+        assert_eq!(float("f90001"), 5.960464477539063e-8);
+        assert_eq!(float("f97c00"), f64::INFINITY);
+        assert!(float("f97e00").is_nan());
+        assert_eq!(float("fa47c35000"), 100000.0);
+        assert_eq!(float("fb3ff199999999999a"), 1.1);
+
+        assert_eq!(parse(&hex("6449455446")).unwrap().as_text(), Some("IETF"));
+        assert_eq!(
+            parse(&hex("4401020304")).unwrap().as_bytes(),
+            Some(&[1, 2, 3, 4][..])
+        );
+        assert!(matches!(parse(&hex("f5")).unwrap(), Value::Bool(true)));
+        assert!(matches!(parse(&hex("f6")).unwrap(), Value::Null));
+
+        let map = parse(&hex("a201020304")).unwrap();
+        assert_eq!(map.get(1).and_then(Value::as_integer), Some(2));
+        assert_eq!(map.get(3).and_then(Value::as_integer), Some(4));
+        assert!(map.get(5).is_none());
+    }
+
+    #[test]
+    fn writes_heads_with_the_shortest_argument() {
+        for (arg, expected) in [
+            (0, "00"),
+            (23, "17"),
+            (24, "1818"),
+            (255, "18ff"),
+            (256, "190100"),
+            (65536, "1a00010000"),
+            (1 << 32, "1b0000000100000000"),
+        ] {
+            let mut out = Vec::new();
+            write_head(&mut out, 0, arg);
+            assert_eq!(out, hex(expected), "{arg}");
+            assert_eq!(parse(&out).unwrap().as_integer(), Some(arg as i128));
+        }
+
+        let mut out = Vec::new();
+        write_head(&mut out, 4, 2);
+        write_text(&mut out, "Signature1");
+        write_bytes(&mut out, &[0xa1]);
+        let Value::Array(items) = parse(&out).unwrap() else {
+            panic!("not an array");
+        };
+        assert_eq!(items[0].as_text(), Some("Signature1"));
+        assert_eq!(items[1].as_bytes(), Some(&[0xa1][..]));
+    }
+
+    #[test]
+    fn rejects_truncated_items() {
+        for text in [
+            "",
+            "18",
+            "1903",
+            "1b00000000",
+            "430102",
+            "6349",
+            "8201",
+            "a20102",
+            "a1",
+            "c1",
+            "f9",
+            "fa0000",
+            "fb00",
+        ] {
+            assert!(parse(&hex(text)).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_items() {
+        for text in [
+            // Trailing data after the item.
+            "0000", // Indefinite lengths and reserved additional information.
+            "5f4101ff", "9fff", "bfff", "1c",     // Invalid UTF-8 text.
+            "62fffe", // A simple value outside those supported.
+            "f0",
+        ] {
+            assert!(parse(&hex(text)).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn rejects_lengths_beyond_the_input_without_allocating() {
+        for text in [
+            "5bffffffffffffffff",
+            "7affffffff00",
+            "9bffffffffffffffff00",
+            "bb7fffffffffffffff0000",
+        ] {
+            assert!(parse(&hex(text)).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested = |depth: usize, head: u8| {
+            let mut data = vec![head; depth];
+            data.push(0);
+            data
+        };
+        assert!(parse(&nested(MAX_DEPTH, 0x81)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH + 1, 0x81)).is_err());
+        assert!(parse(&nested(MAX_DEPTH + 1, 0xc6)).is_err());
+        assert!(parse(&nested(100_000, 0x81)).is_err());
+    }
+}
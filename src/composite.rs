@@ -0,0 +1,603 @@
+use std::collections::HashMap;
+
+use rxing::{
+    common::BitArray,
+    oned::rss::expanded::decoders::{DecodedObject, GeneralAppIdDecoder},
+    pdf417::pdf_417_common::getCodeword,
+};
+
+use crate::{gs1::GS, pharmacode::Run};
+
+/// Elements and modules in a MicroPDF417 codeword.
+const CODEWORD_ELEMENTS: usize = 8;
+const CODEWORD_MODULES: usize = 17;
+const MAX_ELEMENT_MODULES: usize = 6;
+/// Elements in the row address pattern between two codeword columns.
+const RAP_ELEMENTS: usize = 6;
+/// Pixel rows on which a row of codewords must be read.
+const MIN_ROW_READS: usize = 2;
+/// Fewest error correction codewords taken as a match.
+const MIN_EC_CODEWORDS: usize = 2;
+
+/// Prime modulus of the PDF417 error correction field and its generator.
+const MODULUS: u32 = 929;
+const GENERATOR: u32 = 3;
+
+/// Codewords opening a CC-B component, followed by a byte compaction latch
+/// whose byte count is (924) or need not be (901) a multiple of six.
+const CC_B_MODE: u32 = 920;
+const BYTE_LATCH: u32 = 901;
+const BYTE_LATCH_6: u32 = 924;
+/// The lowest mode latch codeword. As the text compaction latch, it also
+/// pads the data to fill the symbol.
+const LATCH_MIN: u32 = 900;
+
+/// CC-A packs 69 bits into each group of seven base-928 codewords; a
+/// shorter final group of `m` codewords carries `10 * m - 1` bits.
+const CC_A_BASE: u128 = 928;
+const CC_A_GROUP: usize = 7;
+const CC_A_GROUP_BITS: usize = 69;
+
+/// Largest compressed date, for 31 December 2099.
+const MAX_DATE: u32 = 99 * 384 + 11 * 32 + 31;
+
+/// Reads a codeword from its eight element widths, bar first. Returns the
+/// cluster (0, 1 or 2) with the codeword value.
+fn read_codeword(widths: &[usize]) -> Option<(u32, u32)> {
+    let total: usize = widths.iter().sum();
+    let modules: Vec<usize> = widths
+        .iter()
+        .map(|w| (w * CODEWORD_MODULES + total / 2) / total)
+        .collect();
+    if modules.iter().sum::<usize>() != CODEWORD_MODULES
+        || modules
+            .iter()
+            .any(|m| !(1..=MAX_ELEMENT_MODULES).contains(m))
+    {
+        return None;
+    }
+
+    let symbol = modules.iter().enumerate().fold(0u32, |symbol, (i, m)| {
+        let bits = if i % 2 == 0 { (1 << m) - 1 } else { 0 };
+        (symbol << m) | bits
+    });
+    let codeword = u32::try_from(getCodeword(symbol)).ok()?;
+
+    // Rows cycle through clusters 0, 3 and 6 of the bar widths.
+    let bars = [modules[0], modules[2], modules[4], modules[6]];
+    let cluster = (bars[0] + bars[2] + 9 - bars[1] - bars[3]) % 9;
+    cluster
+        .is_multiple_of(3)
+        .then_some((cluster as u32 / 3, codeword))
+}
+
+/// Reads the codewords of one row. Codewords follow each other directly or
+/// across a row address pattern, all in the same cluster; the longest such
+/// run is the row, and a tie leaves the row unread.
+fn read_row(runs: &[Run]) -> Option<(u32, Vec<u32>)> {
+    let windows = (runs.len() + 1).saturating_sub(CODEWORD_ELEMENTS);
+    let codewords: HashMap<usize, (u32, u32)> = (0..windows)
+        .filter(|&i| runs[i].dark)
+        .filter_map(|i| {
+            let widths: Vec<usize> = runs[i..i + CODEWORD_ELEMENTS]
+                .iter()
+                .map(|r| r.len)
+                .collect();
+            read_codeword(&widths).map(|codeword| (i, codeword))
+        })
+        .collect();
+
+    let mut best: Option<(u32, Vec<u32>)> = None;
+    let mut tied = false;
+    for (&start, &(cluster, first)) in &codewords {
+        let (mut at, mut row) = (start, vec![first]);
+        while let Some((next, codeword)) = [CODEWORD_ELEMENTS, CODEWORD_ELEMENTS + RAP_ELEMENTS]
+            .iter()
+            .find_map(|step| {
+                let next = at + step;
+                let (c, codeword) = codewords.get(&next)?;
+                (*c == cluster).then_some((next, *codeword))
+            })
+        {
+            at = next;
+            row.push(codeword);
+        }
+
+        let longest = best.as_ref().map_or(0, |(_, best)| best.len());
+        if row.len() > longest {
+            best = Some((cluster, row));
+            tied = false;
+        } else if row.len() == longest {
+            tied = true;
+        }
+    }
+    best.filter(|_| !tied)
+}
+
+/// Reads the rows of a component from its pixel rows, top first. Pixel rows
+/// are grouped by cluster, and each group is taken as the row read most
+/// often within it.
+fn read_rows(pixel_rows: &[Vec<Run>]) -> Option<Vec<u32>> {
+    let mut groups: Vec<(u32, HashMap<Vec<u32>, usize>)> = Vec::new();
+    for (cluster, row) in pixel_rows.iter().filter_map(|runs| read_row(runs)) {
+        match groups.last_mut() {
+            Some((last, reads)) if *last == cluster => *reads.entry(row).or_default() += 1,
+            _ => groups.push((cluster, HashMap::from([(row, 1)]))),
+        }
+    }
+
+    let rows: Vec<Vec<u32>> = groups
+        .into_iter()
+        .filter_map(|(_, reads)| {
+            reads
+                .into_iter()
+                .filter(|(_, count)| *count >= MIN_ROW_READS)
+                .max_by_key(|(row, count)| (*count, row.len()))
+                .map(|(row, _)| row)
+        })
+        .collect();
+    let columns = rows.first()?.len();
+    rows.iter()
+        .all(|row| row.len() == columns)
+        .then(|| rows.concat())
+}
+
+/// Returns the number of trailing error correction codewords, taken as the
+/// number of leading zero syndromes. Components are only read when every
+/// codeword is; errors are not corrected.
+fn ec_count(codewords: &[u32]) -> Option<usize> {
+    let mut power = 1;
+    let count = (0..codewords.len() - 1)
+        .take_while(|_| {
+            power = power * GENERATOR % MODULUS;
+            codewords
+                .iter()
+                .fold(0, |sum, c| (sum * power + c) % MODULUS)
+                == 0
+        })
+        .count();
+    (count >= MIN_EC_CODEWORDS).then_some(count)
+}
+
+/// Appends the low `count` bits of `value`, most significant first.
+fn push_bits(bits: &mut Vec<bool>, value: u128, count: usize) {
+    bits.extend((0..count).rev().map(|i| value >> i & 1 == 1));
+}
+
+/// Unpacks the bytes of a CC-B component's byte compaction codewords.
+fn cc_b_bits(latch: u32, codewords: &[u32]) -> Option<Vec<bool>> {
+    // Padding after the data uses the text compaction latch.
+    let end = codewords
+        .iter()
+        .position(|&c| c >= LATCH_MIN)
+        .unwrap_or(codewords.len());
+    let (data, padding) = codewords.split_at(end);
+    if padding.iter().any(|&c| c != LATCH_MIN) {
+        return None;
+    }
+
+    // Five codewords carry six bytes. With 901 the byte count is not a
+    // multiple of six, and the last one to five codewords are a byte each.
+    let grouped = match latch {
+        BYTE_LATCH_6 if data.len().is_multiple_of(5) => data.len(),
+        BYTE_LATCH_6 => return None,
+        _ => data.len().saturating_sub(1) / 5 * 5,
+    };
+    let mut bits = Vec::new();
+    for group in data[..grouped].chunks(5) {
+        let value = group.iter().fold(0u64, |v, &c| v * 900 + c as u64);
+        push_bits(&mut bits, value.into(), 48);
+    }
+    for &byte in &data[grouped..] {
+        if byte > 0xff {
+            return None;
+        }
+        push_bits(&mut bits, byte.into(), 8);
+    }
+    Some(bits)
+}
+
+/// Unpacks the base-928 groups of a CC-A component.
+fn cc_a_bits(codewords: &[u32]) -> Option<Vec<bool>> {
+    let mut bits = Vec::new();
+    for group in codewords.chunks(CC_A_GROUP) {
+        let count = match group.len() {
+            CC_A_GROUP => CC_A_GROUP_BITS,
+            m => 10 * m - 1,
+        };
+        let value = group
+            .iter()
+            .try_fold(0u128, |v, &c| (c < 928).then(|| v * CC_A_BASE + c as u128))?;
+        if value >> count != 0 {
+            return None;
+        }
+        push_bits(&mut bits, value, count);
+    }
+    Some(bits)
+}
+
+/// Decodes a general-purpose data field from `pos`, keeping the FNC1
+/// separators as GS. Trailing separators left by padding are dropped.
+fn general_purpose(bits: &[bool], pos: usize) -> Option<String> {
+    let mut array = BitArray::with_size(bits.len());
+    for (i, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+        array.set(i);
+    }
+
+    let mut decoder = GeneralAppIdDecoder::new(&array);
+    let mut fields = Vec::new();
+    let (mut pos, mut remaining) = (pos, String::new());
+    loop {
+        let info = decoder.decodeGeneralPurposeField(pos, &remaining).ok()?;
+        fields.push(info.getNewString().to_string());
+        remaining = if info.isRemaining() {
+            info.getRemainingValue().to_string()
+        } else {
+            String::new()
+        };
+        if info.getNewPosition() == pos {
+            break;
+        }
+        pos = info.getNewPosition();
+    }
+    Some(fields.join(&GS.to_string()).trim_matches(GS).to_string())
+}
+
+/// Decodes the data bits of a component into GS1 element strings, per the
+/// encodation method in the leading bits. Method `11`, which compacts AI
+/// (90), is not read.
+fn element_strings(bits: &[bool]) -> Option<String> {
+    let value = |from, count| bits_value(bits, from, count);
+    match (bits.first()?, bits.get(1)?) {
+        (false, _) => general_purpose(bits, 1),
+        // Method 10: an optional production (11) or expiration (17) date,
+        // then the batch or lot number (10) unless an FNC1 ends it at once.
+        (true, false) => {
+            let (date, pos) = if value(2, 2)? == 0b11 {
+                (String::new(), 4)
+            } else {
+                let date = value(2, 16)?;
+                if date > MAX_DATE {
+                    return None;
+                }
+                let ai = if value(18, 1)? == 1 { "17" } else { "11" };
+                let (year, month, day) = (date / 384, date / 32 % 12 + 1, date % 32);
+                (format!("{ai}{year:02}{month:02}{day:02}"), 19)
+            };
+            let rest = general_purpose(bits, pos)?;
+            if rest.is_empty() || lot_is_empty(bits, pos) {
+                Some(date + &rest)
+            } else {
+                Some(format!("{date}10{rest}"))
+            }
+        }
+        (true, true) => None,
+    }
+}
+
+/// Returns true if the general-purpose field at `pos` opens with an FNC1,
+/// which ends an empty lot number.
+fn lot_is_empty(bits: &[bool], pos: usize) -> bool {
+    // Numeric mode opens the field, and a pair whose first digit is FNC1
+    // has the value 8 + 10 * 11 + d.
+    bits_value(bits, pos, 7).is_some_and(|v| (118..=127).contains(&v))
+}
+
+/// Reads `count` bits from `from` as an unsigned value.
+fn bits_value(bits: &[bool], from: usize, count: usize) -> Option<u32> {
+    bits.get(from..from + count)
+        .map(|b| b.iter().fold(0, |v, &bit| v << 1 | u32::from(bit)))
+}
+
+/// Decodes a CC-A or CC-B composite component from the pixel rows above its
+/// linear symbol, top first, into the GS1 element strings it carries.
+pub fn decode(pixel_rows: &[Vec<Run>]) -> Option<String> {
+    let codewords = read_rows(pixel_rows)?;
+    if codewords.len() <= MIN_EC_CODEWORDS {
+        return None;
+    }
+    let data = &codewords[..codewords.len() - ec_count(&codewords)?];
+
+    let bits = match data {
+        [CC_B_MODE, latch @ (BYTE_LATCH | BYTE_LATCH_6), bytes @ ..] => cc_b_bits(*latch, bytes)?,
+        _ => cc_a_bits(data)?,
+    };
+    element_strings(&bits).filter(|elements| !elements.is_empty())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use rxing::pdf417::{
+        encoder::pdf_417_error_correction::generateErrorCorrection, pdf_417_common::SYMBOL_TABLE,
+    };
+
+    use super::*;
+    use crate::pharmacode::runs;
+
+    /// Row address patterns: left and center, then right with its stop bar.
+    const RAP: [usize; RAP_ELEMENTS] = [2, 1, 1, 3, 1, 2];
+    const RIGHT_RAP: [usize; 7] = [2, 1, 1, 3, 1, 1, 1];
+
+    /// The element widths of a codeword in a cluster, bar first.
+    fn codeword_widths(cluster: u32, codeword: u32) -> Vec<usize> {
+        SYMBOL_TABLE
+            .iter()
+            .map(|&symbol| {
+                let bits: Vec<bool> = (0..CODEWORD_MODULES)
+                    .rev()
+                    .map(|i| symbol >> i & 1 == 1)
+                    .collect();
+                runs(bits.into_iter())
+                    .iter()
+                    .map(|r| r.len)
+                    .collect::<Vec<_>>()
+            })
+            .find(|widths| read_codeword(widths) == Some((cluster, codeword)))
+            .unwrap()
+    }
+
+    /// The element widths of each row of a component, light first, with
+    /// a center row address pattern between column pairs.
+    pub(crate) fn rows(codewords: &[u32], columns: usize) -> Vec<Vec<usize>> {
+        codewords
+            .chunks(columns)
+            .enumerate()
+            .map(|(r, row)| {
+                let mut elements = vec![1];
+                elements.extend(RAP);
+                for (c, &codeword) in row.iter().enumerate() {
+                    if c > 0 && c % 2 == 0 {
+                        elements.extend(RAP);
+                    }
+                    elements.extend(codeword_widths(r as u32 % 3, codeword));
+                }
+                elements.extend(RIGHT_RAP);
+                elements
+            })
+            .collect()
+    }
+
+    /// Appends `2^(level + 1)` error correction codewords.
+    pub(crate) fn with_ec(data: &[u32], level: u32) -> Vec<u32> {
+        let text: String = data.iter().map(|&c| char::from_u32(c).unwrap()).collect();
+        let ec = generateErrorCorrection(&text, level).unwrap();
+        data.iter()
+            .copied()
+            .chain(ec.chars().map(u32::from))
+            .collect()
+    }
+
+    /// Three pixel rows of each row of a component, `module` pixels wide.
+    fn pixel_rows(codewords: &[u32], columns: usize, module: usize) -> Vec<Vec<Run>> {
+        rows(codewords, columns)
+            .iter()
+            .flat_map(|row| {
+                let bits: Vec<bool> = row
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, w)| std::iter::repeat_n(i % 2 == 1, w * module))
+                    .chain(std::iter::repeat_n(false, 10 * module))
+                    .collect();
+                std::iter::repeat_n(runs(bits.into_iter()), 3)
+            })
+            .collect()
+    }
+
+    /// Encodes a general-purpose field: digit pairs in numeric mode and
+    /// anything else in alphanumeric mode, with GS as FNC1.
+    fn general_purpose_bits(data: &str) -> Vec<bool> {
+        let chars: Vec<char> = data.chars().collect();
+        let digit = |i: usize| match chars.get(i) {
+            Some(&GS) => Some(10),
+            Some(c) => c.to_digit(10),
+            None => None,
+        };
+
+        let (mut bits, mut alpha, mut i) = (Vec::new(), false, 0);
+        while i < chars.len() {
+            if let (Some(a), Some(b)) = (digit(i), digit(i + 1))
+                && (a, b) != (10, 10)
+            {
+                if alpha {
+                    push_bits(&mut bits, 0, 3);
+                    alpha = false;
+                }
+                push_bits(&mut bits, (8 + 11 * a + b).into(), 7);
+                i += 2;
+                continue;
+            }
+            if !alpha {
+                push_bits(&mut bits, 0, 4);
+                alpha = true;
+            }
+            match chars[i] {
+                GS => push_bits(&mut bits, 15, 5),
+                c @ '0'..='9' => push_bits(&mut bits, (c as u32 - '0' as u32 + 5).into(), 5),
+                c => push_bits(&mut bits, (c as u32 - 'A' as u32 + 32).into(), 6),
+            }
+            i += 1;
+        }
+        bits
+    }
+
+    /// Method 10 bits for an expiration date (17) followed by the field.
+    fn expiry_bits(yy: u32, mm: u32, dd: u32, field: &str) -> Vec<bool> {
+        let mut bits = vec![true, false];
+        push_bits(&mut bits, (yy * 384 + (mm - 1) * 32 + dd).into(), 16);
+        bits.push(true);
+        bits.extend(general_purpose_bits(field));
+        bits
+    }
+
+    /// Packs bits into CC-A data codewords, padded to the capacity of `count`
+    /// codewords.
+    pub(crate) fn cc_a(mut bits: Vec<bool>, count: usize) -> Vec<u32> {
+        let capacity =
+            count / CC_A_GROUP * CC_A_GROUP_BITS + (10 * (count % CC_A_GROUP)).saturating_sub(1);
+        assert!(bits.len() <= capacity);
+        bits.resize(capacity, false);
+
+        let mut codewords = Vec::new();
+        let mut rest = bits.as_slice();
+        for len in (0..count)
+            .step_by(CC_A_GROUP)
+            .map(|start| (count - start).min(CC_A_GROUP))
+        {
+            let size = if len == CC_A_GROUP {
+                CC_A_GROUP_BITS
+            } else {
+                10 * len - 1
+            };
+            let (group, tail) = rest.split_at(size);
+            let mut value = group.iter().fold(0u128, |v, &b| v << 1 | u128::from(b));
+            let mut digits = vec![0; len];
+            for digit in digits.iter_mut().rev() {
+                *digit = (value % CC_A_BASE) as u32;
+                value /= CC_A_BASE;
+            }
+            codewords.extend(digits);
+            rest = tail;
+        }
+        codewords
+    }
+
+    /// Packs bits, padded to whole bytes, into CC-B data codewords.
+    fn cc_b(mut bits: Vec<bool>) -> Vec<u32> {
+        bits.resize(bits.len().div_ceil(8) * 8, false);
+        let bytes: Vec<u64> = bits
+            .chunks(8)
+            .map(|b| b.iter().fold(0, |v, &bit| v << 1 | u64::from(bit)))
+            .collect();
+
+        let latch = if bytes.len().is_multiple_of(6) {
+            BYTE_LATCH_6
+        } else {
+            BYTE_LATCH
+        };
+        let mut codewords = vec![CC_B_MODE, latch];
+        let groups = bytes.len() / 6;
+        for group in bytes[..groups * 6].chunks(6) {
+            let mut value = group.iter().fold(0, |v, &b| v << 8 | b);
+            let mut digits = [0; 5];
+            for digit in digits.iter_mut().rev() {
+                *digit = (value % 900) as u32;
+                value /= 900;
+            }
+            codewords.extend(digits);
+        }
+        codewords.extend(bytes[groups * 6..].iter().map(|&b| b as u32));
+        codewords
+    }
+
+    #[test]
+    fn codewords_are_read_in_every_cluster() {
+        for cluster in 0..3 {
+            for codeword in [0, 1, 450, 900, 920, 928] {
+                let widths = codeword_widths(cluster, codeword);
+                assert_eq!(widths.len(), CODEWORD_ELEMENTS);
+                let mut pixels: Vec<usize> = widths.iter().map(|w| w * 3).collect();
+                // Ink spread of a pixel.
+                pixels[0] += 1;
+                pixels[1] -= 1;
+                assert_eq!(read_codeword(&pixels), Some((cluster, codeword)));
+            }
+        }
+        // Seventeen modules that are not a codeword of any cluster.
+        assert_eq!(read_codeword(&[1, 1, 1, 1, 1, 1, 1, 10]), None);
+    }
+
+    #[test]
+    fn rows_are_read_across_row_address_patterns() {
+        let codewords = [920, 924, 17, 400];
+        let rows = pixel_rows(&codewords, 4, 2);
+        assert_eq!(read_row(&rows[0]), Some((0, codewords.to_vec())));
+        assert_eq!(read_rows(&rows), Some(codewords.to_vec()));
+
+        // Rows of different widths are not a component.
+        let mut rows = pixel_rows(&[1, 2, 3, 4, 5, 6, 7, 8], 4, 2);
+        rows.extend(pixel_rows(&[9, 10, 11], 4, 2).into_iter().take(3));
+        assert_eq!(read_rows(&rows[..9]), None);
+    }
+
+    #[test]
+    fn error_correction_codewords_are_counted() {
+        let data = [920, 924, 17, 400, 12];
+        for level in 0..3 {
+            let codewords = with_ec(&data, level);
+            assert_eq!(ec_count(&codewords), Some(2 << level));
+        }
+
+        let mut codewords = with_ec(&data, 1);
+        codewords[2] += 1;
+        assert_ne!(ec_count(&codewords), Some(4));
+    }
+
+    #[test]
+    fn general_purpose_fields_keep_fnc1_separators() {
+        let field = format!("10ABC12{GS}21123456");
+        let mut bits = vec![false];
+        bits.extend(general_purpose_bits(&field));
+        assert_eq!(element_strings(&bits).as_deref(), Some(field.as_str()));
+
+        // Padding after the data adds nothing.
+        bits.resize(bits.len() + 20, false);
+        assert_eq!(element_strings(&bits).as_deref(), Some(field.as_str()));
+    }
+
+    #[test]
+    fn method_10_restores_the_date_and_lot_number() {
+        let bits = expiry_bits(26, 12, 31, "ABC12");
+        assert_eq!(element_strings(&bits).as_deref(), Some("1726123110ABC12"));
+
+        // A production date, then an FNC1 that ends an empty lot number.
+        let mut bits = expiry_bits(25, 1, 0, &format!("{GS}21123456"));
+        bits[18] = false;
+        assert_eq!(element_strings(&bits).as_deref(), Some("1125010021123456"));
+
+        // A date past 2099, and method 11.
+        let mut bits = vec![true, false];
+        push_bits(&mut bits, (MAX_DATE + 1).into(), 16);
+        bits.push(true);
+        bits.extend(general_purpose_bits("ABC12"));
+        assert_eq!(element_strings(&bits), None);
+        assert_eq!(element_strings(&[true, true, false, false]), None);
+    }
+
+    #[test]
+    fn cc_a_and_cc_b_components_decode_from_rows() {
+        let bits = expiry_bits(26, 12, 31, "ABC12");
+
+        // 12 data and 4 error correction codewords in four columns.
+        let codewords = with_ec(&cc_a(bits.clone(), 12), 1);
+        assert_eq!(
+            decode(&pixel_rows(&codewords, 4, 2)).as_deref(),
+            Some("1726123110ABC12")
+        );
+
+        // CC-B, padded to two columns.
+        let mut data = cc_b(bits);
+        data.resize(data.len().next_multiple_of(2) + 2, LATCH_MIN);
+        let codewords = with_ec(&data, 2);
+        assert_eq!(
+            decode(&pixel_rows(&codewords, 2, 3)).as_deref(),
+            Some("1726123110ABC12")
+        );
+
+        // A misread codeword fails the syndromes rather than misreading.
+        let mut codewords = with_ec(&cc_a(expiry_bits(26, 12, 31, "ABC12"), 12), 1);
+        codewords[5] = (codewords[5] + 1) % 929;
+        assert_eq!(decode(&pixel_rows(&codewords, 4, 2)), None);
+    }
+
+    #[test]
+    fn byte_compaction_unpacks_groups_and_single_bytes() {
+        for len in [1, 5, 6, 7, 11, 12] {
+            let bits: Vec<bool> = (0..len * 8).map(|i| i % 3 == 0).collect();
+            let data = cc_b(bits.clone());
+            let [CC_B_MODE, latch, bytes @ ..] = data.as_slice() else {
+                unreachable!()
+            };
+            assert_eq!(cc_b_bits(*latch, bytes), Some(bits), "{len}");
+        }
+    }
+}
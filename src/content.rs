@@ -0,0 +1,835 @@
+use std::cell::Cell;
+
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::JsValue;
+
+const SHC_PREFIX: &str = "shc:/";
+const SHC_CHAR_OFFSET: u8 = 45;
+const EMV_CRC_TAG: &str = "6304";
+
+thread_local! {
+    static CONTENT_PARSING: Cell<bool> = const { Cell::new(false) };
+}
+
+pub enum Field {
+    Text(String),
+    Bool(bool),
+    List(Vec<String>),
+    Map(Vec<(String, String)>),
+}
+
+/// The classified content of a decoded value.
+pub struct Content {
+    pub content_type: &'static str,
+    pub fields: Vec<(&'static str, Field)>,
+}
+
+impl Content {
+    fn new(content_type: &'static str) -> Self {
+        Self {
+            content_type,
+            fields: Vec::new(),
+        }
+    }
+
+    fn text(mut self, key: &'static str, value: impl Into<String>) -> Self {
+        self.fields.push((key, Field::Text(value.into())));
+        self
+    }
+
+    fn opt_text(self, key: &'static str, value: Option<String>) -> Self {
+        match value {
+            Some(v) if !v.is_empty() => self.text(key, v),
+            _ => self,
+        }
+    }
+
+    fn field(mut self, key: &'static str, value: Field) -> Self {
+        self.fields.push((key, value));
+        self
+    }
+
+    /// Sets `contentType` and `content` on `target`.
+    pub fn attach_to(&self, target: &Object) {
+        let _ = Reflect::set(
+            target,
+            &JsValue::from_str("contentType"),
+            &JsValue::from_str(self.content_type),
+        );
+        let _ = Reflect::set(target, &JsValue::from_str("content"), &self.fields_to_js());
+    }
+
+    fn fields_to_js(&self) -> Object {
+        let obj = Object::new();
+        for (key, field) in &self.fields {
+            let value: JsValue = match field {
+                Field::Text(s) => JsValue::from_str(s),
+                Field::Bool(b) => JsValue::from_bool(*b),
+                Field::List(items) => items
+                    .iter()
+                    .map(|s| JsValue::from_str(s))
+                    .collect::<Array>()
+                    .into(),
+                Field::Map(entries) => {
+                    let map = Object::new();
+                    for (k, v) in entries {
+                        let _ = Reflect::set(&map, &JsValue::from_str(k), &JsValue::from_str(v));
+                    }
+                    map.into()
+                }
+            };
+            let _ = Reflect::set(&obj, &JsValue::from_str(key), &value);
+        }
+        obj
+    }
+}
+
+pub fn set_content_parsing(enabled: bool) {
+    CONTENT_PARSING.with(|c| c.set(enabled));
+}
+
+pub fn content_parsing_enabled() -> bool {
+    CONTENT_PARSING.with(|c| c.get())
+}
+
+fn strip_prefix_ci<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = value.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &value[prefix.len()..])
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn query_params(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((k, v)) => (k.to_ascii_lowercase(), percent_decode(v)),
+            None => (pair.to_ascii_lowercase(), String::new()),
+        })
+        .collect()
+}
+
+fn param(params: &[(String, String)], key: &str) -> Option<String> {
+    params
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.clone())
+}
+
+/// Splits `MECARD:`/`WIFI:`/`MATMSG:` style `KEY:value;` records, honouring
+/// backslash escapes.
+fn split_records(body: &str) -> Vec<(String, String)> {
+    let mut records = Vec::new();
+    let mut current = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    current.push('\\');
+                    current.push(next);
+                }
+            }
+            ';' => {
+                if !current.is_empty() {
+                    records.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        records.push(current);
+    }
+
+    records
+        .into_iter()
+        .filter_map(|record| {
+            let (key, value) = split_unescaped(&record, ':')?;
+            Some((key.to_ascii_uppercase(), unescape(value)))
+        })
+        .collect()
+}
+
+fn split_unescaped(value: &str, sep: char) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            c if c == sep && !escaped => return Some((&value[..i], &value[i + 1..])),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                out.push(next);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn record(records: &[(String, String)], key: &str) -> Option<String> {
+    records
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.clone())
+}
+
+fn records(records: &[(String, String)], key: &str) -> Vec<String> {
+    records
+        .iter()
+        .filter(|(k, _)| k == key)
+        .map(|(_, v)| v.clone())
+        .collect()
+}
+
+fn parse_url(value: &str) -> Option<Content> {
+    let (scheme, rest) = value.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+
+    let host_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let host = &rest[..host_end];
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some(
+        Content::new("url")
+            .text("url", value)
+            .text("scheme", scheme.to_ascii_lowercase())
+            .text("host", host)
+            .opt_text("path", Some(rest[host_end..].to_string())),
+    )
+}
+
+fn parse_wifi(value: &str) -> Option<Content> {
+    let body = strip_prefix_ci(value, "WIFI:")?;
+    let recs = split_records(body);
+    let ssid = record(&recs, "S")?;
+
+    let content = Content::new("wifi")
+        .text("ssid", ssid)
+        .opt_text("encryption", record(&recs, "T"))
+        .opt_text("password", record(&recs, "P"));
+
+    Some(match record(&recs, "H") {
+        Some(h) => content.field("hidden", Field::Bool(h.eq_ignore_ascii_case("true"))),
+        None => content,
+    })
+}
+
+fn parse_mecard(value: &str) -> Option<Content> {
+    let body = strip_prefix_ci(value, "MECARD:")?;
+    let recs = split_records(body);
+
+    let name = record(&recs, "N").map(|n| match n.split_once(',') {
+        Some((last, first)) => format!("{} {}", first.trim(), last.trim()),
+        None => n,
+    });
+
+    Some(
+        Content::new("contact")
+            .opt_text("name", name)
+            .field("phones", Field::List(records(&recs, "TEL")))
+            .field("emails", Field::List(records(&recs, "EMAIL")))
+            .opt_text("organization", record(&recs, "ORG"))
+            .opt_text("address", record(&recs, "ADR"))
+            .opt_text("url", record(&recs, "URL"))
+            .opt_text("note", record(&recs, "NOTE")),
+    )
+}
+
+fn parse_vcard(value: &str) -> Option<Content> {
+    let trimmed = value.trim_start();
+    strip_prefix_ci(trimmed, "BEGIN:VCARD")?;
+
+    // Unfold continuation lines (RFC 6350 section 3.2).
+    let mut lines: Vec<String> = Vec::new();
+    for line in trimmed.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(cont), Some(last)) => last.push_str(cont),
+            _ => lines.push(line.trim_end_matches('\r').to_string()),
+        }
+    }
+
+    let mut props: Vec<(String, String)> = Vec::new();
+    for line in &lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name.split(';').next().unwrap_or(name);
+        // Drop any `group.` prefix, e.g. `item1.EMAIL`.
+        let name = name.rsplit('.').next().unwrap_or(name);
+        props.push((name.to_ascii_uppercase(), unescape(value)));
+    }
+
+    let name = record(&props, "FN").or_else(|| {
+        record(&props, "N").map(|n| {
+            let mut parts = n.split(';');
+            let last = parts.next().unwrap_or_default();
+            let first = parts.next().unwrap_or_default();
+            format!("{first} {last}").trim().to_string()
+        })
+    });
+
+    Some(
+        Content::new("contact")
+            .opt_text("name", name)
+            .field("phones", Field::List(records(&props, "TEL")))
+            .field("emails", Field::List(records(&props, "EMAIL")))
+            .opt_text("organization", record(&props, "ORG"))
+            .opt_text("title", record(&props, "TITLE"))
+            .opt_text(
+                "address",
+                record(&props, "ADR").map(|a| {
+                    a.split(';')
+                        .map(str::trim)
+                        .filter(|p| !p.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }),
+            )
+            .opt_text("url", record(&props, "URL"))
+            .opt_text("note", record(&props, "NOTE")),
+    )
+}
+
+fn parse_geo(value: &str) -> Option<Content> {
+    let body = strip_prefix_ci(value, "geo:")?;
+    let (coords, query) = match body.split_once('?') {
+        Some((c, q)) => (c, Some(q)),
+        None => (body, None),
+    };
+    // Coordinate reference system parameters such as `;crs=wgs84` are ignored.
+    let coords = coords.split(';').next().unwrap_or(coords);
+
+    let mut parts = coords.split(',');
+    let latitude: f64 = parts.next()?.trim().parse().ok()?;
+    let longitude: f64 = parts.next()?.trim().parse().ok()?;
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return None;
+    }
+    let altitude = parts.next().map(|a| a.trim().to_string());
+
+    Some(
+        Content::new("geo")
+            .text("latitude", latitude.to_string())
+            .text("longitude", longitude.to_string())
+            .opt_text("altitude", altitude)
+            .opt_text("query", query.and_then(|q| param(&query_params(q), "q"))),
+    )
+}
+
+fn parse_mailto(value: &str) -> Option<Content> {
+    let body = strip_prefix_ci(value, "mailto:")?;
+    let (to, query) = match body.split_once('?') {
+        Some((t, q)) => (t, q),
+        None => (body, ""),
+    };
+    let params = query_params(query);
+
+    Some(
+        Content::new("email")
+            .text("to", percent_decode(to))
+            .opt_text("cc", param(&params, "cc"))
+            .opt_text("subject", param(&params, "subject"))
+            .opt_text("body", param(&params, "body")),
+    )
+}
+
+fn parse_matmsg(value: &str) -> Option<Content> {
+    let body = strip_prefix_ci(value, "MATMSG:")?;
+    let recs = split_records(body);
+
+    Some(
+        Content::new("email")
+            .text("to", record(&recs, "TO")?)
+            .opt_text("subject", record(&recs, "SUB"))
+            .opt_text("body", record(&recs, "BODY")),
+    )
+}
+
+fn parse_tel(value: &str) -> Option<Content> {
+    let number = strip_prefix_ci(value, "tel:")?;
+    if number.is_empty() {
+        return None;
+    }
+
+    Some(Content::new("phone").text("number", percent_decode(number)))
+}
+
+fn parse_sms(value: &str) -> Option<Content> {
+    if let Some(body) =
+        strip_prefix_ci(value, "smsto:").or_else(|| strip_prefix_ci(value, "mmsto:"))
+    {
+        let (number, message) = match body.split_once(':') {
+            Some((n, m)) => (n, Some(m.to_string())),
+            None => (body, None),
+        };
+
+        return Some(
+            Content::new("sms")
+                .text("number", number)
+                .opt_text("message", message),
+        );
+    }
+
+    let body = strip_prefix_ci(value, "sms:").or_else(|| strip_prefix_ci(value, "mms:"))?;
+    let (number, query) = match body.split_once('?') {
+        Some((n, q)) => (n, q),
+        None => (body, ""),
+    };
+
+    Some(
+        Content::new("sms")
+            .text("number", percent_decode(number))
+            .opt_text("message", param(&query_params(query), "body")),
+    )
+}
+
+/// CRC-16/CCITT-FALSE, as required by the EMVCo merchant-presented QR spec.
+fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Splits an EMVCo `ID (2) | length (2) | value` sequence. Lengths count
+/// characters, not bytes.
+fn parse_tlv(data: &str) -> Option<Vec<(String, String)>> {
+    let chars: Vec<char> = data.chars().collect();
+    let mut items = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let header: String = chars.get(pos..pos + 4)?.iter().collect();
+        // IDs and lengths are two ASCII digits each, so the header can be
+        // sliced by byte.
+        if !header.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let len: usize = header[2..].parse().ok()?;
+        let value: String = chars.get(pos + 4..pos + 4 + len)?.iter().collect();
+        items.push((header[..2].to_string(), value));
+        pos += 4 + len;
+    }
+    Some(items)
+}
+
+fn parse_emv(value: &str) -> Option<Content> {
+    if !value.starts_with("000201") {
+        return None;
+    }
+    let items = parse_tlv(value)?;
+
+    let crc_valid = match value.rfind(EMV_CRC_TAG) {
+        Some(pos) if pos + EMV_CRC_TAG.len() + 4 == value.len() => {
+            let expected = &value[pos + EMV_CRC_TAG.len()..];
+            let actual = crc16_ccitt(&value.as_bytes()[..pos + EMV_CRC_TAG.len()]);
+            u16::from_str_radix(expected, 16).is_ok_and(|e| e == actual)
+        }
+        _ => false,
+    };
+
+    let get = |id: &str| record(&items, id);
+    let merchant_accounts: Vec<(String, String)> = items
+        .iter()
+        .filter(|(id, _)| id.parse::<u8>().is_ok_and(|n| (2..=51).contains(&n)))
+        .cloned()
+        .collect();
+    let initiation = get("01").map(|v| match v.as_str() {
+        "11" => "static".to_string(),
+        "12" => "dynamic".to_string(),
+        _ => v,
+    });
+
+    Some(
+        Content::new("emv_payment")
+            .opt_text("payloadFormatIndicator", get("00"))
+            .opt_text("pointOfInitiation", initiation)
+            .field("merchantAccounts", Field::Map(merchant_accounts))
+            .opt_text("merchantCategoryCode", get("52"))
+            .opt_text("currency", get("53"))
+            .opt_text("amount", get("54"))
+            .opt_text("countryCode", get("58"))
+            .opt_text("merchantName", get("59"))
+            .opt_text("merchantCity", get("60"))
+            .opt_text("postalCode", get("61"))
+            .field("crcValid", Field::Bool(crc_valid)),
+    )
+}
+
+/// Decodes the `shc:/` numeric encoding, where every two digits encode one
+/// character of the JWS as `value + 45`.
+pub fn decode_shc_numeric(digits: &str) -> Option<String> {
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return None;
+    }
+
+    digits
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let [tens @ b'0'..=b'9', ones @ b'0'..=b'9'] = *pair else {
+                return None;
+            };
+            let n = (tens - b'0') * 10 + (ones - b'0');
+            (n <= b'z' - SHC_CHAR_OFFSET).then(|| char::from(n + SHC_CHAR_OFFSET))
+        })
+        .collect()
+}
+
+/// Splits a `shc:/` value into `(chunk index, chunk count, numeric data)`.
+/// Single QR cards report `1 of 1`.
+pub fn split_shc(value: &str) -> Option<(u32, u32, &str)> {
+    let body = strip_prefix_ci(value, SHC_PREFIX)?;
+    let parts: Vec<&str> = body.split('/').collect();
+
+    match parts.as_slice() {
+        [data] => Some((1, 1, data)),
+        [index, count, data] => {
+            let number = |text: &str| -> Option<u32> {
+                text.bytes()
+                    .all(|c| c.is_ascii_digit())
+                    .then(|| text.parse().ok())?
+            };
+            let (index, count) = (number(index)?, number(count)?);
+            (index >= 1 && index <= count).then_some((index, count, *data))
+        }
+        _ => None,
+    }
+}
+
+fn parse_shc(value: &str) -> Option<Content> {
+    let (index, count, data) = split_shc(value)?;
+    let decoded = decode_shc_numeric(data)?;
+
+    let content = Content::new("smart_health_card")
+        .text("chunkIndex", index.to_string())
+        .text("chunkCount", count.to_string());
+
+    Some(if count == 1 {
+        content.text("jws", decoded)
+    } else {
+        content.text("jwsChunk", decoded)
+    })
+}
+
+/// Classifies a decoded value and extracts its structured fields.
+///
+/// Returns `None` for values that are not one of the recognized content types.
+pub fn classify(value: &str) -> Option<Content> {
+    parse_shc(value)
+        .or_else(|| parse_url(value))
+        .or_else(|| parse_wifi(value))
+        .or_else(|| parse_mecard(value))
+        .or_else(|| parse_vcard(value))
+        .or_else(|| parse_geo(value))
+        .or_else(|| parse_mailto(value))
+        .or_else(|| parse_matmsg(value))
+        .or_else(|| parse_tel(value))
+        .or_else(|| parse_sms(value))
+        .or_else(|| parse_emv(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text<'a>(content: &'a Content, key: &str) -> Option<&'a str> {
+        content.fields.iter().find_map(|(k, field)| match field {
+            Field::Text(text) if *k == key => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    fn list<'a>(content: &'a Content, key: &str) -> &'a [String] {
+        content
+            .fields
+            .iter()
+            .find_map(|(k, field)| match field {
+                Field::List(items) if *k == key => Some(items.as_slice()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn flag(content: &Content, key: &str) -> Option<bool> {
+        content.fields.iter().find_map(|(k, field)| match field {
+            Field::Bool(b) if *k == key => Some(*b),
+            _ => None,
+        })
+    }
+
+    fn classified(value: &str) -> Content {
+        classify(value).unwrap_or_else(|| panic!("{value} was not classified"))
+    }
+
+    #[test]
+    fn urls_need_an_http_scheme_and_a_host() {
+        let content = classified("HTTPS://example.com/päth?q=1#top");
+        assert_eq!(content.content_type, "url");
+        assert_eq!(text(&content, "scheme"), Some("https"));
+        assert_eq!(text(&content, "host"), Some("example.com"));
+        assert_eq!(text(&content, "path"), Some("/päth?q=1#top"));
+        assert_eq!(text(&classified("http://example.com"), "path"), None);
+
+        for value in [
+            "ftp://example.com",
+            "https://",
+            "https:// example.com",
+            "example.com",
+        ] {
+            assert!(classify(value).is_none(), "{value}");
+        }
+    }
+
+    #[test]
+    fn wifi_records_honour_escapes() {
+        let content = classified(r#"WIFI:T:WPA;S:My\;Net\:é;P:pa\;ss;H:true;;"#);
+        assert_eq!(content.content_type, "wifi");
+        assert_eq!(text(&content, "ssid"), Some("My;Net:é"));
+        assert_eq!(text(&content, "encryption"), Some("WPA"));
+        assert_eq!(text(&content, "password"), Some("pa;ss"));
+        assert_eq!(flag(&content, "hidden"), Some(true));
+
+        // The SSID is required.
+        assert!(classify("WIFI:T:WPA;P:secret;;").is_none());
+    }
+
+    #[test]
+    fn mecards_become_contacts() {
+        let content = classified(
+            "MECARD:N:Doe,Jane;TEL:+15551234;TEL:+15555678;EMAIL:jane@example.com;ORG:Ünïcode;;",
+        );
+        assert_eq!(content.content_type, "contact");
+        assert_eq!(text(&content, "name"), Some("Jane Doe"));
+        assert_eq!(list(&content, "phones"), ["+15551234", "+15555678"]);
+        assert_eq!(list(&content, "emails"), ["jane@example.com"]);
+        assert_eq!(text(&content, "organization"), Some("Ünïcode"));
+    }
+
+    #[test]
+    fn vcards_unfold_lines_and_drop_groups() {
+        let content = classified(
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nN:Doe;Jane;;;\r\nitem1.EMAIL;TYPE=work:jane@exa\r\n mple.com\r\n\
+             TEL;TYPE=cell:+15551234\r\nADR:;;1 Main St;Springfield;;;USA\r\nNOTE:a\\, b\r\nEND:VCARD",
+        );
+        assert_eq!(content.content_type, "contact");
+        assert_eq!(text(&content, "name"), Some("Jane Doe"));
+        assert_eq!(list(&content, "emails"), ["jane@example.com"]);
+        assert_eq!(list(&content, "phones"), ["+15551234"]);
+        assert_eq!(
+            text(&content, "address"),
+            Some("1 Main St, Springfield, USA")
+        );
+        assert_eq!(text(&content, "note"), Some("a, b"));
+
+        let content = classified("BEGIN:VCARD\nFN:Dr. Jane Doe\nN:Doe;Jane\nEND:VCARD");
+        assert_eq!(text(&content, "name"), Some("Dr. Jane Doe"));
+    }
+
+    #[test]
+    fn geo_coordinates_are_range_checked() {
+        let content = classified("geo:48.2010,16.3695,183;crs=wgs84?q=Wien%20Mitte");
+        assert_eq!(content.content_type, "geo");
+        assert_eq!(text(&content, "latitude"), Some("48.201"));
+        assert_eq!(text(&content, "longitude"), Some("16.3695"));
+        assert_eq!(text(&content, "altitude"), Some("183"));
+        assert_eq!(text(&content, "query"), Some("Wien Mitte"));
+
+        for value in [
+            "geo:91,0",
+            "geo:0,181",
+            "geo:north,east",
+            "geo:48.2",
+            "geo:",
+        ] {
+            assert!(classify(value).is_none(), "{value}");
+        }
+    }
+
+    #[test]
+    fn email_links_and_messages() {
+        let content =
+            classified("mailto:a%40example.com?Subject=Hi%20there&cc=b@example.com&body=%E2%9C%93");
+        assert_eq!(content.content_type, "email");
+        assert_eq!(text(&content, "to"), Some("a@example.com"));
+        assert_eq!(text(&content, "cc"), Some("b@example.com"));
+        assert_eq!(text(&content, "subject"), Some("Hi there"));
+        assert_eq!(text(&content, "body"), Some("✓"));
+
+        let content = classified("MATMSG:TO:a@example.com;SUB:Hi;BODY:Grüße;;");
+        assert_eq!(text(&content, "to"), Some("a@example.com"));
+        assert_eq!(text(&content, "body"), Some("Grüße"));
+        assert!(classify("MATMSG:SUB:Hi;;").is_none());
+    }
+
+    #[test]
+    fn phone_numbers_and_messages() {
+        let content = classified("tel:+1%20555%201234");
+        assert_eq!(content.content_type, "phone");
+        assert_eq!(text(&content, "number"), Some("+1 555 1234"));
+        assert!(classify("tel:").is_none());
+
+        let content = classified("SMSTO:+15551234:Hello: world");
+        assert_eq!(content.content_type, "sms");
+        assert_eq!(text(&content, "number"), Some("+15551234"));
+        assert_eq!(text(&content, "message"), Some("Hello: world"));
+
+        let content = classified("sms:+15551234?body=%C3%A9t%C3%A9");
+        assert_eq!(text(&content, "number"), Some("+15551234"));
+        assert_eq!(text(&content, "message"), Some("été"));
+    }
+
+    #[test]
+    fn crc_matches_the_ccitt_false_check_value() {
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29b1);
+    }
+
+    /// An EMVCo payload with a valid CRC over everything before it.
+    fn emv(body: &str) -> String {
+        let data = format!("{body}{EMV_CRC_TAG}");
+        format!("{data}{:04X}", crc16_ccitt(data.as_bytes()))
+    }
+
+    #[test]
+    fn emv_payloads_are_split_into_fields() {
+        let value = emv("000201010212\
+             26290012com.example01101234567890\
+             52045812530397854041.505802AT5911Café Müller6004Wien");
+        let content = classified(&value);
+        assert_eq!(content.content_type, "emv_payment");
+        assert_eq!(text(&content, "payloadFormatIndicator"), Some("01"));
+        assert_eq!(text(&content, "pointOfInitiation"), Some("dynamic"));
+        assert_eq!(text(&content, "merchantCategoryCode"), Some("5812"));
+        assert_eq!(text(&content, "currency"), Some("978"));
+        assert_eq!(text(&content, "amount"), Some("1.50"));
+        assert_eq!(text(&content, "countryCode"), Some("AT"));
+        // Lengths count characters, not UTF-8 bytes.
+        assert_eq!(text(&content, "merchantName"), Some("Café Müller"));
+        assert_eq!(text(&content, "merchantCity"), Some("Wien"));
+        assert_eq!(flag(&content, "crcValid"), Some(true));
+        assert!(content.fields.iter().any(|(k, field)| *k == "merchantAccounts"
+            && matches!(field, Field::Map(entries) if entries == &[("26".to_string(), "0012com.example01101234567890".to_string())])));
+
+        let tampered = value.replace("1.50", "9.50");
+        assert_eq!(flag(&classified(&tampered), "crcValid"), Some(false));
+    }
+
+    #[test]
+    fn malformed_emv_payloads_are_not_classified() {
+        for value in [
+            "000201aé12xxxx",
+            "000201é",
+            "0002015",
+            "00020101",
+            "000201010+12",
+            "000201-1xx",
+            "0002\u{1d}1",
+        ] {
+            assert!(classify(value).is_none(), "{value}");
+        }
+    }
+
+    #[test]
+    fn smart_health_cards_report_their_chunks() {
+        let content = classified("shc:/5677");
+        assert_eq!(content.content_type, "smart_health_card");
+        assert_eq!(text(&content, "chunkIndex"), Some("1"));
+        assert_eq!(text(&content, "jws"), Some("ez"));
+
+        let content = classified("SHC:/2/3/5677");
+        assert_eq!(text(&content, "chunkCount"), Some("3"));
+        assert_eq!(text(&content, "jwsChunk"), Some("ez"));
+
+        for value in ["shc:/567", "shc:/4/3/5677", "shc:/1/3/5é", "shc:/0/0/5677"] {
+            assert!(classify(value).is_none(), "{value}");
+        }
+    }
+
+    #[test]
+    fn non_ascii_input_never_panics() {
+        let prefixes = [
+            "",
+            "000201",
+            "00020101",
+            "shc:/",
+            "shc:/1/",
+            "http://",
+            "WIFI:",
+            "WIFI:S:",
+            "MECARD:",
+            "BEGIN:VCARD",
+            "geo:",
+            "geo:1,",
+            "mailto:",
+            "mailto:a?",
+            "MATMSG:",
+            "tel:",
+            "sms:",
+            "smsto:",
+            "%",
+            "%e",
+        ];
+        let tails = [
+            "é",
+            "aé12xxxx",
+            "日本語",
+            "\\é",
+            "%é",
+            "é:é;é",
+            "\u{1f600}",
+            "\r\n é",
+        ];
+        for prefix in prefixes {
+            for tail in tails {
+                let _ = classify(&format!("{prefix}{tail}"));
+                let _ = classify(&format!("{tail}{prefix}"));
+            }
+        }
+    }
+}
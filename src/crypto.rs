@@ -0,0 +1,442 @@
+use p256::ecdsa::{Signature, VerifyingKey, signature::Verifier};
+use rsa::{BigUint, Pss, RsaPublicKey};
+use sha2::{Digest, Sha256};
+
+use crate::error::Error;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// DER encodings of the object identifiers for public key algorithms.
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_RSASSA_PSS: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0a];
+
+const DER_INTEGER: u8 = 0x02;
+const DER_BIT_STRING: u8 = 0x03;
+const DER_OID: u8 = 0x06;
+const DER_SEQUENCE: u8 = 0x30;
+
+/// Encodes bytes as standard base64 with padding.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, b)| acc | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes standard base64 (RFC 4648, section 4). Padding is optional but
+/// must complete the last group when present. Whitespace is ignored so that
+/// PEM bodies can be passed as they are.
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text: Vec<u8> = text.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    let padding = text.iter().rev().take_while(|c| **c == b'=').count();
+    if padding > 0 && (padding > 2 || !text.len().is_multiple_of(4)) {
+        return None;
+    }
+    decode_base64(&text[..text.len() - padding], b'+', b'/')
+}
+
+/// Decodes unpadded base64url (RFC 4648, section 5), as used by JWS and JWK.
+pub fn base64url_decode(text: &str) -> Option<Vec<u8>> {
+    decode_base64(text.as_bytes(), b'-', b'_')
+}
+
+/// Decodes base64 with the given characters for 62 and 63. Characters of the
+/// other alphabet are rejected rather than mixed in.
+fn decode_base64(text: &[u8], c62: u8, c63: u8) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut acc, mut bits) = (0u32, 0);
+    for &c in text {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            _ if c == c62 => 62,
+            _ if c == c63 => 63,
+            _ => return None,
+        };
+        acc = (acc << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    // A single leftover character cannot encode a whole byte, and the bits
+    // left over by a canonical encoder are zero.
+    (bits < 6 && acc & ((1 << bits) - 1) == 0).then_some(out)
+}
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// Reads the DER element at the start of `data`, returning its tag, its
+/// contents and the remaining input.
+fn der_element(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, &rest[count..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// Reads a DER element that must have the given tag.
+fn der_expect(data: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    der_element(data).and_then(|(t, contents, rest)| (t == tag).then_some((contents, rest)))
+}
+
+/// A public key from a trust list.
+pub enum PublicKey {
+    P256(VerifyingKey),
+    Rsa(RsaPublicKey),
+}
+
+/// Signature algorithms used by COSE and JWS health credentials.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// ECDSA on P-256 with SHA-256, with the signature as `r || s`.
+    Es256,
+    /// RSASSA-PSS with SHA-256 and a 32-byte salt.
+    Ps256,
+}
+
+impl PublicKey {
+    /// Parses a `SubjectPublicKeyInfo` or an X.509 certificate, as PEM or as
+    /// base64-encoded DER.
+    pub fn from_pem_or_base64(text: &str) -> Result<(Self, Option<Vec<u8>>), Error> {
+        let body: String = text
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("-----"))
+            .collect();
+        let der = base64_decode(&body).ok_or(Error::InvalidOptions)?;
+
+        // A certificate's first element is the to-be-signed certificate,
+        // itself a sequence; a SubjectPublicKeyInfo starts with the
+        // algorithm identifier, whose first element is an OID.
+        let (outer, _) = der_expect(&der, DER_SEQUENCE).ok_or(Error::InvalidOptions)?;
+        let (first, _) = der_expect(outer, DER_SEQUENCE).ok_or(Error::InvalidOptions)?;
+        if der_expect(first, DER_OID).is_some() {
+            return Ok((Self::from_spki(&der)?, None));
+        }
+
+        let spki = certificate_spki(first).ok_or(Error::InvalidOptions)?;
+        Ok((Self::from_spki(spki)?, Some(der)))
+    }
+
+    /// Parses a DER-encoded `SubjectPublicKeyInfo` holding a P-256 or RSA key.
+    pub fn from_spki(der: &[u8]) -> Result<Self, Error> {
+        let parse = || {
+            let (spki, _) = der_expect(der, DER_SEQUENCE)?;
+            let (algorithm, rest) = der_expect(spki, DER_SEQUENCE)?;
+            let (key_bits, _) = der_expect(rest, DER_BIT_STRING)?;
+            // The first byte of a bit string counts its unused bits.
+            let key = key_bits.strip_prefix(&[0])?;
+            let (oid, params) = der_expect(algorithm, DER_OID)?;
+
+            if oid == OID_EC_PUBLIC_KEY {
+                let (curve, _) = der_expect(params, DER_OID)?;
+                if curve != OID_PRIME256V1 {
+                    return None;
+                }
+                return VerifyingKey::from_sec1_bytes(key).ok().map(PublicKey::P256);
+            }
+
+            if oid == OID_RSA_ENCRYPTION || oid == OID_RSASSA_PSS {
+                let (rsa_key, _) = der_expect(key, DER_SEQUENCE)?;
+                let (modulus, rest) = der_expect(rsa_key, DER_INTEGER)?;
+                let (exponent, _) = der_expect(rest, DER_INTEGER)?;
+                return RsaPublicKey::new(
+                    BigUint::from_bytes_be(modulus),
+                    BigUint::from_bytes_be(exponent),
+                )
+                .ok()
+                .map(PublicKey::Rsa);
+            }
+
+            None
+        };
+
+        parse().ok_or(Error::InvalidOptions)
+    }
+
+    /// Builds a P-256 key from the big-endian affine coordinates of a JWK.
+    pub fn from_p256_coordinates(x: &[u8], y: &[u8]) -> Result<Self, Error> {
+        if x.len() != 32 || y.len() != 32 {
+            return Err(Error::InvalidOptions);
+        }
+        let mut point = Vec::with_capacity(65);
+        point.push(0x04);
+        point.extend_from_slice(x);
+        point.extend_from_slice(y);
+        VerifyingKey::from_sec1_bytes(&point)
+            .map(PublicKey::P256)
+            .map_err(|_| Error::InvalidOptions)
+    }
+
+    /// Returns true if `signature` is a valid signature of `message`.
+    pub fn verify(&self, algorithm: Algorithm, message: &[u8], signature: &[u8]) -> bool {
+        match (self, algorithm) {
+            (PublicKey::P256(key), Algorithm::Es256) => Signature::from_slice(signature)
+                .is_ok_and(|signature| key.verify(message, &signature).is_ok()),
+            (PublicKey::Rsa(key), Algorithm::Ps256) => key
+                .verify(Pss::new::<Sha256>(), &sha256(message), signature)
+                .is_ok(),
+            _ => false,
+        }
+    }
+}
+
+/// Returns the `SubjectPublicKeyInfo` of a to-be-signed certificate.
+fn certificate_spki(tbs: &[u8]) -> Option<&[u8]> {
+    let mut rest = tbs;
+    // The version is an optional explicitly tagged [0] element.
+    if let Some((0xa0, _, after)) = der_element(rest) {
+        rest = after;
+    }
+    // Serial number, signature algorithm, issuer, validity and subject.
+    for _ in 0..5 {
+        rest = der_element(rest)?.2;
+    }
+    let (_, contents, after) = der_element(rest)?;
+    let header_len = rest.len() - after.len() - contents.len();
+    Some(&rest[..header_len + contents.len()])
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use p256::ecdsa::{SigningKey, signature::Signer};
+
+    use super::*;
+
+    /// A fixed P-256 key, so that signatures are reproducible.
+    pub(crate) fn signing_key() -> SigningKey {
+        SigningKey::from_slice(&sha256(b"wascan test key")).unwrap()
+    }
+
+    /// Signs `message` with ES256, returning `r || s`.
+    pub(crate) fn es256(key: &SigningKey, message: &[u8]) -> Vec<u8> {
+        let signature: Signature = key.sign(message);
+        signature.to_bytes().to_vec()
+    }
+
+    /// The uncompressed SEC1 point of a key.
+    pub(crate) fn sec1_point(key: &SigningKey) -> Vec<u8> {
+        key.verifying_key()
+            .to_encoded_point(false)
+            .as_bytes()
+            .to_vec()
+    }
+
+    /// Encodes a DER element with a definite length.
+    pub(crate) fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        match contents.len() {
+            len @ 0..0x80 => out.push(len as u8),
+            len @ 0x80..0x100 => out.extend([0x81, len as u8]),
+            len => {
+                out.push(0x82);
+                out.extend((len as u16).to_be_bytes());
+            }
+        }
+        out.extend_from_slice(contents);
+        out
+    }
+
+    pub(crate) fn spki(key: &SigningKey) -> Vec<u8> {
+        let algorithm = [
+            der(DER_OID, OID_EC_PUBLIC_KEY),
+            der(DER_OID, OID_PRIME256V1),
+        ]
+        .concat();
+        let key_bits = [&[0][..], &sec1_point(key)].concat();
+        der(
+            DER_SEQUENCE,
+            &[
+                der(DER_SEQUENCE, &algorithm),
+                der(DER_BIT_STRING, &key_bits),
+            ]
+            .concat(),
+        )
+    }
+
+    /// A self-signed X.509 v3 certificate for the key. The signature is not
+    /// checked by the parser and is left empty.
+    pub(crate) fn certificate(key: &SigningKey) -> Vec<u8> {
+        let name = der(DER_SEQUENCE, &der(0x31, &der(DER_SEQUENCE, b"")));
+        let ecdsa_with_sha256 = der(
+            DER_SEQUENCE,
+            &der(DER_OID, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02]),
+        );
+        let tbs = [
+            der(0xa0, &der(DER_INTEGER, &[2])),
+            der(DER_INTEGER, &[0x01, 0x23]),
+            ecdsa_with_sha256.clone(),
+            name.clone(),
+            der(
+                DER_SEQUENCE,
+                &[der(0x17, b"260101000000Z"), der(0x17, b"270101000000Z")].concat(),
+            ),
+            name,
+            spki(key),
+        ]
+        .concat();
+        der(
+            DER_SEQUENCE,
+            &[
+                der(DER_SEQUENCE, &tbs),
+                ecdsa_with_sha256,
+                der(DER_BIT_STRING, &[0]),
+            ]
+            .concat(),
+        )
+    }
+
+    pub(crate) fn pem(label: &str, der: &[u8]) -> String {
+        let body = base64_encode(der);
+        let lines: Vec<&str> = body
+            .as_bytes()
+            .chunks(64)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect();
+        format!(
+            "-----BEGIN {label}-----\n{}\n-----END {label}-----\n",
+            lines.join("\n")
+        )
+    }
+
+    #[test]
+    fn base64_round_trips_the_rfc_4648_vectors() {
+        for (bytes, text) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64_encode(bytes.as_bytes()), text);
+            assert_eq!(base64_decode(text).unwrap(), bytes.as_bytes());
+            assert_eq!(
+                base64_decode(text.trim_end_matches('=')).unwrap(),
+                bytes.as_bytes()
+            );
+            assert_eq!(
+                base64url_decode(text.trim_end_matches('=')).unwrap(),
+                bytes.as_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn base64_alphabets_are_not_mixed() {
+        let bytes = [0xfb, 0xff, 0xbf];
+        assert_eq!(base64_decode("+/+/").unwrap(), bytes);
+        assert_eq!(base64url_decode("-_-_").unwrap(), bytes);
+
+        assert_eq!(base64_decode("-_-_"), None);
+        assert_eq!(base64_decode("+/-_"), None);
+        assert_eq!(base64url_decode("+/+/"), None);
+        assert_eq!(base64url_decode("-_+/"), None);
+    }
+
+    #[test]
+    fn base64_rejects_malformed_input() {
+        // Padding that does not complete a group, or more than two pads.
+        assert_eq!(base64_decode("Zg="), None);
+        assert_eq!(base64_decode("Zg==="), None);
+        assert_eq!(base64_decode("Z==="), None);
+        // Padding is not part of base64url.
+        assert_eq!(base64url_decode("Zg=="), None);
+        // A lone character, and leftover bits a canonical encoder clears.
+        assert_eq!(base64_decode("Zm9vY"), None);
+        assert_eq!(base64_decode("Zh=="), None);
+        assert_eq!(base64url_decode("Zm9"), None);
+        // Padding inside the text.
+        assert_eq!(base64_decode("Zg==Zm8="), None);
+    }
+
+    #[test]
+    fn base64_ignores_whitespace_in_pem_bodies() {
+        assert_eq!(base64_decode(" Zm9v\r\nYmFy\n").unwrap(), b"foobar");
+        assert_eq!(base64_decode("Zm9vYg\n==\n").unwrap(), b"foob");
+        assert_eq!(base64url_decode("Zm9v YmFy"), None);
+    }
+
+    #[test]
+    fn keys_are_read_from_spki_and_certificates() {
+        let key = signing_key();
+        let signature = es256(&key, b"message");
+
+        let (spki_key, der) =
+            PublicKey::from_pem_or_base64(&pem("PUBLIC KEY", &spki(&key))).unwrap();
+        assert!(der.is_none());
+        assert!(spki_key.verify(Algorithm::Es256, b"message", &signature));
+
+        let der = super::tests::certificate(&key);
+        let (cert_key, certificate) = PublicKey::from_pem_or_base64(&base64_encode(&der)).unwrap();
+        assert_eq!(certificate.unwrap(), der);
+        assert!(cert_key.verify(Algorithm::Es256, b"message", &signature));
+
+        let point = sec1_point(&key);
+        let jwk_key = PublicKey::from_p256_coordinates(&point[1..33], &point[33..]).unwrap();
+        assert!(jwk_key.verify(Algorithm::Es256, b"message", &signature));
+    }
+
+    #[test]
+    fn es256_signatures_must_match_message_key_and_algorithm() {
+        let key = signing_key();
+        let public = PublicKey::from_spki(&spki(&key)).unwrap();
+        let mut signature = es256(&key, b"message");
+
+        assert!(!public.verify(Algorithm::Es256, b"massage", &signature));
+        assert!(!public.verify(Algorithm::Ps256, b"message", &signature));
+        assert!(!public.verify(Algorithm::Es256, b"message", &signature[..63]));
+        signature[10] ^= 1;
+        assert!(!public.verify(Algorithm::Es256, b"message", &signature));
+
+        let other = SigningKey::from_slice(&sha256(b"another key")).unwrap();
+        assert!(!public.verify(Algorithm::Es256, b"message", &es256(&other, b"message")));
+    }
+
+    #[test]
+    fn malformed_keys_are_rejected() {
+        let key = signing_key();
+        let mut spki = spki(&key);
+        assert!(PublicKey::from_spki(&spki[..spki.len() - 1]).is_err());
+        // A point that is not on the curve.
+        let last = spki.len() - 1;
+        spki[last] ^= 1;
+        assert!(PublicKey::from_spki(&spki).is_err());
+
+        assert!(PublicKey::from_pem_or_base64("not base64!").is_err());
+        assert!(PublicKey::from_pem_or_base64(&base64_encode(&der(DER_SEQUENCE, b""))).is_err());
+        assert!(PublicKey::from_p256_coordinates(&[1; 31], &[1; 32]).is_err());
+        // A DER length claiming more than the input.
+        assert!(der_element(&[DER_SEQUENCE, 0x84, 0xff, 0xff, 0xff, 0xff]).is_none());
+    }
+}
@@ -0,0 +1,664 @@
+use std::collections::HashMap;
+
+use rxing::{
+    BarcodeFormat, BinaryBitmap, Luma8LuminanceSource,
+    common::HybridBinarizer,
+    oned::{pattern_match_variance, rss::rss_utils::getRSSvalue},
+};
+
+use crate::{
+    composite,
+    detector::{Detection, Format},
+    pharmacode::{Run, runs},
+    validator,
+};
+
+/// Rows scanned from top to bottom. Stacked symbols put the two halves on
+/// separate rows, so the whole height is covered rather than the middle.
+const SCAN_ROWS: usize = 64;
+/// Scan lines on which a half must be read before it is paired.
+const MIN_HALF_READS: usize = 2;
+
+/// Elements 1 to 4 of the nine finder patterns; element 5 is always one
+/// module wide.
+const FINDER_PATTERNS: [[u32; 4]; 9] = [
+    [3, 8, 2, 1],
+    [3, 5, 5, 1],
+    [3, 3, 7, 1],
+    [3, 1, 9, 1],
+    [2, 7, 4, 1],
+    [2, 5, 6, 1],
+    [2, 3, 8, 1],
+    [1, 5, 7, 1],
+    [1, 3, 9, 1],
+];
+/// Bounds on the share of elements 2 and 3 in elements 2 to 5 of a finder.
+const MIN_FINDER_RATIO: f32 = 9.5 / 12.0;
+const MAX_FINDER_RATIO: f32 = 12.5 / 14.0;
+/// Bound on the widest to narrowest of elements 2 to 5, which are nominally
+/// up to nine to one; the margin absorbs a pixel of rounding at small
+/// module sizes.
+const MAX_FINDER_ELEMENT_RATIO: u32 = 12;
+const MAX_AVG_VARIANCE: f32 = 0.2;
+const MAX_INDIVIDUAL_VARIANCE: f32 = 0.45;
+
+/// Character group tables of ISO/IEC 24724, indexed by group.
+const OUTSIDE_EVEN_TOTAL_SUBSET: [u32; 5] = [1, 10, 34, 70, 126];
+const OUTSIDE_GSUM: [u32; 5] = [0, 161, 961, 2015, 2715];
+const OUTSIDE_ODD_WIDEST: [u32; 5] = [8, 6, 4, 3, 1];
+const INSIDE_ODD_TOTAL_SUBSET: [u32; 4] = [4, 20, 48, 81];
+const INSIDE_GSUM: [u32; 4] = [0, 336, 1036, 1516];
+const INSIDE_ODD_WIDEST: [u32; 4] = [2, 4, 6, 8];
+
+/// Modules in an outside and an inside data character.
+const OUTSIDE_MODULES: u32 = 16;
+const INSIDE_MODULES: u32 = 15;
+
+/// Weights of the outside character within a half and of the left half
+/// within the symbol value.
+const OUTSIDE_WEIGHT: u32 = 1597;
+const LEFT_HALF_WEIGHT: u64 = 4_537_077;
+/// Added to the symbol value when a composite component is printed above.
+const LINKAGE_VALUE: u64 = 10_000_000_000_000;
+
+/// A data character: its value and its contribution to the checksum.
+struct Character {
+    value: u32,
+    checksum: u32,
+}
+
+/// The outside and inside characters around one finder pattern.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Half {
+    value: u32,
+    checksum: u32,
+    finder: u32,
+}
+
+/// Moves one module to the element with the largest rounding error.
+fn increment(counts: &mut [u32; 4], errors: &[f32; 4]) {
+    let index = (0..4).max_by(|a, b| errors[*a].total_cmp(&errors[*b]));
+    counts[index.unwrap_or(0)] += 1;
+}
+
+/// Takes one module from the element with the smallest rounding error.
+fn decrement(counts: &mut [u32; 4], errors: &[f32; 4]) -> Option<()> {
+    let index = (0..4).min_by(|a, b| errors[*a].total_cmp(&errors[*b]))?;
+    counts[index] = counts[index].checked_sub(1).filter(|count| *count > 0)?;
+    Some(())
+}
+
+/// Corrects module counts rounded to the wrong total, using the parity of
+/// the odd and even sums to tell which of them is off.
+fn adjust_counts(
+    (odd, odd_errors): (&mut [u32; 4], &[f32; 4]),
+    (even, even_errors): (&mut [u32; 4], &[f32; 4]),
+    outside: bool,
+) -> Option<()> {
+    let odd_sum: u32 = odd.iter().sum();
+    let even_sum: u32 = even.iter().sum();
+    let (odd_range, even_range, modules) = if outside {
+        (4..=12, 4..=12, OUTSIDE_MODULES)
+    } else {
+        (5..=11, 4..=10, INSIDE_MODULES)
+    };
+
+    let mut increment_odd = odd_sum < *odd_range.start();
+    let mut decrement_odd = odd_sum > *odd_range.end();
+    let mut increment_even = even_sum < *even_range.start();
+    let mut decrement_even = even_sum > *even_range.end();
+
+    // Odd sums are even in outside characters and odd in inside ones; even
+    // sums are always even.
+    let odd_parity_bad = (odd_sum & 1) == u32::from(outside);
+    let even_parity_bad = (even_sum & 1) == 1;
+    match odd_sum as i32 + even_sum as i32 - modules as i32 {
+        1 if odd_parity_bad && !even_parity_bad => decrement_odd = true,
+        1 if !odd_parity_bad && even_parity_bad => decrement_even = true,
+        -1 if odd_parity_bad && !even_parity_bad => increment_odd = true,
+        -1 if !odd_parity_bad && even_parity_bad => increment_even = true,
+        0 if odd_parity_bad && even_parity_bad => {
+            if odd_sum < even_sum {
+                increment_odd = true;
+                decrement_even = true;
+            } else {
+                decrement_odd = true;
+                increment_even = true;
+            }
+        }
+        0 if !odd_parity_bad && !even_parity_bad => {}
+        _ => return None,
+    }
+
+    if (increment_odd && decrement_odd) || (increment_even && decrement_even) {
+        return None;
+    }
+    if increment_odd {
+        increment(odd, odd_errors);
+    }
+    if decrement_odd {
+        decrement(odd, odd_errors)?;
+    }
+    if increment_even {
+        increment(even, even_errors);
+    }
+    if decrement_even {
+        decrement(even, even_errors)?;
+    }
+    Some(())
+}
+
+/// Decodes a data character from its eight element widths, outermost
+/// element first.
+fn decode_character(widths: &[usize], outside: bool) -> Option<Character> {
+    let modules = if outside {
+        OUTSIDE_MODULES
+    } else {
+        INSIDE_MODULES
+    };
+    let module = widths.iter().sum::<usize>() as f32 / modules as f32;
+
+    let (mut odd, mut even) = ([0u32; 4], [0u32; 4]);
+    let (mut odd_errors, mut even_errors) = ([0f32; 4], [0f32; 4]);
+    for (i, width) in widths.iter().enumerate() {
+        let exact = *width as f32 / module;
+        let count = ((exact + 0.5) as u32).clamp(1, 8);
+        let (counts, errors) = if i % 2 == 0 {
+            (&mut odd, &mut odd_errors)
+        } else {
+            (&mut even, &mut even_errors)
+        };
+        counts[i / 2] = count;
+        errors[i / 2] = exact - count as f32;
+    }
+    adjust_counts((&mut odd, &odd_errors), (&mut even, &even_errors), outside)?;
+
+    let portion = |counts: &[u32; 4]| counts.iter().rev().fold(0, |acc, c| acc * 9 + c);
+    let checksum = portion(&odd) + 3 * portion(&even);
+    let widest = |counts: &[u32; 4]| counts.iter().copied().max().unwrap_or(0);
+
+    let value = if outside {
+        let odd_sum: u32 = odd.iter().sum();
+        if !odd_sum.is_multiple_of(2) || !(4..=12).contains(&odd_sum) {
+            return None;
+        }
+        let group = ((12 - odd_sum) / 2) as usize;
+        let odd_widest = OUTSIDE_ODD_WIDEST[group];
+        if widest(&odd) > odd_widest || widest(&even) > 9 - odd_widest {
+            return None;
+        }
+        getRSSvalue(&odd, odd_widest, false) * OUTSIDE_EVEN_TOTAL_SUBSET[group]
+            + getRSSvalue(&even, 9 - odd_widest, true)
+            + OUTSIDE_GSUM[group]
+    } else {
+        let even_sum: u32 = even.iter().sum();
+        if !even_sum.is_multiple_of(2) || !(4..=10).contains(&even_sum) {
+            return None;
+        }
+        let group = ((10 - even_sum) / 2) as usize;
+        let odd_widest = INSIDE_ODD_WIDEST[group];
+        if widest(&odd) > odd_widest || widest(&even) > 9 - odd_widest {
+            return None;
+        }
+        getRSSvalue(&even, 9 - odd_widest, false) * INSIDE_ODD_TOTAL_SUBSET[group]
+            + getRSSvalue(&odd, odd_widest, true)
+            + INSIDE_GSUM[group]
+    };
+
+    Some(Character { value, checksum })
+}
+
+/// Returns the value of a finder pattern from its five element widths,
+/// counted from the outside character.
+fn finder_value(finder: &[usize]) -> Option<u32> {
+    let counts: Vec<u32> = finder.iter().map(|w| *w as u32).collect();
+    let inner: u32 = counts[1..].iter().sum();
+    let ratio = (counts[1] + counts[2]) as f32 / inner as f32;
+    let (min, max) = (counts[1..].iter().min()?, counts[1..].iter().max()?);
+    if !(MIN_FINDER_RATIO..=MAX_FINDER_RATIO).contains(&ratio)
+        || *max >= MAX_FINDER_ELEMENT_RATIO * *min
+    {
+        return None;
+    }
+
+    FINDER_PATTERNS
+        .iter()
+        .position(|pattern| {
+            pattern_match_variance(&counts[..4], pattern, MAX_INDIVIDUAL_VARIANCE)
+                < MAX_AVG_VARIANCE
+        })
+        .map(|value| value as u32)
+}
+
+/// Decodes every half whose finder pattern has a dark second element, with
+/// the pixel extent of the finder. The right half of a symbol is read from
+/// the reversed row, where its finder starts light.
+fn decode_halves(runs: &[Run], finder_dark: bool) -> Vec<(Half, usize, usize)> {
+    let mut halves = Vec::new();
+    for i in 8..runs.len().saturating_sub(12) {
+        let elements = &runs[i - 8..i + 13];
+        if elements[9].dark != finder_dark {
+            continue;
+        }
+        let widths: Vec<usize> = elements.iter().map(|run| run.len).collect();
+        let Some(finder) = finder_value(&widths[8..13]) else {
+            continue;
+        };
+
+        let inside: Vec<usize> = widths[13..].iter().rev().copied().collect();
+        let (Some(outside), Some(inside)) = (
+            decode_character(&widths[..8], true),
+            decode_character(&inside, false),
+        ) else {
+            continue;
+        };
+
+        let (first, last) = (elements[8], elements[12]);
+        let (start, end) = (
+            first.start.min(last.start),
+            (first.start + first.len).max(last.start + last.len),
+        );
+        halves.push((
+            Half {
+                value: OUTSIDE_WEIGHT * outside.value + inside.value,
+                checksum: outside.checksum + 4 * inside.checksum,
+                finder,
+            },
+            start,
+            end,
+        ));
+    }
+    halves
+}
+
+/// Returns true if the check value carried by the two finder patterns
+/// matches the checksum of the data characters.
+fn checksum_matches(left: &Half, right: &Half) -> bool {
+    let mut target = 9 * left.finder + right.finder;
+    if target > 72 {
+        target -= 1;
+    }
+    if target > 8 {
+        target -= 1;
+    }
+    (left.checksum + 16 * right.checksum) % 79 == target
+}
+
+/// Decodes a GS1 DataBar Omnidirectional, Truncated, Stacked or Stacked
+/// Omnidirectional symbol into the element string `01` + GTIN-14.
+///
+/// A linkage flag in the symbol value marks a CC-A or CC-B composite
+/// component printed above it, whose element strings follow the GTIN in the
+/// value. A flagged component that cannot be read leaves the GTIN alone and
+/// sets `linked_composite`.
+pub fn decode(gray_data: &[u8], width: u32, height: u32) -> Option<Detection> {
+    let src = Luma8LuminanceSource::new(gray_data.to_vec(), width, height);
+    let bitmap = BinaryBitmap::new(HybridBinarizer::new(src));
+    let (width, height) = (width as usize, height as usize);
+
+    type Tally = HashMap<Half, (usize, Vec<(f32, f32)>)>;
+    let (mut lefts, mut rights): (Tally, Tally) = (HashMap::new(), HashMap::new());
+    for row in 1..=SCAN_ROWS {
+        let y = height * row / (SCAN_ROWS + 1);
+        let Ok(bits) = bitmap.get_black_row(y) else {
+            continue;
+        };
+        let mut runs = runs((0..width).map(|x| bits.get(x)));
+        for (tally, finder_dark) in [(&mut lefts, true), (&mut rights, false)] {
+            for (half, start, end) in decode_halves(&runs, finder_dark) {
+                let entry = tally
+                    .entry(half)
+                    .or_insert_with(|| (0, vec![(start as f32, y as f32), (end as f32, y as f32)]));
+                entry.0 += 1;
+            }
+            runs.reverse();
+        }
+    }
+
+    let (left, right) = lefts
+        .iter()
+        .filter(|(_, (count, _))| *count >= MIN_HALF_READS)
+        .flat_map(|left| {
+            rights
+                .iter()
+                .filter(|(_, (count, _))| *count >= MIN_HALF_READS)
+                .filter(move |right| checksum_matches(left.0, right.0))
+                .map(move |right| (left, right))
+        })
+        .max_by_key(|(left, right)| left.1.0 + right.1.0)?;
+
+    let symbol = LEFT_HALF_WEIGHT * left.0.value as u64 + right.0.value as u64;
+    if symbol >= 2 * LINKAGE_VALUE {
+        return None;
+    }
+    let digits = format!("{:013}", symbol % LINKAGE_VALUE);
+    let check = validator::gs1_check_digit(&digits)?;
+    let points: Vec<(f32, f32)> = left.1.1.iter().chain(&right.1.1).copied().collect();
+
+    // The component sits above the first scan line through the symbol.
+    let composite = (symbol >= LINKAGE_VALUE)
+        .then(|| {
+            let top = points.iter().map(|(_, y)| *y as usize).min()?;
+            let rows: Vec<Vec<Run>> = (0..top)
+                .filter_map(|y| bitmap.get_black_row(y).ok())
+                .map(|bits| runs((0..width).map(|x| bits.get(x))))
+                .collect();
+            composite::decode(&rows)
+        })
+        .flatten();
+    let value = format!("01{digits}{check}{}", composite.as_deref().unwrap_or(""));
+
+    let format = Format::Rxing(BarcodeFormat::RSS_14);
+    validator::validate(&format, &value).map(|value| Detection {
+        value,
+        format,
+        points,
+        structured_append: None,
+        linked_composite: symbol >= LINKAGE_VALUE && composite.is_none(),
+        add_on: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use image::{GrayImage, Luma};
+    use rxing::{DecodeHintValue, DecodeHints, MultiFormatReader, Reader};
+
+    use super::*;
+
+    /// The element widths of a character subset value, found by search.
+    fn subset_widths(value: u32, modules: u32, max_width: u32, no_narrow: bool) -> [u32; 4] {
+        (0..8u32.pow(4))
+            .map(|n| [n % 8 + 1, n / 8 % 8 + 1, n / 64 % 8 + 1, n / 512 + 1])
+            .find(|widths| {
+                widths.iter().sum::<u32>() == modules
+                    && widths.iter().all(|w| *w <= max_width)
+                    && (no_narrow || widths.contains(&1))
+                    && getRSSvalue(widths, max_width, no_narrow) == value
+            })
+            .unwrap()
+    }
+
+    /// Encodes a data character as its eight element widths, outermost
+    /// element first.
+    fn character_widths(value: u32, outside: bool) -> Vec<usize> {
+        let (odd, even) = if outside {
+            let group = OUTSIDE_GSUM.iter().rposition(|sum| value >= *sum).unwrap();
+            let value = value - OUTSIDE_GSUM[group];
+            let (widest, modules) = (OUTSIDE_ODD_WIDEST[group], 12 - 2 * group as u32);
+            let subset = OUTSIDE_EVEN_TOTAL_SUBSET[group];
+            (
+                subset_widths(value / subset, modules, widest, false),
+                subset_widths(value % subset, OUTSIDE_MODULES - modules, 9 - widest, true),
+            )
+        } else {
+            let group = INSIDE_GSUM.iter().rposition(|sum| value >= *sum).unwrap();
+            let value = value - INSIDE_GSUM[group];
+            let (widest, modules) = (INSIDE_ODD_WIDEST[group], 10 - 2 * group as u32);
+            let subset = INSIDE_ODD_TOTAL_SUBSET[group];
+            (
+                subset_widths(value % subset, INSIDE_MODULES - modules, widest, true),
+                subset_widths(value / subset, modules, 9 - widest, false),
+            )
+        };
+        (0..8)
+            .map(|i| if i % 2 == 0 { odd[i / 2] } else { even[i / 2] } as usize)
+            .collect()
+    }
+
+    /// The finder pair whose check value matches the two halves' checksums.
+    fn finders(left: u32, right: u32) -> (u32, u32) {
+        let half = |checksum, finder| Half {
+            value: 0,
+            checksum,
+            finder,
+        };
+        (0..9)
+            .flat_map(|l| (0..9).map(move |r| (l, r)))
+            .find(|(l, r)| checksum_matches(&half(left, *l), &half(right, *r)))
+            .unwrap()
+    }
+
+    /// The 21 element widths of the left and right halves of a DataBar
+    /// Omnidirectional symbol, each in reading order from the outside.
+    fn halves(symbol: u64) -> (Vec<usize>, Vec<usize>) {
+        let encode = |value: u32| {
+            let outside = character_widths(value / OUTSIDE_WEIGHT, true);
+            let inside = character_widths(value % OUTSIDE_WEIGHT, false);
+            let checksum = decode_character(&outside, true).unwrap().checksum
+                + 4 * decode_character(&inside, false).unwrap().checksum;
+            (outside, inside, checksum)
+        };
+        let (left, right) = (
+            encode((symbol / LEFT_HALF_WEIGHT) as u32),
+            encode((symbol % LEFT_HALF_WEIGHT) as u32),
+        );
+        let (left_finder, right_finder) = finders(left.2, right.2);
+
+        let half = |(outside, inside, _): (Vec<usize>, Vec<usize>, u32), finder: u32| {
+            let finder = FINDER_PATTERNS[finder as usize].map(|w| w as usize);
+            [
+                outside,
+                finder.to_vec(),
+                vec![1],
+                inside.into_iter().rev().collect(),
+            ]
+            .concat()
+        };
+        (half(left, left_finder), half(right, right_finder))
+    }
+
+    /// Renders rows of elements that alternate from light, each row
+    /// `height` pixels tall, with a ten-module quiet zone.
+    fn render(rows: &[Vec<usize>], module: u32, height: u32) -> GrayImage {
+        let quiet = 10;
+        let modules = rows
+            .iter()
+            .map(|row| row.iter().sum::<usize>() as u32)
+            .max()
+            .unwrap();
+        let pixels: Vec<Vec<bool>> = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .flat_map(|(i, w)| std::iter::repeat_n(i % 2 == 1, w * module as usize))
+                    .collect()
+            })
+            .collect();
+
+        GrayImage::from_fn(
+            (modules + 2 * quiet) * module,
+            rows.len() as u32 * height,
+            |x, y| {
+                let row = &pixels[(y / height) as usize];
+                let dark = (x / module)
+                    .checked_sub(quiet)
+                    .is_some_and(|_| row.get((x - quiet * module) as usize) == Some(&true));
+                Luma([if dark { 0 } else { 255 }])
+            },
+        )
+    }
+
+    /// A DataBar Omnidirectional symbol between its guard patterns.
+    fn omnidirectional(symbol: u64, module: u32) -> GrayImage {
+        let (left, right) = halves(symbol);
+        let row = [
+            vec![1, 1],
+            left,
+            right.into_iter().rev().collect(),
+            vec![1, 1],
+        ]
+        .concat();
+        render(&[row], module, 40)
+    }
+
+    fn decode_image(image: &GrayImage) -> Option<Detection> {
+        decode(image.as_raw(), image.width(), image.height())
+    }
+
+    #[test]
+    fn characters_round_trip_through_every_group() {
+        for value in [0, 160, 161, 960, 961, 2014, 2015, 2714, 2715, 2840] {
+            let widths = character_widths(value, true);
+            assert_eq!(widths.iter().sum::<usize>(), OUTSIDE_MODULES as usize);
+            assert_eq!(decode_character(&widths, true).unwrap().value, value);
+        }
+        for value in [0, 335, 336, 1035, 1036, 1515, 1516, 1596] {
+            let widths = character_widths(value, false);
+            assert_eq!(widths.iter().sum::<usize>(), INSIDE_MODULES as usize);
+            assert_eq!(decode_character(&widths, false).unwrap().value, value);
+        }
+    }
+
+    #[test]
+    fn characters_decode_from_uneven_pixel_widths() {
+        let widths = character_widths(1234, true);
+        let mut pixels: Vec<usize> = widths.iter().map(|w| w * 3).collect();
+        // Ink spread: a pixel from a space to the next bar.
+        pixels[1] += 1;
+        pixels[2] -= 1;
+        assert_eq!(decode_character(&pixels, true).unwrap().value, 1234);
+
+        // Rounding to one module too many is corrected by the parities.
+        let widths = character_widths(700, false);
+        let mut pixels: Vec<usize> = widths.iter().map(|w| w * 4).collect();
+        pixels[3] += 2;
+        assert_eq!(decode_character(&pixels, false).unwrap().value, 700);
+    }
+
+    #[test]
+    fn characters_with_impossible_widths_are_rejected() {
+        // An odd element wider than its group allows.
+        assert!(decode_character(&[7, 1, 1, 1, 1, 1, 1, 3], true).is_none());
+    }
+
+    #[test]
+    fn finder_patterns_are_recognised() {
+        for (value, pattern) in FINDER_PATTERNS.iter().enumerate() {
+            let widths: Vec<usize> = pattern
+                .iter()
+                .chain(&[1])
+                .map(|w| *w as usize * 4)
+                .collect();
+            assert_eq!(finder_value(&widths), Some(value as u32));
+        }
+        // Wide elements of the wrong share of the pattern.
+        assert_eq!(finder_value(&[12, 4, 4, 4, 4]), None);
+        assert_eq!(finder_value(&[12, 20, 20, 20, 4]), None);
+    }
+
+    #[test]
+    fn checksums_select_the_finder_pair() {
+        let half = |checksum, finder| Half {
+            value: 0,
+            checksum,
+            finder,
+        };
+        // Encoders skip the pairs that would stand for 8 and 73, so each
+        // check value is carried by one pair of finders; the decoder takes
+        // the skipped pairs as aliases.
+        for check in 0..79 {
+            let shifted = check + u32::from(check >= 8) + u32::from(check >= 72);
+            let printed = (shifted / 9, shifted % 9);
+            let pairs: Vec<(u32, u32)> = (0..9)
+                .flat_map(|l| (0..9).map(move |r| (l, r)))
+                .filter(|pair| ![(0, 8), (8, 1)].contains(pair))
+                .filter(|(l, r)| checksum_matches(&half(check, *l), &half(0, *r)))
+                .collect();
+            assert_eq!(pairs, [printed], "{check}");
+        }
+
+        let (l, r) = finders(10, 20);
+        assert!(checksum_matches(&half(10, l), &half(20, r)));
+        assert!(!checksum_matches(&half(10, l), &half(21, r)));
+        assert!(!checksum_matches(&half(10, (l + 1) % 9), &half(20, r)));
+    }
+
+    #[test]
+    fn omnidirectional_symbols_decode_to_element_strings() {
+        let image = omnidirectional(31_234_567_890, 3);
+        let detection = decode_image(&image).unwrap();
+        assert_eq!(detection.value, "0100312345678906");
+        assert!(!detection.linked_composite);
+
+        // rxing's reader agrees on the same symbol.
+        let source =
+            Luma8LuminanceSource::new(image.as_raw().clone(), image.width(), image.height());
+        let mut bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+        let hints = DecodeHints::default().with(DecodeHintValue::PossibleFormats(HashSet::from([
+            BarcodeFormat::RSS_14,
+        ])));
+        let result = MultiFormatReader::default()
+            .decode_with_hints(&mut bitmap, &hints)
+            .unwrap();
+        assert_eq!(result.getText(), "00312345678906");
+    }
+
+    #[test]
+    fn linkage_flags_a_composite_component() {
+        let detection =
+            decode_image(&omnidirectional(LINKAGE_VALUE + 9_501_101_530_003, 3)).unwrap();
+        assert_eq!(detection.value, "0195011015300038");
+        assert!(detection.linked_composite);
+    }
+
+    #[test]
+    fn composite_components_are_merged_into_the_element_string() {
+        // (17) 261231 and (10) ABC12 in CC-A method 10: a 1, a 0, the
+        // compressed date, the AI 17 flag and the lot in alphanumeric mode.
+        let mut bits = "10".to_string();
+        bits += &format!("{:016b}1", 26 * 384 + 11 * 32 + 31);
+        bits += "0000 100000 100001 100010 00110 00111";
+        let bits: Vec<bool> = bits
+            .bytes()
+            .filter(|b| *b != b' ')
+            .map(|b| b == b'1')
+            .collect();
+        let codewords = composite::tests::with_ec(&composite::tests::cc_a(bits, 12), 1);
+
+        let (left, right) = halves(LINKAGE_VALUE + 9_501_101_530_003);
+        let linear = [
+            vec![1, 1],
+            left,
+            right.into_iter().rev().collect(),
+            vec![1, 1],
+        ]
+        .concat();
+        let mut rows = composite::tests::rows(&codewords, 4);
+        rows.push(vec![]);
+        rows.extend(std::iter::repeat_n(linear, 6));
+
+        let detection = decode_image(&render(&rows, 3, 6)).unwrap();
+        assert_eq!(detection.value, "01950110153000381726123110ABC12");
+        assert!(!detection.linked_composite);
+    }
+
+    #[test]
+    fn stacked_halves_are_paired_across_rows() {
+        let (left, right) = halves(31_234_567_890);
+        let top = [vec![1, 1], left, vec![1]].concat();
+        let bottom = [vec![3], right.into_iter().rev().collect(), vec![1, 1]].concat();
+        let detection = decode_image(&render(&[top, bottom], 3, 20)).unwrap();
+        assert_eq!(detection.value, "0100312345678906");
+    }
+
+    #[test]
+    fn symbols_with_a_bad_checksum_are_not_read() {
+        let (left, mut right) = halves(31_234_567_890);
+        let finder = finder_value(&right[8..13]).unwrap() as usize;
+        let other = FINDER_PATTERNS[(finder + 3) % 9].map(|w| w as usize);
+        right[8..12].copy_from_slice(&other);
+
+        let row = [
+            vec![1, 1],
+            left,
+            right.into_iter().rev().collect(),
+            vec![1, 1],
+        ]
+        .concat();
+        assert!(decode_image(&render(&[row], 3, 40)).is_none());
+    }
+}
@@ -1,20 +1,34 @@
-use std::io::Cursor;
+use std::{
+    cell::{Cell, RefCell},
+    io::Cursor,
+};
 
 use image::{
-    GrayImage, ImageReader,
+    GrayImage, ImageError, ImageReader,
     imageops::{FilterType, crop, resize},
 };
-use js_sys::Uint8Array;
+use js_sys::{Array, Uint8Array};
 use rxing::{
-    BinaryBitmap, Luma8LuminanceSource, Reader, common::HybridBinarizer, oned::UPCAReader,
+    BarcodeFormat, Binarizer, BinaryBitmap, Luma8LuminanceSource, RXingResult,
+    RXingResultMetadataType, RXingResultMetadataValue, Reader,
+    common::HybridBinarizer,
+    oned::{
+        UPCAReader, UPCEANExtension2Support, UPCEANReader, UPCEReader,
+        rss::expanded::RSSExpandedReader,
+    },
     qrcode::QRCodeReader,
 };
-use wasm_bindgen::{JsCast, prelude::Closure};
-use web_sys::{Event, File, FileReader};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{JsFuture, spawn_local};
+use web_sys::{Blob, CanvasRenderingContext2d, HtmlCanvasElement, ImageBitmap};
 
 use crate::{
+    databar,
     error::Error,
     event::{invoke_on_detect, invoke_on_stop},
+    feedback, gs1, options, pharmacode,
+    structured_append::{self, StructuredAppend},
+    validator,
 };
 
 const MIN_IMAGE_DIMENSION: u32 = 60;
@@ -22,6 +36,168 @@ const OPTIMAL_IMAGE_DIMENSION: u32 = 1200;
 const IMAGE_CROP_FACTOR: u32 = 2;
 const STREAM_CROP_FACTOR: u32 = 2;
 
+/// Formats tried on every image and frame when none are configured.
+const DEFAULT_SCAN_FORMATS: [Format; 2] = [
+    Format::Rxing(BarcodeFormat::UPC_A),
+    Format::Rxing(BarcodeFormat::QR_CODE),
+];
+
+/// Formats that `set_scan_formats` accepts, i.e. those with a reader
+/// included in the build.
+const SUPPORTED_SCAN_FORMATS: [Format; 6] = [
+    Format::Rxing(BarcodeFormat::UPC_A),
+    Format::Rxing(BarcodeFormat::UPC_E),
+    Format::Rxing(BarcodeFormat::QR_CODE),
+    Format::Rxing(BarcodeFormat::RSS_14),
+    Format::Rxing(BarcodeFormat::RSS_EXPANDED),
+    Format::Pharmacode,
+];
+
+/// Start guard of EAN-2 and EAN-5 add-ons, in modules.
+const ADD_ON_START_PATTERN: [u32; 3] = [1, 1, 2];
+const EAN_5_LENGTH: usize = 5;
+
+thread_local! {
+    static SCAN_FORMATS: RefCell<Vec<Format>> = RefCell::new(DEFAULT_SCAN_FORMATS.to_vec());
+    static REQUIRE_ADD_ON: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The symbology of a detection: one read by rxing, or one that wascan
+/// decodes itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Rxing(BarcodeFormat),
+    /// Laetus Pharmacode, one-track.
+    Pharmacode,
+}
+
+impl Format {
+    /// Returns the stable name used for the format in the JS API.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Rxing(format) => format_name(format),
+            Format::Pharmacode => "pharmacode",
+        }
+    }
+
+    /// Returns the format for a name produced by `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pharmacode" => Some(Format::Pharmacode),
+            _ => format_from_name(name).map(Format::Rxing),
+        }
+    }
+}
+
+impl From<BarcodeFormat> for Format {
+    fn from(format: BarcodeFormat) -> Self {
+        Format::Rxing(format)
+    }
+}
+
+/// A decoded barcode value together with its symbology.
+#[derive(Debug, Clone)]
+pub struct Detection {
+    pub value: String,
+    pub format: Format,
+    /// Result points (finder patterns, bar ends) in source frame pixels.
+    pub points: Vec<(f32, f32)>,
+    /// Position within a QR Code structured append sequence.
+    pub structured_append: Option<StructuredAppend>,
+    /// Set when a GS1 DataBar symbol flags a composite component that could
+    /// not be read.
+    pub linked_composite: bool,
+    /// EAN-2 or EAN-5 supplemental add-on printed after a UPC/EAN symbol.
+    pub add_on: Option<String>,
+}
+
+impl Detection {
+    fn from_result(value: String, res: &RXingResult) -> Self {
+        Detection {
+            value,
+            format: Format::Rxing(*res.getBarcodeFormat()),
+            points: res.getPoints().iter().map(|p| (p.x, p.y)).collect(),
+            structured_append: StructuredAppend::from_result(res),
+            linked_composite: false,
+            add_on: match res
+                .getRXingResultMetadata()
+                .get(&RXingResultMetadataType::UPC_EAN_EXTENSION)
+            {
+                Some(RXingResultMetadataValue::UpcEanExtension(add_on)) => Some(add_on.clone()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Maps points from a scaled and cropped buffer back to the source frame.
+    fn map_points(mut self, scale_x: f32, scale_y: f32, offset_x: u32, offset_y: u32) -> Self {
+        for (x, y) in &mut self.points {
+            *x = *x * scale_x + offset_x as f32;
+            *y = *y * scale_y + offset_y as f32;
+        }
+        self
+    }
+}
+
+/// Returns the stable name used for `format` in the JS API.
+pub fn format_name(format: &BarcodeFormat) -> &'static str {
+    match format {
+        BarcodeFormat::AZTEC => "aztec",
+        BarcodeFormat::CODABAR => "codabar",
+        BarcodeFormat::CODE_39 => "code_39",
+        BarcodeFormat::CODE_93 => "code_93",
+        BarcodeFormat::CODE_128 => "code_128",
+        BarcodeFormat::DATA_MATRIX => "data_matrix",
+        BarcodeFormat::EAN_8 => "ean_8",
+        BarcodeFormat::EAN_13 => "ean_13",
+        BarcodeFormat::ITF => "itf",
+        BarcodeFormat::MAXICODE => "maxicode",
+        BarcodeFormat::PDF_417 => "pdf_417",
+        BarcodeFormat::QR_CODE => "qr_code",
+        BarcodeFormat::MICRO_QR_CODE => "micro_qr_code",
+        BarcodeFormat::RECTANGULAR_MICRO_QR_CODE => "rmqr_code",
+        BarcodeFormat::RSS_14 => "rss_14",
+        BarcodeFormat::RSS_EXPANDED => "rss_expanded",
+        BarcodeFormat::TELEPEN => "telepen",
+        BarcodeFormat::UPC_A => "upc_a",
+        BarcodeFormat::UPC_E => "upc_e",
+        BarcodeFormat::UPC_EAN_EXTENSION => "upc_ean_extension",
+        BarcodeFormat::DXFilmEdge => "dx_film_edge",
+        _ => "unknown",
+    }
+}
+
+/// Returns the format for a name produced by `format_name`. The names match
+/// those of the Shape Detection API's `BarcodeDetector`.
+pub fn format_from_name(name: &str) -> Option<BarcodeFormat> {
+    let format = match name {
+        "aztec" => BarcodeFormat::AZTEC,
+        "codabar" => BarcodeFormat::CODABAR,
+        "code_39" => BarcodeFormat::CODE_39,
+        "code_93" => BarcodeFormat::CODE_93,
+        "code_128" => BarcodeFormat::CODE_128,
+        "data_matrix" => BarcodeFormat::DATA_MATRIX,
+        "ean_8" => BarcodeFormat::EAN_8,
+        "ean_13" => BarcodeFormat::EAN_13,
+        "itf" => BarcodeFormat::ITF,
+        "maxicode" => BarcodeFormat::MAXICODE,
+        "pdf_417" => BarcodeFormat::PDF_417,
+        "qr_code" => BarcodeFormat::QR_CODE,
+        "micro_qr_code" => BarcodeFormat::MICRO_QR_CODE,
+        "rmqr_code" => BarcodeFormat::RECTANGULAR_MICRO_QR_CODE,
+        "rss_14" => BarcodeFormat::RSS_14,
+        "rss_expanded" => BarcodeFormat::RSS_EXPANDED,
+        "telepen" => BarcodeFormat::TELEPEN,
+        "upc_a" => BarcodeFormat::UPC_A,
+        "upc_e" => BarcodeFormat::UPC_E,
+        "upc_ean_extension" => BarcodeFormat::UPC_EAN_EXTENSION,
+        "dx_film_edge" => BarcodeFormat::DXFilmEdge,
+        _ => return None,
+    };
+
+    Some(format)
+}
+
 fn prepare_image_data(image: &GrayImage, width: u32, height: u32) -> (Vec<u8>, u32, u32) {
     if width > OPTIMAL_IMAGE_DIMENSION || height > OPTIMAL_IMAGE_DIMENSION {
         let ratio = width as f64 / height as f64;
@@ -42,138 +218,353 @@ fn prepare_image_data(image: &GrayImage, width: u32, height: u32) -> (Vec<u8>, u
     }
 }
 
-fn detect_barcode(gray_data: Vec<u8>, width: u32, height: u32) -> Result<String, Error> {
-    // Try UPC-A first
-    let upca_result = {
-        let src = Luma8LuminanceSource::new(gray_data.clone(), width, height);
-        let binarizer = HybridBinarizer::new(src);
-        let mut bitmap = BinaryBitmap::new(binarizer);
-        let mut reader = UPCAReader::default();
-        reader.decode(&mut bitmap)
-    };
-
-    if let Ok(res) = upca_result {
-        return Ok(res.getText().to_string());
+/// Sets the formats tried on every image and frame, in order.
+pub fn set_scan_formats(formats: &JsValue) -> Result<(), Error> {
+    let formats = formats
+        .dyn_ref::<Array>()
+        .ok_or(Error::InvalidOptions)?
+        .iter()
+        .map(|name| {
+            name.as_string()
+                .and_then(|name| Format::from_name(&name))
+                .filter(|format| SUPPORTED_SCAN_FORMATS.contains(format))
+                .ok_or(Error::InvalidOptions)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if formats.is_empty() {
+        return Err(Error::InvalidOptions);
     }
 
-    // Try QR code
-    let qr_result = {
-        let src = Luma8LuminanceSource::new(gray_data, width, height);
-        let binarizer = HybridBinarizer::new(src);
-        let mut bitmap = BinaryBitmap::new(binarizer);
-        let mut reader = QRCodeReader::new();
-        reader.decode(&mut bitmap)
+    SCAN_FORMATS.with(|slot| *slot.borrow_mut() = formats);
+    Ok(())
+}
+
+/// Configures the UPC-A and UPC-E readers.
+pub fn set_upc_options(options: &JsValue) -> Result<(), Error> {
+    let obj = options::as_object(options)?;
+    let require_add_on = options::get_bool(&obj, "requireAddOn")?.unwrap_or(false);
+
+    REQUIRE_ADD_ON.with(|slot| slot.set(require_add_on));
+    Ok(())
+}
+
+/// Returns true if UPC symbols without an add-on are rejected.
+pub fn add_on_required() -> bool {
+    REQUIRE_ADD_ON.with(|slot| slot.get())
+}
+
+/// Reads an EAN-2 add-on to the right of a UPC symbol, on the row it was
+/// read from.
+fn read_ean_2<B: Binarizer>(bitmap: &BinaryBitmap<B>, res: &RXingResult) -> Option<String> {
+    let points = res.getPoints();
+    let y = points.first()?.y;
+    let row = bitmap.get_black_row(y as usize).ok()?;
+
+    // rxing reports the center of the end guard, `101` for UPC-A and
+    // `010101` for UPC-E; the add-on starts after its last bar.
+    let bars_after_center = match res.getBarcodeFormat() {
+        BarcodeFormat::UPC_E => 2,
+        _ => 1,
     };
+    let mut end = points.iter().map(|p| p.x).fold(0.0, f32::max) as usize;
+    for _ in 0..bars_after_center {
+        end = row.getNextUnset(row.getNextSet(end));
+    }
+    let guard = UPCEReader
+        .findGuardPattern(&row, end, false, &ADD_ON_START_PATTERN)
+        .ok()?;
+
+    let range = [guard[0] as u32, guard[1] as u32, 0];
+    UPCEANExtension2Support::default()
+        .decodeRow(y as u32, &row, &range)
+        .ok()
+        .map(|add_on| add_on.getText().to_string())
+}
 
-    match qr_result {
-        Ok(res) => Ok(res.getText().to_string()),
-        Err(_) => Err(Error::NotDetected),
+/// Validates a UPC-A or UPC-E read and attaches its add-on.
+///
+/// rxing reads EAN-5 add-ons itself, but its EAN-2 fallback decodes from the
+/// start of the row, so two-digit add-ons are read here instead.
+fn upc_detection<B: Binarizer>(bitmap: &BinaryBitmap<B>, res: &RXingResult) -> Option<Detection> {
+    let value = validator::validate(&Format::Rxing(*res.getBarcodeFormat()), res.getText())?;
+    let mut detection = Detection::from_result(value, res);
+    detection.add_on = detection
+        .add_on
+        .filter(|add_on| add_on.len() == EAN_5_LENGTH)
+        .or_else(|| read_ean_2(bitmap, res));
+
+    if add_on_required() && detection.add_on.is_none() {
+        return None;
     }
+    Some(detection)
 }
 
-pub fn detect_from_image(file: File) {
-    let Ok(reader) = FileReader::new() else {
-        invoke_on_detect(Err(&Error::Internal));
-        invoke_on_stop();
+fn decode_upc_a(gray_data: Vec<u8>, width: u32, height: u32) -> Option<Detection> {
+    let src = Luma8LuminanceSource::new(gray_data, width, height);
+    let binarizer = HybridBinarizer::new(src);
+    let mut bitmap = BinaryBitmap::new(binarizer);
+    let res = UPCAReader::default().decode(&mut bitmap).ok()?;
 
-        return;
-    };
+    upc_detection(&bitmap, &res)
+}
 
-    let onload = {
-        let reader_ref = reader.clone();
-        Closure::wrap(Box::new(move |_evt: Event| {
-            let Ok(js_val) = reader_ref.result() else {
-                invoke_on_detect(Err(&Error::Internal));
-                invoke_on_stop();
+fn decode_upc_e(gray_data: Vec<u8>, width: u32, height: u32) -> Option<Detection> {
+    let src = Luma8LuminanceSource::new(gray_data, width, height);
+    let binarizer = HybridBinarizer::new(src);
+    let mut bitmap = BinaryBitmap::new(binarizer);
+    let res = UPCEReader.decode(&mut bitmap).ok()?;
 
-                return;
-            };
+    upc_detection(&bitmap, &res)
+}
 
-            let array = Uint8Array::new(&js_val);
-            let mut input_bytes = vec![0u8; array.length() as usize];
-            array.copy_to(&mut input_bytes);
+fn decode_qr_code(gray_data: Vec<u8>, width: u32, height: u32) -> Option<Detection> {
+    let src = Luma8LuminanceSource::new(gray_data, width, height);
+    let binarizer = HybridBinarizer::new(src);
+    let mut bitmap = BinaryBitmap::new(binarizer);
+    let res = QRCodeReader::new().decode(&mut bitmap).ok()?;
 
-            let dyn_image = match ImageReader::new(Cursor::new(&input_bytes)).with_guessed_format()
-            {
-                Ok(rdr) => match rdr.decode() {
-                    Ok(img) => img,
-                    Err(_) => {
-                        invoke_on_detect(Err(&Error::Internal));
-                        invoke_on_stop();
-
-                        return;
-                    }
-                },
-                Err(_) => {
-                    invoke_on_detect(Err(&Error::Internal));
-                    invoke_on_stop();
-
-                    return;
-                }
-            };
-
-            if dyn_image.width() < OPTIMAL_IMAGE_DIMENSION
-                || dyn_image.height() < OPTIMAL_IMAGE_DIMENSION
-            {
-                invoke_on_detect(Err(&Error::NotDetected));
-                invoke_on_stop();
+    // Structured append symbols hold a fragment of the message, which is
+    // validated once the sequence is reassembled.
+    if structured_append::enabled() && StructuredAppend::from_result(&res).is_some() {
+        return Some(Detection::from_result(res.getText().to_string(), &res));
+    }
+    validator::validate(&Format::Rxing(*res.getBarcodeFormat()), res.getText())
+        .map(|value| Detection::from_result(value, &res))
+}
 
-                return;
-            }
+/// Decodes GS1 DataBar Expanded, including the stacked variant, into a GS1
+/// element string.
+fn decode_rss_expanded(gray_data: Vec<u8>, width: u32, height: u32) -> Option<Detection> {
+    let src = Luma8LuminanceSource::new(gray_data, width, height);
+    let binarizer = HybridBinarizer::new(src);
+    let mut bitmap = BinaryBitmap::new(binarizer);
+    let res = RSSExpandedReader::new().decode(&mut bitmap).ok()?;
+    let value = gs1::element_string_from_hri(res.getText())?;
+
+    validator::validate(&Format::Rxing(*res.getBarcodeFormat()), &value)
+        .map(|value| Detection::from_result(value, &res))
+}
 
-            let gray = dyn_image.to_luma8();
-            let full_width = gray.width();
-            let full_height = gray.height();
-
-            let crop_w = full_width / IMAGE_CROP_FACTOR;
-            let crop_h = full_height / IMAGE_CROP_FACTOR;
-            let crop_x = (full_width - crop_w) / 2;
-            let crop_y = (full_height - crop_h) / 2;
-            let mut cropped_gray = gray.clone();
-            let cropped = crop(&mut cropped_gray, crop_x, crop_y, crop_w, crop_h).to_image();
-
-            let (gray_data, w, h) = prepare_image_data(&cropped, cropped.width(), cropped.height());
-
-            let result = match detect_barcode(gray_data, w, h) {
-                Ok(text) => {
-                    invoke_on_detect(Ok(text.as_str()));
-                    invoke_on_stop();
-                    return;
-                }
-                Err(_) => {
-                    let (full_gray_data, full_w, full_h) =
-                        prepare_image_data(&gray, full_width, full_height);
-                    detect_barcode(full_gray_data, full_w, full_h)
-                }
-            };
-
-            match result {
-                Ok(text) => invoke_on_detect(Ok(text.as_str())),
-                Err(e) => invoke_on_detect(Err(&e)),
+/// Returns the formats tried on every image and frame, in order.
+pub fn scan_formats() -> Vec<Format> {
+    SCAN_FORMATS.with(|slot| slot.borrow().clone())
+}
+
+fn detect_barcode(
+    gray_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    formats: &[Format],
+) -> Result<Detection, Error> {
+    formats
+        .iter()
+        .find_map(|format| match format {
+            Format::Rxing(BarcodeFormat::UPC_A) => decode_upc_a(gray_data.clone(), width, height),
+            Format::Rxing(BarcodeFormat::UPC_E) => decode_upc_e(gray_data.clone(), width, height),
+            Format::Rxing(BarcodeFormat::QR_CODE) => {
+                decode_qr_code(gray_data.clone(), width, height)
             }
+            Format::Rxing(BarcodeFormat::RSS_14) => databar::decode(&gray_data, width, height),
+            Format::Rxing(BarcodeFormat::RSS_EXPANDED) => {
+                decode_rss_expanded(gray_data.clone(), width, height)
+            }
+            Format::Pharmacode => pharmacode::decode(&gray_data, width, height),
+            Format::Rxing(_) => None,
+        })
+        .ok_or(Error::NotDetected)
+}
+
+/// Runs the crop/resize/fallback pipeline used for still images.
+pub fn detect_from_gray_image(gray: GrayImage) -> Result<Detection, Error> {
+    if gray.width() < MIN_IMAGE_DIMENSION || gray.height() < MIN_IMAGE_DIMENSION {
+        return Err(Error::ImageTooSmall);
+    }
+
+    let full_width = gray.width();
+    let full_height = gray.height();
+
+    let crop_w = full_width / IMAGE_CROP_FACTOR;
+    let crop_h = full_height / IMAGE_CROP_FACTOR;
+    let crop_x = (full_width - crop_w) / 2;
+    let crop_y = (full_height - crop_h) / 2;
+    let mut cropped_gray = gray.clone();
+    let cropped = crop(&mut cropped_gray, crop_x, crop_y, crop_w, crop_h).to_image();
+
+    let (gray_data, w, h) = prepare_image_data(&cropped, cropped.width(), cropped.height());
+    let formats = scan_formats();
+
+    detect_barcode(gray_data, w, h, &formats)
+        .map(|detection| {
+            let (scale_x, scale_y) = (crop_w as f32 / w as f32, crop_h as f32 / h as f32);
+            detection.map_points(scale_x, scale_y, crop_x, crop_y)
+        })
+        .or_else(|_| {
+            let (full_gray_data, full_w, full_h) =
+                prepare_image_data(&gray, full_width, full_height);
+            let (scale_x, scale_y) = (
+                full_width as f32 / full_w as f32,
+                full_height as f32 / full_h as f32,
+            );
+            detect_barcode(full_gray_data, full_w, full_h, &formats)
+                .map(|detection| detection.map_points(scale_x, scale_y, 0, 0))
+        })
+}
+
+/// Decodes an encoded image (PNG, JPEG, ...) with the `image` crate.
+///
+/// Unknown formats yield `UnsupportedFormat`; recognized but unreadable data
+/// yields `ImageDecodeFailed`.
+pub fn decode_image_bytes(input_bytes: &[u8]) -> Result<GrayImage, Error> {
+    let reader = ImageReader::new(Cursor::new(input_bytes))
+        .with_guessed_format()
+        .map_err(|_| Error::FileReadFailed)?;
+    if reader.format().is_none() {
+        return Err(Error::UnsupportedFormat);
+    }
+
+    match reader.decode() {
+        Ok(dyn_image) => Ok(dyn_image.to_luma8()),
+        Err(ImageError::Unsupported(_)) => Err(Error::UnsupportedFormat),
+        Err(_) => Err(Error::ImageDecodeFailed),
+    }
+}
+
+pub fn canvas_context(canvas: &HtmlCanvasElement) -> Result<CanvasRenderingContext2d, Error> {
+    canvas
+        .get_context("2d")
+        .ok()
+        .flatten()
+        .and_then(|ctx| ctx.dyn_into::<CanvasRenderingContext2d>().ok())
+        .ok_or(Error::Internal)
+}
+
+/// Decodes a blob with the browser's own image decoders, which cover formats
+/// the `image` crate lacks, such as HEIC/HEIF on Safari and AVIF.
+async fn decode_with_browser(blob: &Blob) -> Result<GrayImage, Error> {
+    let window = web_sys::window().ok_or(Error::WindowNotFound)?;
+    let document = window.document().ok_or(Error::DocumentNotFound)?;
+
+    let promise = window
+        .create_image_bitmap_with_blob(blob)
+        .map_err(|_| Error::UnsupportedFormat)?;
+    let bitmap: ImageBitmap = JsFuture::from(promise)
+        .await
+        .map_err(|_| Error::UnsupportedFormat)?
+        .dyn_into()
+        .map_err(|_| Error::Internal)?;
+
+    let (width, height) = (bitmap.width(), bitmap.height());
+    let canvas: HtmlCanvasElement = document
+        .create_element("canvas")
+        .ok()
+        .and_then(|el| el.dyn_into().ok())
+        .ok_or(Error::Internal)?;
+    canvas.set_width(width);
+    canvas.set_height(height);
 
-            invoke_on_stop();
-        }) as Box<dyn FnMut(_)>)
+    let ctx = canvas_context(&canvas)?;
+    ctx.draw_image_with_image_bitmap(&bitmap, 0.0, 0.0)
+        .map_err(|_| Error::Internal)?;
+    bitmap.close();
+    let image_data = ctx
+        .get_image_data(0.0, 0.0, width as f64, height as f64)
+        .map_err(|_| Error::Internal)?;
+
+    GrayImage::from_raw(width, height, rgba_to_gray(&image_data.data())).ok_or(Error::Internal)
+}
+
+/// Reads and scans an encoded image blob, falling back to the browser's
+/// decoders when the `image` crate does not support the format.
+pub async fn detect_from_blob(blob: &Blob) -> Result<Detection, Error> {
+    let buffer = JsFuture::from(blob.array_buffer())
+        .await
+        .map_err(|_| Error::FileReadFailed)?;
+    let input_bytes = Uint8Array::new(&buffer).to_vec();
+
+    // Report why the Rust decoder failed if the browser cannot decode it either.
+    let gray = match decode_image_bytes(&input_bytes) {
+        Ok(gray) => gray,
+        Err(rust_error) => {
+            decode_with_browser(blob)
+                .await
+                .map_err(|browser_error| match browser_error {
+                    Error::UnsupportedFormat => rust_error,
+                    other => other,
+                })?
+        }
     };
 
-    reader.set_onload(Some(onload.as_ref().unchecked_ref()));
-    onload.forget();
+    detect_from_gray_image(gray)
+}
+
+/// Scans raw RGBA pixels, as returned by `CanvasRenderingContext2D.getImageData`.
+pub fn detect_from_rgba(rgba: &[u8], width: u32, height: u32) -> Result<Detection, Error> {
+    let gray = GrayImage::from_raw(width, height, rgba_to_gray(rgba)).ok_or(Error::Internal)?;
+
+    detect_from_gray_image(gray)
+}
+
+/// Converts RGBA pixels to 8-bit luma using the BT.601 weights.
+pub fn rgba_to_gray(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4)
+        .map(|px| {
+            let r = px[0] as f32;
+            let g = px[1] as f32;
+            let b = px[2] as f32;
+            (0.299 * r + 0.587 * g + 0.114 * b) as u8
+        })
+        .collect()
+}
 
-    if reader.read_as_array_buffer(&file).is_err() {
-        invoke_on_detect(Err(&Error::Internal));
-        invoke_on_stop();
+/// Reassembles structured append sequences and applies the caller-supplied
+/// filter to a still-image result.
+pub fn filter_result(result: Result<Detection, Error>) -> Result<Detection, Error> {
+    match structured_append::assemble(result) {
+        Ok(detection) if !validator::passes_filter(&detection) => Err(Error::Rejected),
+        other => other,
     }
 }
 
-pub fn detect_from_stream(gray_data: Vec<u8>, width: u32, height: u32) -> Result<String, Error> {
-    if width < MIN_IMAGE_DIMENSION || height < MIN_IMAGE_DIMENSION {
-        return Err(Error::NotDetected);
+/// Reports a still-image result through `on_detect` and ends the scan.
+pub fn emit_image_result(result: Result<Detection, Error>) {
+    let result = filter_result(result);
+    match &result {
+        Ok(_) => feedback::success(),
+        Err(Error::Rejected) => feedback::rejected(),
+        Err(_) => {}
     }
 
+    invoke_on_detect(result.as_ref());
+    invoke_on_stop();
+}
+
+pub fn detect_from_image(blob: &Blob) {
+    let blob = blob.clone();
+    spawn_local(async move {
+        emit_image_result(detect_from_blob(&blob).await);
+    });
+}
+
+/// Returns the centered region `(x, y, width, height)` of a stream frame
+/// that is searched for barcodes.
+pub fn stream_region(width: u32, height: u32) -> (u32, u32, u32, u32) {
     let crop_w = width / STREAM_CROP_FACTOR;
     let crop_h = height / STREAM_CROP_FACTOR;
-    let crop_x = (width - crop_w) / 2;
-    let crop_y = (height - crop_h) / 2;
+    ((width - crop_w) / 2, (height - crop_h) / 2, crop_w, crop_h)
+}
+
+/// Decodes the stream region of a frame, trying `formats` in order.
+pub fn detect_from_stream(
+    gray_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    formats: &[Format],
+) -> Result<Detection, Error> {
+    if width < MIN_IMAGE_DIMENSION || height < MIN_IMAGE_DIMENSION {
+        return Err(Error::NotDetected);
+    }
+
+    let (crop_x, crop_y, crop_w, crop_h) = stream_region(width, height);
 
     let mut cropped = vec![0u8; (crop_w * crop_h) as usize];
     for y in 0..crop_h {
@@ -184,5 +575,173 @@ pub fn detect_from_stream(gray_data: Vec<u8>, width: u32, height: u32) -> Result
         }
     }
 
-    detect_barcode(cropped, crop_w, crop_h)
+    detect_barcode(cropped, crop_w, crop_h, formats)
+        .map(|detection| detection.map_points(1.0, 1.0, crop_x, crop_y))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use image::Luma;
+    use rxing::{
+        MultiFormatWriter, Writer,
+        oned::{EAN13Writer, OneDimensionalCodeWriter, UPCEWriter},
+    };
+
+    use super::*;
+
+    /// Odd-parity (L) digit patterns of EAN add-ons; the even-parity (G)
+    /// patterns are their complements reversed.
+    const L_PATTERNS: [&str; 10] = [
+        "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111", "0111011",
+        "0110111", "0001011",
+    ];
+    /// EAN-5 digit parities by check value.
+    const EAN_5_PARITIES: [&str; 10] = [
+        "GGLLL", "GLGLL", "GLLGL", "GLLLG", "LGGLL", "LLGGL", "LLLGG", "LGLGL", "LGLLG", "LLGLG",
+    ];
+
+    fn modules(pattern: &str) -> impl Iterator<Item = bool> + '_ {
+        pattern.bytes().map(|b| b == b'1')
+    }
+
+    /// Encodes an EAN-2 or EAN-5 add-on, start guard first.
+    fn add_on(digits: &str) -> Vec<bool> {
+        let d: Vec<usize> = digits.bytes().map(|b| (b - b'0') as usize).collect();
+        let parities = if d.len() == 2 {
+            ["LL", "LG", "GL", "GG"][(d[0] * 10 + d[1]) % 4]
+        } else {
+            let check = (3 * (d[0] + d[2] + d[4]) + 9 * (d[1] + d[3])) % 10;
+            EAN_5_PARITIES[check]
+        };
+
+        let mut out: Vec<bool> = modules("1011").collect();
+        for (i, (digit, parity)) in d.iter().zip(parities.chars()).enumerate() {
+            if i > 0 {
+                out.extend(modules("01"));
+            }
+            let l = modules(L_PATTERNS[*digit]);
+            if parity == 'G' {
+                out.extend(l.map(|m| !m).collect::<Vec<_>>().into_iter().rev());
+            } else {
+                out.extend(l);
+            }
+        }
+        out
+    }
+
+    /// Renders a UPC symbol and an optional add-on, 3 pixels per module.
+    fn upc_image(symbol: Vec<bool>, add_on_digits: Option<&str>) -> GrayImage {
+        let (quiet, gap, module) = (10, 9, 3);
+        let mut row = vec![false; quiet];
+        row.extend(symbol);
+        if let Some(digits) = add_on_digits {
+            row.extend(std::iter::repeat_n(false, gap));
+            row.extend(add_on(digits));
+        }
+        row.extend(std::iter::repeat_n(false, quiet));
+        GrayImage::from_fn(row.len() as u32 * module, 60, |x, _| {
+            Luma([if row[(x / module) as usize] { 0 } else { 255 }])
+        })
+    }
+
+    fn upc_a(contents: &str, add_on_digits: Option<&str>) -> GrayImage {
+        let symbol = EAN13Writer.encode_oned(&format!("0{contents}")).unwrap();
+        upc_image(symbol, add_on_digits)
+    }
+
+    fn upc_e(contents: &str, add_on_digits: Option<&str>) -> GrayImage {
+        upc_image(UPCEWriter.encode_oned(contents).unwrap(), add_on_digits)
+    }
+
+    fn read(
+        decode: fn(Vec<u8>, u32, u32) -> Option<Detection>,
+        image: GrayImage,
+    ) -> Option<(String, Option<String>)> {
+        let (width, height) = image.dimensions();
+        decode(image.into_raw(), width, height).map(|d| (d.value, d.add_on))
+    }
+
+    fn found(value: &str, add_on: Option<&str>) -> Option<(String, Option<String>)> {
+        Some((value.to_string(), add_on.map(str::to_string)))
+    }
+
+    /// Renders `contents` as a QR Code with `module` pixels per module.
+    pub(crate) fn qr_image(contents: &str, module: u32) -> GrayImage {
+        let matrix = MultiFormatWriter
+            .encode(contents, &BarcodeFormat::QR_CODE, 0, 0)
+            .unwrap();
+        let quiet = 4;
+        let side = (matrix.getWidth() + 2 * quiet) * module;
+        GrayImage::from_fn(side, side, |x, y| {
+            let (mx, my) = (x / module, y / module);
+            let dark = (quiet..matrix.getWidth() + quiet).contains(&mx)
+                && (quiet..matrix.getHeight() + quiet).contains(&my)
+                && matrix.get(mx - quiet, my - quiet);
+            Luma([if dark { 0 } else { 255 }])
+        })
+    }
+
+    #[test]
+    fn still_images_below_the_optimal_size_are_scanned() {
+        let image = qr_image("wascan", 8);
+        assert!(image.width() < OPTIMAL_IMAGE_DIMENSION);
+
+        let detection = detect_from_gray_image(image).unwrap();
+        assert_eq!(detection.value, "wascan");
+    }
+
+    #[test]
+    fn still_images_below_the_minimum_size_are_rejected() {
+        let image = GrayImage::new(MIN_IMAGE_DIMENSION - 1, 400);
+        assert!(matches!(
+            detect_from_gray_image(image),
+            Err(Error::ImageTooSmall)
+        ));
+    }
+
+    #[test]
+    fn reads_upc_a_with_either_add_on() {
+        let value = "036000291452";
+        assert_eq!(read(decode_upc_a, upc_a(value, None)), found(value, None));
+        assert_eq!(
+            read(decode_upc_a, upc_a(value, Some("12"))),
+            found(value, Some("12"))
+        );
+        assert_eq!(
+            read(decode_upc_a, upc_a(value, Some("52495"))),
+            found(value, Some("52495"))
+        );
+    }
+
+    #[test]
+    fn reads_upc_e_with_either_add_on() {
+        let value = "04252614";
+        assert_eq!(read(decode_upc_e, upc_e(value, None)), found(value, None));
+        assert_eq!(
+            read(decode_upc_e, upc_e(value, Some("07"))),
+            found(value, Some("07"))
+        );
+        assert_eq!(
+            read(decode_upc_e, upc_e(value, Some("90000"))),
+            found(value, Some("90000"))
+        );
+        // A UPC-A image is not read as UPC-E, nor the reverse.
+        assert_eq!(read(decode_upc_e, upc_a("036000291452", None)), None);
+        assert_eq!(read(decode_upc_a, upc_e(value, None)), None);
+    }
+
+    #[test]
+    fn required_add_ons_reject_bare_symbols() {
+        REQUIRE_ADD_ON.with(|slot| slot.set(true));
+        assert_eq!(read(decode_upc_a, upc_a("036000291452", None)), None);
+        assert_eq!(read(decode_upc_e, upc_e("04252614", None)), None);
+        assert_eq!(
+            read(decode_upc_a, upc_a("036000291452", Some("12"))),
+            found("036000291452", Some("12"))
+        );
+        assert_eq!(
+            read(decode_upc_e, upc_e("04252614", Some("52495"))),
+            found("04252614", Some("52495"))
+        );
+    }
 }
@@ -30,6 +30,45 @@ pub enum Error {
     #[error("ERR_NOT_DETECTED")]
     NotDetected,
 
+    #[error("ERR_INVALID_OPTIONS")]
+    InvalidOptions,
+
+    #[error("ERR_REJECTED")]
+    Rejected,
+
+    #[error("ERR_UNSUPPORTED_FORMAT")]
+    UnsupportedFormat,
+
+    #[error("ERR_IMAGE_DECODE_FAILED")]
+    ImageDecodeFailed,
+
+    #[error("ERR_IMAGE_TOO_SMALL")]
+    ImageTooSmall,
+
+    #[error("ERR_FILE_READ_FAILED")]
+    FileReadFailed,
+
+    #[error("ERR_CAMERA_IN_USE")]
+    CameraInUse,
+
+    #[error("ERR_OVERCONSTRAINED")]
+    Overconstrained,
+
+    #[error("ERR_INSECURE_CONTEXT")]
+    InsecureContext,
+
+    #[error("ERR_ENCODE_FAILED")]
+    EncodeFailed,
+
+    #[error("ERR_SEQUENCE_INCOMPLETE")]
+    SequenceIncomplete,
+
+    #[error("ERR_SEQUENCE_PARITY")]
+    SequenceParityMismatch,
+
+    #[error("ERR_PAYLOAD_DECODE_FAILED")]
+    PayloadDecodeFailed,
+
     #[error("ERR_INTERNAL")]
     Internal,
 }
@@ -45,14 +84,59 @@ impl Error {
             Error::NoMedia => "ERR_NO_MEDIA",
             Error::NoPermission => "ERR_NO_PERMISSION",
             Error::NotDetected => "ERR_NOT_DETECTED",
+            Error::InvalidOptions => "ERR_INVALID_OPTIONS",
+            Error::Rejected => "ERR_REJECTED",
+            Error::UnsupportedFormat => "ERR_UNSUPPORTED_FORMAT",
+            Error::ImageDecodeFailed => "ERR_IMAGE_DECODE_FAILED",
+            Error::ImageTooSmall => "ERR_IMAGE_TOO_SMALL",
+            Error::FileReadFailed => "ERR_FILE_READ_FAILED",
+            Error::CameraInUse => "ERR_CAMERA_IN_USE",
+            Error::Overconstrained => "ERR_OVERCONSTRAINED",
+            Error::InsecureContext => "ERR_INSECURE_CONTEXT",
+            Error::EncodeFailed => "ERR_ENCODE_FAILED",
+            Error::SequenceIncomplete => "ERR_SEQUENCE_INCOMPLETE",
+            Error::SequenceParityMismatch => "ERR_SEQUENCE_PARITY",
+            Error::PayloadDecodeFailed => "ERR_PAYLOAD_DECODE_FAILED",
             Error::Internal => "ERR_INTERNAL",
         }
     }
+
+    /// Returns a human-readable description of the error.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Error::WindowNotFound => "The global window object is not available",
+            Error::DocumentNotFound => "The document object is not available",
+            Error::NoFileSelected => "No file was selected",
+            Error::InvalidMime => "The selected file is not an image or PDF document",
+            Error::InvalidVideoElementId => "No video element exists with the given id",
+            Error::NoMedia => "No camera could be opened",
+            Error::NoPermission => "Camera access was denied",
+            Error::NotDetected => "No barcode was detected",
+            Error::InvalidOptions => "The options object contains an invalid value",
+            Error::Rejected => "The detected value was rejected by the filter",
+            Error::UnsupportedFormat => "The file format is not supported",
+            Error::ImageDecodeFailed => "The image data could not be decoded",
+            Error::ImageTooSmall => "The image is too small for reliable detection",
+            Error::FileReadFailed => "The file could not be read",
+            Error::CameraInUse => "The camera is already in use by another application",
+            Error::Overconstrained => "No camera satisfies the requested constraints",
+            Error::InsecureContext => "Camera access requires a secure (HTTPS) context",
+            Error::EncodeFailed => "The value cannot be encoded in the requested format",
+            Error::SequenceIncomplete => {
+                "The barcode is part of a structured append sequence that is not complete yet"
+            }
+            Error::SequenceParityMismatch => {
+                "The reassembled structured append message does not match its parity"
+            }
+            Error::PayloadDecodeFailed => "The barcode payload could not be decoded",
+            Error::Internal => "An internal error occurred",
+        }
+    }
 }
 
 impl From<Error> for JsValue {
     fn from(error: Error) -> Self {
-        JsValue::from_str(error.code())
+        error_to_js(&error, None)
     }
 }
 
@@ -80,6 +164,19 @@ pub fn error_codes() -> JsValue {
         ("NoMedia", Error::NoMedia),
         ("NoPermission", Error::NoPermission),
         ("NotDetected", Error::NotDetected),
+        ("InvalidOptions", Error::InvalidOptions),
+        ("Rejected", Error::Rejected),
+        ("UnsupportedFormat", Error::UnsupportedFormat),
+        ("ImageDecodeFailed", Error::ImageDecodeFailed),
+        ("ImageTooSmall", Error::ImageTooSmall),
+        ("FileReadFailed", Error::FileReadFailed),
+        ("CameraInUse", Error::CameraInUse),
+        ("Overconstrained", Error::Overconstrained),
+        ("InsecureContext", Error::InsecureContext),
+        ("EncodeFailed", Error::EncodeFailed),
+        ("SequenceIncomplete", Error::SequenceIncomplete),
+        ("SequenceParityMismatch", Error::SequenceParityMismatch),
+        ("PayloadDecodeFailed", Error::PayloadDecodeFailed),
         ("Internal", Error::Internal),
     ];
 
@@ -92,6 +189,41 @@ pub fn error_codes() -> JsValue {
     obj.into()
 }
 
-pub fn error_to_js(error: &Error) -> JsValue {
-    JsValue::from(*error)
+/// Builds the `{ code, message, cause? }` object describing an error.
+///
+/// `cause` is the browser error that led to `error`, reduced to its `name`
+/// and `message` so that it survives serialization.
+pub fn error_to_js(error: &Error, cause: Option<&JsValue>) -> JsValue {
+    let obj = Object::new();
+    let _ = set_str(&obj, "code", error.code());
+    let _ = set_str(&obj, "message", error.message());
+    if let Some(cause) = cause {
+        let _ = Reflect::set(&obj, &JsValue::from_str("cause"), &cause_to_js(cause));
+    }
+
+    obj.into()
+}
+
+fn cause_to_js(cause: &JsValue) -> JsValue {
+    if let Some(message) = cause.as_string() {
+        let obj = Object::new();
+        let _ = set_str(&obj, "name", "Error");
+        let _ = set_str(&obj, "message", &message);
+        return obj.into();
+    }
+
+    let property = |key: &str| {
+        Reflect::get(cause, &JsValue::from_str(key))
+            .ok()
+            .and_then(|value| value.as_string())
+    };
+
+    let obj = Object::new();
+    let _ = set_str(
+        &obj,
+        "name",
+        &property("name").unwrap_or_else(|| "Error".into()),
+    );
+    let _ = set_str(&obj, "message", &property("message").unwrap_or_default());
+    obj.into()
 }
@@ -1,17 +1,28 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use js_sys::{Function, Object, Reflect};
 use wasm_bindgen::{JsValue, prelude::wasm_bindgen};
 use web_sys::console;
 
-use crate::error::{self, Error};
+use crate::{
+    content,
+    detector::Detection,
+    error::{self, Error},
+};
 
 thread_local! {
     static ON_START: RefCell<Option<Function>> = const { RefCell::new(None) };
     static ON_DETECT: RefCell<Option<Function>> = const { RefCell::new(None) };
     static ON_STOP: RefCell<Option<Function>> = const { RefCell::new(None) };
+    static ON_DROP_HOVER: RefCell<Option<Function>> = const { RefCell::new(None) };
+    static ON_BATCH_COMPLETE: RefCell<Option<Function>> = const { RefCell::new(None) };
+    static ON_STATS: RefCell<Option<Function>> = const { RefCell::new(None) };
+    static ON_STRUCTURED_APPEND_PROGRESS: RefCell<Option<Function>> = const { RefCell::new(None) };
+    static STATS_INTERVAL_MS: Cell<u32> = const { Cell::new(DEFAULT_STATS_INTERVAL_MS) };
 }
 
+const DEFAULT_STATS_INTERVAL_MS: u32 = 1000;
+
 pub fn invoke_on_start() {
     ON_START.with(|slot| {
         if let Some(cb) = &*slot.borrow() {
@@ -23,38 +34,144 @@ pub fn invoke_on_start() {
     });
 }
 
-pub fn invoke_on_detect(result: Result<&str, &Error>) {
-    let cb_arg = {
-        let obj = Object::new();
-        match result {
-            Ok(value) => {
-                let _ = Reflect::set(
-                    &obj,
-                    &JsValue::from_str("success"),
-                    &JsValue::from_bool(true),
-                );
-                let _ = Reflect::set(&obj, &JsValue::from_str("value"), &JsValue::from_str(value));
-                obj.into()
-            }
-            Err(error) => {
-                let _ = Reflect::set(
-                    &obj,
-                    &JsValue::from_str("success"),
-                    &JsValue::from_bool(false),
-                );
-                let _ = Reflect::set(
-                    &obj,
-                    &JsValue::from_str("error"),
-                    &error::error_to_js(error),
-                );
-                obj.into()
+/// Builds the `{ value, format }` object describing a detection.
+pub fn detection_to_js(detection: &Detection) -> Object {
+    let obj = Object::new();
+    let _ = Reflect::set(
+        &obj,
+        &JsValue::from_str("value"),
+        &JsValue::from_str(&detection.value),
+    );
+    let _ = Reflect::set(
+        &obj,
+        &JsValue::from_str("format"),
+        &JsValue::from_str(detection.format.name()),
+    );
+    if let Some(part) = detection.structured_append {
+        let _ = Reflect::set(&obj, &JsValue::from_str("structuredAppend"), &part.to_js());
+    }
+    if let Some(add_on) = &detection.add_on {
+        let _ = Reflect::set(
+            &obj,
+            &JsValue::from_str("addOn"),
+            &JsValue::from_str(add_on),
+        );
+    }
+    if detection.linked_composite {
+        let _ = Reflect::set(&obj, &JsValue::from_str("linkedComposite"), &JsValue::TRUE);
+    }
+    obj
+}
+
+fn detect_payload(result: Result<&Detection, &Error>) -> Object {
+    match result {
+        Ok(detection) => {
+            let obj = detection_to_js(detection);
+            let _ = Reflect::set(
+                &obj,
+                &JsValue::from_str("success"),
+                &JsValue::from_bool(true),
+            );
+            if content::content_parsing_enabled()
+                && let Some(parsed) = content::classify(&detection.value)
+            {
+                parsed.attach_to(&obj);
             }
+            obj
         }
-    };
+        Err(error) => error_payload(error, None),
+    }
+}
 
+fn error_payload(error: &Error, cause: Option<&JsValue>) -> Object {
+    let obj = Object::new();
+    let _ = Reflect::set(
+        &obj,
+        &JsValue::from_str("success"),
+        &JsValue::from_bool(false),
+    );
+    let _ = Reflect::set(
+        &obj,
+        &JsValue::from_str("error"),
+        &error::error_to_js(error, cause),
+    );
+    obj
+}
+
+fn call_on_detect(cb_arg: &JsValue) {
     ON_DETECT.with(|slot| {
         if let Some(cb) = &*slot.borrow() {
-            let res = cb.call1(&JsValue::NULL, &cb_arg);
+            let res = cb.call1(&JsValue::NULL, cb_arg);
+            if let Err(e) = res {
+                console::error_1(&e);
+            }
+        }
+    });
+}
+
+pub fn invoke_on_detect(result: Result<&Detection, &Error>) {
+    call_on_detect(&detect_payload(result).into());
+}
+
+/// Reports an error together with the browser error that caused it.
+pub fn invoke_on_detect_error(error: &Error, cause: &JsValue) {
+    call_on_detect(&error_payload(error, Some(cause)).into());
+}
+
+/// Reports a result carrying extra fields that identify its source, such as
+/// the file of a batch or the page of a PDF.
+pub fn invoke_on_detect_tagged(result: Result<&Detection, &Error>, tags: &[(&str, JsValue)]) {
+    let obj = detect_payload(result);
+    for (key, value) in tags {
+        let _ = Reflect::set(&obj, &JsValue::from_str(key), value);
+    }
+
+    call_on_detect(&obj.into());
+}
+
+pub fn invoke_on_batch_complete(total: u32, detected: u32) {
+    ON_BATCH_COMPLETE.with(|slot| {
+        if let Some(cb) = &*slot.borrow() {
+            let summary = Object::new();
+            let _ = Reflect::set(
+                &summary,
+                &JsValue::from_str("total"),
+                &JsValue::from_f64(total as f64),
+            );
+            let _ = Reflect::set(
+                &summary,
+                &JsValue::from_str("detected"),
+                &JsValue::from_f64(detected as f64),
+            );
+            let _ = Reflect::set(
+                &summary,
+                &JsValue::from_str("failed"),
+                &JsValue::from_f64(total.saturating_sub(detected) as f64),
+            );
+
+            let res = cb.call1(&JsValue::NULL, &summary);
+            if let Err(e) = res {
+                console::error_1(&e);
+            }
+        }
+    });
+}
+
+pub fn invoke_on_stats(stats: &JsValue) {
+    ON_STATS.with(|slot| {
+        if let Some(cb) = &*slot.borrow() {
+            let res = cb.call1(&JsValue::NULL, stats);
+            if let Err(e) = res {
+                console::error_1(&e);
+            }
+        }
+    });
+}
+
+pub fn invoke_on_structured_append_progress(progress: &JsValue) {
+    ON_STRUCTURED_APPEND_PROGRESS.with(|slot| {
+        if let Some(cb) = &*slot.borrow() {
+            let res = cb.call1(&JsValue::NULL, progress);
             if let Err(e) = res {
                 console::error_1(&e);
             }
@@ -62,6 +179,13 @@ pub fn invoke_on_detect(result: Result<&str, &Error>) {
     });
 }
 
+/// Returns the `on_stats` interval, or `None` if no callback is registered.
+pub fn stats_interval_ms() -> Option<u32> {
+    ON_STATS
+        .with(|slot| slot.borrow().is_some())
+        .then(|| STATS_INTERVAL_MS.with(|interval| interval.get()))
+}
+
 pub fn invoke_on_stop() {
     ON_STOP.with(|slot| {
         if let Some(cb) = &*slot.borrow() {
@@ -73,6 +197,17 @@ pub fn invoke_on_stop() {
     });
 }
 
+pub fn invoke_on_drop_hover(active: bool) {
+    ON_DROP_HOVER.with(|slot| {
+        if let Some(cb) = &*slot.borrow() {
+            let res = cb.call1(&JsValue::NULL, &JsValue::from_bool(active));
+            if let Err(e) = res {
+                console::error_1(&e);
+            }
+        }
+    });
+}
+
 /// Registers a callback function to be called when scanning starts.
 #[wasm_bindgen]
 pub fn on_start(cb: Function) {
@@ -84,7 +219,17 @@ pub fn on_start(cb: Function) {
 /// The callback receives an object with:
 /// - `success: boolean` - true if detection succeeded, false otherwise
 /// - `value?: string` - the detected barcode (only present if success is true)
-/// - `error?: string` - the error code (only present if success is false)
+/// - `format?: string` - the barcode symbology, e.g. `"upc_a"` (only present if success is true)
+/// - `contentType?: string` - the classified content type, when content parsing is enabled
+/// - `content?: object` - the parsed fields for `contentType`
+/// - `error?: object` - `{ code, message, cause? }` (only present if success is false); `code`
+///   is one of `error_codes()` and `cause` holds the `name` and `message` of the browser error
+/// - `fileName?: string`, `index?: number` - the source file (only present in batch mode)
+/// - `page?: number` - the 1-based source page (only present for PDF documents)
+/// - `source?: string` - `"keyboard"` for scans captured from keyboard-wedge scanners
+/// - `backend?: string`, `backendsAgree?: boolean` - the stream detection backend (see `set_detection_backend`)
+/// - `structuredAppend?: { index, total, parity }` - the symbol's place in a QR Code structured
+///   append sequence (only present when reassembly is disabled)
 #[wasm_bindgen]
 pub fn on_detect(cb: Function) {
     ON_DETECT.with(|slot| *slot.borrow_mut() = Some(cb));
@@ -95,3 +240,53 @@ pub fn on_detect(cb: Function) {
 pub fn on_stop(cb: Function) {
     ON_STOP.with(|slot| *slot.borrow_mut() = Some(cb));
 }
+
+/// Registers a callback function to be called when files are dragged over or
+/// out of a drop zone bound with `bind_drop_zone`.
+///
+/// The callback receives `true` when a drag enters the zone and `false` when
+/// it leaves or the files are dropped.
+#[wasm_bindgen]
+pub fn on_drop_hover(cb: Function) {
+    ON_DROP_HOVER.with(|slot| *slot.borrow_mut() = Some(cb));
+}
+
+/// Registers a callback function to be called when every file of a batch
+/// has been scanned.
+///
+/// The callback receives `{ total: number, detected: number, failed: number }`.
+#[wasm_bindgen]
+pub fn on_batch_complete(cb: Function) {
+    ON_BATCH_COMPLETE.with(|slot| *slot.borrow_mut() = Some(cb));
+}
+
+/// Registers a callback function to be called periodically with stream scan
+/// statistics while the camera is scanning, and once more when it stops.
+///
+/// The callback receives the same object as `get_scan_stats`.
+///
+/// ## Arguments
+/// * `cb` - The callback function, or `null` to stop reporting
+/// * `interval_ms` - Minimum time between calls in milliseconds (default `1000`)
+#[wasm_bindgen]
+pub fn on_stats(cb: Option<Function>, interval_ms: Option<u32>) {
+    ON_STATS.with(|slot| *slot.borrow_mut() = cb);
+    STATS_INTERVAL_MS
+        .with(|interval| interval.set(interval_ms.unwrap_or(DEFAULT_STATS_INTERVAL_MS).max(1)));
+}
+
+/// Registers a callback function to be called when a new symbol of a QR Code
+/// structured append sequence is read.
+///
+/// The callback receives an object with:
+/// - `index: number` - the 0-based position of the symbol just read
+/// - `received: number`, `total: number` - symbols read so far and in the whole sequence
+/// - `parity: number` - the parity byte identifying the sequence
+/// - `missing: number[]` - positions of the symbols still to be read
+///
+/// The concatenated message is reported through `on_detect` once every
+/// symbol has been read.
+#[wasm_bindgen]
+pub fn on_structured_append_progress(cb: Option<Function>) {
+    ON_STRUCTURED_APPEND_PROGRESS.with(|slot| *slot.borrow_mut() = cb);
+}
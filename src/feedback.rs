@@ -0,0 +1,242 @@
+use std::cell::RefCell;
+
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::JsValue;
+use web_sys::{AudioContext, AudioContextState, OscillatorType};
+
+use crate::{error::Error, options};
+
+/// A beep synthesized with a single oscillator.
+#[derive(Clone, Copy)]
+struct Tone {
+    frequency: f32,
+    duration_ms: u32,
+    volume: f32,
+    wave: OscillatorType,
+}
+
+/// Sound and vibration played for one kind of outcome. `None` disables it.
+#[derive(Clone)]
+struct Cue {
+    tone: Option<Tone>,
+    vibration: Option<Vec<u32>>,
+}
+
+#[derive(Clone)]
+struct FeedbackOptions {
+    success: Cue,
+    rejected: Cue,
+}
+
+impl Default for FeedbackOptions {
+    fn default() -> Self {
+        FeedbackOptions {
+            success: Cue {
+                tone: Some(Tone {
+                    frequency: 1760.0,
+                    duration_ms: 120,
+                    volume: 0.3,
+                    wave: OscillatorType::Sine,
+                }),
+                vibration: Some(vec![100]),
+            },
+            rejected: Cue {
+                tone: Some(Tone {
+                    frequency: 220.0,
+                    duration_ms: 250,
+                    volume: 0.3,
+                    wave: OscillatorType::Square,
+                }),
+                vibration: Some(vec![60, 60, 60]),
+            },
+        }
+    }
+}
+
+thread_local! {
+    static FEEDBACK_OPTIONS: RefCell<Option<FeedbackOptions>> = const { RefCell::new(None) };
+    static AUDIO_CONTEXT: RefCell<Option<AudioContext>> = const { RefCell::new(None) };
+}
+
+fn parse_tone(value: JsValue, default: Option<Tone>) -> Result<Option<Tone>, Error> {
+    if value.as_bool() == Some(false) {
+        return Ok(None);
+    }
+
+    let obj = options::as_object(&value)?;
+    let base = default.unwrap_or(Tone {
+        frequency: 880.0,
+        duration_ms: 150,
+        volume: 0.3,
+        wave: OscillatorType::Sine,
+    });
+
+    let wave = match options::get(&obj, "type") {
+        Some(value) => match OscillatorType::from_js_value(&value) {
+            Some(OscillatorType::Custom) | None => return Err(Error::InvalidOptions),
+            Some(wave) => wave,
+        },
+        None => base.wave,
+    };
+
+    Ok(Some(Tone {
+        frequency: options::get_f64(&obj, "frequency")?.map_or(base.frequency, |f| f as f32),
+        duration_ms: options::get_u32(&obj, "duration")?.unwrap_or(base.duration_ms),
+        volume: options::get_f64(&obj, "volume")?.map_or(base.volume, |v| v.clamp(0.0, 1.0) as f32),
+        wave,
+    }))
+}
+
+fn parse_vibration(value: JsValue) -> Result<Option<Vec<u32>>, Error> {
+    if value.as_bool() == Some(false) {
+        return Ok(None);
+    }
+    if let Some(ms) = value.as_f64() {
+        return Ok(Some(vec![ms.max(0.0) as u32]));
+    }
+    if !Array::is_array(&value) {
+        return Err(Error::InvalidOptions);
+    }
+
+    Array::from(&value)
+        .iter()
+        .map(|step| {
+            step.as_f64()
+                .filter(|ms| *ms >= 0.0)
+                .map(|ms| ms as u32)
+                .ok_or(Error::InvalidOptions)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+fn parse_cue(
+    obj: &Object,
+    tone_key: &str,
+    vibration_key: &str,
+    default: Cue,
+) -> Result<Cue, Error> {
+    Ok(Cue {
+        tone: match options::get(obj, tone_key) {
+            Some(value) => parse_tone(value, default.tone)?,
+            None => default.tone,
+        },
+        vibration: match options::get(obj, vibration_key) {
+            Some(value) => parse_vibration(value)?,
+            None => default.vibration,
+        },
+    })
+}
+
+pub fn set_feedback_options(options: &JsValue) -> Result<(), Error> {
+    let obj = options::as_object(options)?;
+    if !options::get_bool(&obj, "enabled")?.unwrap_or(true) {
+        FEEDBACK_OPTIONS.with(|slot| *slot.borrow_mut() = None);
+        return Ok(());
+    }
+
+    let defaults = FeedbackOptions::default();
+    let mut parsed = FeedbackOptions {
+        success: parse_cue(&obj, "success", "successVibration", defaults.success)?,
+        rejected: parse_cue(&obj, "rejected", "rejectedVibration", defaults.rejected)?,
+    };
+
+    if !options::get_bool(&obj, "sound")?.unwrap_or(true) {
+        parsed.success.tone = None;
+        parsed.rejected.tone = None;
+    }
+    if !options::get_bool(&obj, "vibrate")?.unwrap_or(true) {
+        parsed.success.vibration = None;
+        parsed.rejected.vibration = None;
+    }
+
+    // Creating the context here, typically inside a click handler, lets
+    // browsers with autoplay restrictions start it in the running state.
+    if parsed.success.tone.is_some() || parsed.rejected.tone.is_some() {
+        audio_context();
+    }
+
+    FEEDBACK_OPTIONS.with(|slot| *slot.borrow_mut() = Some(parsed));
+    Ok(())
+}
+
+fn audio_context() -> Option<AudioContext> {
+    AUDIO_CONTEXT.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        if slot.is_none() {
+            *slot = AudioContext::new().ok();
+        }
+        slot.clone()
+    })
+}
+
+fn play_tone(tone: &Tone) {
+    let Some(ctx) = audio_context() else {
+        return;
+    };
+    if ctx.state() == AudioContextState::Suspended {
+        let _ = ctx.resume();
+    }
+
+    let (Ok(oscillator), Ok(gain)) = (ctx.create_oscillator(), ctx.create_gain()) else {
+        return;
+    };
+
+    let start = ctx.current_time();
+    let end = start + tone.duration_ms as f64 / 1000.0;
+
+    oscillator.set_type(tone.wave);
+    oscillator.frequency().set_value(tone.frequency);
+
+    // Fading out over the tone avoids the click of an abrupt stop.
+    let volume = gain.gain();
+    let _ = volume.set_value_at_time(tone.volume, start);
+    let _ = volume.linear_ramp_to_value_at_time(0.0, end);
+
+    if oscillator.connect_with_audio_node(&gain).is_err()
+        || gain.connect_with_audio_node(&ctx.destination()).is_err()
+    {
+        return;
+    }
+    let _ = oscillator.start_with_when(start);
+    let _ = oscillator.stop_with_when(end);
+}
+
+fn vibrate(pattern: &[u32]) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+
+    let steps: Array = pattern.iter().map(|&ms| JsValue::from(ms)).collect();
+    // Unsupported on iOS; `vibrate` is simply absent there.
+    if Reflect::has(&window.navigator(), &JsValue::from_str("vibrate")).unwrap_or(false) {
+        window.navigator().vibrate_with_pattern(&steps);
+    }
+}
+
+fn play(select: impl Fn(&FeedbackOptions) -> &Cue) {
+    FEEDBACK_OPTIONS.with(|slot| {
+        let slot = slot.borrow();
+        let Some(options) = slot.as_ref() else {
+            return;
+        };
+
+        let cue = select(options);
+        if let Some(tone) = &cue.tone {
+            play_tone(tone);
+        }
+        if let Some(pattern) = &cue.vibration {
+            vibrate(pattern);
+        }
+    });
+}
+
+/// Plays the success cue, if feedback is enabled.
+pub fn success() {
+    play(|options| &options.success);
+}
+
+/// Plays the cue for values rejected by the filter.
+pub fn rejected() {
+    play(|options| &options.rejected);
+}
@@ -0,0 +1,564 @@
+use std::{fmt::Write as _, io::Cursor};
+
+use image::{GrayImage, ImageFormat, Luma};
+use js_sys::{Object, Uint8Array};
+use rxing::{
+    BarcodeFormat, EncodeHintValue, EncodeHints, MultiFormatWriter, Writer,
+    datamatrix::encoder::{DefaultPlacement, SymbolInfoLookup, SymbolShapeHint, error_correction},
+};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::HtmlCanvasElement;
+
+use crate::{
+    detector::{canvas_context, format_from_name},
+    error::Error,
+    options,
+};
+
+/// rxing's escape character for the FNC1 function code in Code 128 input.
+const CODE_128_FNC1: char = '\u{f1}';
+const GROUP_SEPARATOR: char = '\u{1d}';
+const DATA_MATRIX_FNC1: u8 = 232;
+const DATA_MATRIX_PAD: u8 = 129;
+
+const DEFAULT_MODULE_SIZE: u32 = 4;
+const DEFAULT_BAR_HEIGHT_MODULES: u32 = 50;
+const MAX_MODULE_SIZE: u32 = 100;
+const MAX_QUIET_ZONE_MODULES: u32 = 50;
+const MAX_BAR_HEIGHT: u32 = 4096;
+/// Bound on either side of the generated image, which keeps a large module
+/// size on a large symbol from forcing a huge PNG or canvas allocation.
+const MAX_IMAGE_DIMENSION: u32 = 8192;
+
+enum Output {
+    Svg,
+    Png,
+    Canvas(Option<HtmlCanvasElement>),
+}
+
+struct GenerateOptions {
+    output: Output,
+    module_size: u32,
+    quiet_zone: Option<u32>,
+    bar_height: Option<u32>,
+    error_correction: Option<String>,
+    gs1: bool,
+}
+
+/// An encoded symbol with one entry per module, row by row.
+pub struct Symbol {
+    pub format: BarcodeFormat,
+    pub modules: Vec<bool>,
+    pub cols: u32,
+    pub rows: u32,
+    pub linear: bool,
+}
+
+/// Pixel layout of a symbol: module size, quiet zone and bar height applied.
+struct Layout {
+    width: u32,
+    height: u32,
+    module_size: u32,
+    offset_x: u32,
+    offset_y: u32,
+    row_height: u32,
+}
+
+impl Symbol {
+    pub fn is_dark(&self, col: u32, row: u32) -> bool {
+        self.modules[(row * self.cols + col) as usize]
+    }
+
+    /// Returns the dark runs of a row as `(first column, length)` pairs.
+    pub fn dark_runs(&self, row: u32) -> Vec<(u32, u32)> {
+        let mut runs = Vec::new();
+        let mut col = 0;
+        while col < self.cols {
+            if !self.is_dark(col, row) {
+                col += 1;
+                continue;
+            }
+            let start = col;
+            while col < self.cols && self.is_dark(col, row) {
+                col += 1;
+            }
+            runs.push((start, col - start));
+        }
+        runs
+    }
+
+    /// The minimum quiet zone, in modules, of the symbology's specification.
+    fn default_quiet_zone(&self) -> u32 {
+        match self.format {
+            _ if self.linear => 10,
+            BarcodeFormat::DATA_MATRIX => 1,
+            BarcodeFormat::PDF_417 => 2,
+            BarcodeFormat::AZTEC => 0,
+            _ => 4,
+        }
+    }
+
+    /// Applies the options, failing if the image would exceed
+    /// `MAX_IMAGE_DIMENSION` on either side.
+    fn layout(&self, opts: &GenerateOptions) -> Result<Layout, Error> {
+        let module_size = opts.module_size;
+        let quiet_zone = opts.quiet_zone.unwrap_or_else(|| self.default_quiet_zone());
+        let offset_x = quiet_zone * module_size;
+        let side = |modules: u32| {
+            modules
+                .checked_mul(module_size)
+                .and_then(|len| len.checked_add(2 * offset_x))
+                .filter(|len| *len <= MAX_IMAGE_DIMENSION)
+                .ok_or(Error::InvalidOptions)
+        };
+
+        if self.linear {
+            let row_height = opts
+                .bar_height
+                .unwrap_or(DEFAULT_BAR_HEIGHT_MODULES * module_size);
+            Ok(Layout {
+                width: side(self.cols)?,
+                height: row_height,
+                module_size,
+                offset_x,
+                offset_y: 0,
+                row_height,
+            })
+        } else {
+            Ok(Layout {
+                width: side(self.cols)?,
+                height: side(self.rows)?,
+                module_size,
+                offset_x,
+                offset_y: offset_x,
+                row_height: module_size,
+            })
+        }
+    }
+}
+
+/// Reads `errorCorrection`, a level name such as `"H"` or a number.
+pub fn parse_error_correction(obj: &Object) -> Result<Option<String>, Error> {
+    match options::get(obj, "errorCorrection") {
+        Some(value) => value
+            .as_string()
+            .or_else(|| value.as_f64().map(|n| n.to_string()))
+            .map(Some)
+            .ok_or(Error::InvalidOptions),
+        None => Ok(None),
+    }
+}
+
+fn parse_options(options: &JsValue) -> Result<GenerateOptions, Error> {
+    let obj = options::as_object(options)?;
+
+    let canvas = match options::get(&obj, "canvas") {
+        Some(value) => Some(
+            value
+                .dyn_into::<HtmlCanvasElement>()
+                .map_err(|_| Error::InvalidOptions)?,
+        ),
+        None => None,
+    };
+    let output = match options::get_string(&obj, "output")?.as_deref() {
+        Some("svg") => Output::Svg,
+        Some("png") => Output::Png,
+        Some("canvas") => Output::Canvas(canvas),
+        Some(_) => return Err(Error::InvalidOptions),
+        None if canvas.is_some() => Output::Canvas(canvas),
+        None => Output::Svg,
+    };
+
+    let module_size = options::get_u32(&obj, "moduleSize")?.unwrap_or(DEFAULT_MODULE_SIZE);
+    if module_size == 0 || module_size > MAX_MODULE_SIZE {
+        return Err(Error::InvalidOptions);
+    }
+
+    let quiet_zone = options::get_u32(&obj, "quietZone")?;
+    let bar_height = options::get_u32(&obj, "height")?.filter(|h| *h > 0);
+    if quiet_zone.is_some_and(|q| q > MAX_QUIET_ZONE_MODULES)
+        || bar_height.is_some_and(|h| h > MAX_BAR_HEIGHT)
+    {
+        return Err(Error::InvalidOptions);
+    }
+
+    Ok(GenerateOptions {
+        output,
+        module_size,
+        quiet_zone,
+        bar_height,
+        error_correction: parse_error_correction(&obj)?,
+        gs1: options::get_bool(&obj, "gs1")?.unwrap_or(false),
+    })
+}
+
+/// Encodes `value` at one pixel per module without margins, so that the
+/// layout can be applied exactly.
+pub fn encode(
+    format: BarcodeFormat,
+    value: &str,
+    error_correction: Option<&str>,
+    gs1: bool,
+) -> Result<Symbol, Error> {
+    let mut hints = EncodeHints::default()
+        .with(EncodeHintValue::Margin("0".into()))
+        .with(EncodeHintValue::CharacterSet("UTF-8".into()));
+    if let Some(level) = error_correction {
+        hints = hints.with(EncodeHintValue::ErrorCorrection(level.to_string()));
+    }
+
+    let contents = match format {
+        // Code 128 marks GS1 data with a leading FNC1 and separates
+        // variable-length fields with further FNC1s.
+        BarcodeFormat::CODE_128 if gs1 => std::iter::once(CODE_128_FNC1)
+            .chain(value.chars().map(|c| {
+                if c == GROUP_SEPARATOR {
+                    CODE_128_FNC1
+                } else {
+                    c
+                }
+            }))
+            .collect(),
+        BarcodeFormat::QR_CODE if gs1 => {
+            hints = hints.with(EncodeHintValue::Gs1Format(true));
+            value.to_string()
+        }
+        BarcodeFormat::DATA_MATRIX if gs1 => return encode_gs1_data_matrix(value),
+        _ => value.to_string(),
+    };
+
+    let matrix = MultiFormatWriter
+        .encode_with_hints(&contents, &format, 0, 0, &hints)
+        .map_err(|_| Error::EncodeFailed)?;
+
+    let (cols, rows) = (matrix.width(), matrix.height());
+    let modules = (0..rows)
+        .flat_map(|y| (0..cols).map(move |x| (x, y)))
+        .map(|(x, y)| matrix.get(x, y))
+        .collect();
+
+    Ok(Symbol {
+        format,
+        modules,
+        cols,
+        rows,
+        linear: rows == 1,
+    })
+}
+
+/// Encodes GS1 data as DataMatrix ASCII codewords: a leading FNC1, digit
+/// pairs, and FNC1 for each group separator.
+fn gs1_data_matrix_codewords(value: &str) -> Result<Vec<u8>, Error> {
+    let mut codewords = vec![DATA_MATRIX_FNC1];
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            GROUP_SEPARATOR => codewords.push(DATA_MATRIX_FNC1),
+            '0'..='9' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                let pair = chars.next().and_then(|d| d.to_digit(10)).unwrap_or(0);
+                let tens = c.to_digit(10).unwrap_or(0);
+                codewords.push(130 + (tens * 10 + pair) as u8);
+            }
+            c if c.is_ascii() => codewords.push(c as u8 + 1),
+            // GS1 element strings are restricted to a subset of ASCII.
+            _ => return Err(Error::EncodeFailed),
+        }
+    }
+    Ok(codewords)
+}
+
+/// Builds a GS1 DataMatrix from rxing's symbol tables, error correction and
+/// module placement. rxing's own GS1 encoder mistakes FNC1 for an ECI.
+fn encode_gs1_data_matrix(value: &str) -> Result<Symbol, Error> {
+    let mut data = gs1_data_matrix_codewords(value)?;
+    let info = SymbolInfoLookup::new()
+        .lookup_with_shape(data.len() as u32, SymbolShapeHint::FORCE_SQUARE)
+        .ok()
+        .flatten()
+        .ok_or(Error::EncodeFailed)?;
+
+    // Padding: one pad codeword, then pads scrambled by the 253-state
+    // algorithm (ISO/IEC 16022, 5.2.3).
+    let capacity = info.getDataCapacity() as usize;
+    if data.len() < capacity {
+        data.push(DATA_MATRIX_PAD);
+    }
+    while data.len() < capacity {
+        let position = data.len() as u32 + 1;
+        let pad = DATA_MATRIX_PAD as u32 + (149 * position) % 253 + 1;
+        data.push(if pad <= 254 { pad } else { pad - 254 } as u8);
+    }
+
+    let data: String = data.into_iter().map(char::from).collect();
+    let codewords = error_correction::encodeECC200(&data, info).map_err(|_| Error::EncodeFailed)?;
+
+    let map_err = |_| Error::EncodeFailed;
+    let (data_cols, data_rows) = (
+        info.getSymbolDataWidth().map_err(map_err)?,
+        info.getSymbolDataHeight().map_err(map_err)?,
+    );
+    let (cols, rows) = (
+        info.getSymbolWidth().map_err(map_err)?,
+        info.getSymbolHeight().map_err(map_err)?,
+    );
+    let mut placement = DefaultPlacement::new(codewords, data_cols as usize, data_rows as usize);
+    placement.place().map_err(map_err)?;
+
+    // Each data region is framed by a solid L on the left and bottom and an
+    // alternating clock track on the top and right.
+    let region_cols = data_cols / ((cols - data_cols) / 2);
+    let region_rows = data_rows / ((rows - data_rows) / 2);
+    let mut modules = Vec::with_capacity((cols * rows) as usize);
+    for y in 0..rows {
+        let (region_y, ry) = (y / (region_rows + 2), y % (region_rows + 2));
+        for x in 0..cols {
+            let (region_x, rx) = (x / (region_cols + 2), x % (region_cols + 2));
+            let dark = if ry == region_rows + 1 {
+                true
+            } else if ry == 0 {
+                x % 2 == 0
+            } else if rx == 0 {
+                true
+            } else if rx == region_cols + 1 {
+                y % 2 == 1
+            } else {
+                placement.getBit(
+                    (region_x * region_cols + rx - 1) as usize,
+                    (region_y * region_rows + ry - 1) as usize,
+                )
+            };
+            modules.push(dark);
+        }
+    }
+
+    Ok(Symbol {
+        format: BarcodeFormat::DATA_MATRIX,
+        modules,
+        cols,
+        rows,
+        linear: false,
+    })
+}
+
+fn to_svg(symbol: &Symbol, layout: &Layout) -> String {
+    let mut path = String::new();
+    for row in 0..symbol.rows {
+        let y = layout.offset_y + row * layout.row_height;
+        for (col, len) in symbol.dark_runs(row) {
+            let x = layout.offset_x + col * layout.module_size;
+            let w = len * layout.module_size;
+            let _ = write!(path, "M{x} {y}h{w}v{}h-{w}z", layout.row_height);
+        }
+    }
+
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" "#,
+            r#"viewBox="0 0 {w} {h}" shape-rendering="crispEdges">"#,
+            r##"<rect width="{w}" height="{h}" fill="#fff"/>"##,
+            r##"<path d="{path}" fill="#000"/></svg>"##
+        ),
+        w = layout.width,
+        h = layout.height,
+        path = path,
+    )
+}
+
+fn to_png(symbol: &Symbol, layout: &Layout) -> Result<Vec<u8>, Error> {
+    let mut image = GrayImage::from_pixel(layout.width, layout.height, Luma([255]));
+    for row in 0..symbol.rows {
+        let y0 = layout.offset_y + row * layout.row_height;
+        for (col, len) in symbol.dark_runs(row) {
+            let x0 = layout.offset_x + col * layout.module_size;
+            for y in y0..y0 + layout.row_height {
+                for x in x0..x0 + len * layout.module_size {
+                    image.put_pixel(x, y, Luma([0]));
+                }
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|_| Error::Internal)?;
+    Ok(png)
+}
+
+fn to_canvas(
+    symbol: &Symbol,
+    layout: &Layout,
+    canvas: Option<HtmlCanvasElement>,
+) -> Result<HtmlCanvasElement, Error> {
+    let canvas = match canvas {
+        Some(canvas) => canvas,
+        None => web_sys::window()
+            .ok_or(Error::WindowNotFound)?
+            .document()
+            .ok_or(Error::DocumentNotFound)?
+            .create_element("canvas")
+            .ok()
+            .and_then(|el| el.dyn_into::<HtmlCanvasElement>().ok())
+            .ok_or(Error::Internal)?,
+    };
+    canvas.set_width(layout.width);
+    canvas.set_height(layout.height);
+
+    let ctx = canvas_context(&canvas)?;
+    ctx.set_fill_style_str("#fff");
+    ctx.fill_rect(0.0, 0.0, layout.width as f64, layout.height as f64);
+    ctx.set_fill_style_str("#000");
+    for row in 0..symbol.rows {
+        let y = layout.offset_y + row * layout.row_height;
+        for (col, len) in symbol.dark_runs(row) {
+            ctx.fill_rect(
+                (layout.offset_x + col * layout.module_size) as f64,
+                y as f64,
+                (len * layout.module_size) as f64,
+                layout.row_height as f64,
+            );
+        }
+    }
+
+    Ok(canvas)
+}
+
+pub fn generate(format: &str, value: &str, options: &JsValue) -> Result<JsValue, Error> {
+    let format = format_from_name(format).ok_or(Error::InvalidOptions)?;
+    let opts = parse_options(options)?;
+    let symbol = encode(format, value, opts.error_correction.as_deref(), opts.gs1)?;
+    let layout = symbol.layout(&opts)?;
+
+    match opts.output {
+        Output::Svg => Ok(JsValue::from_str(&to_svg(&symbol, &layout))),
+        Output::Png => Ok(Uint8Array::from(to_png(&symbol, &layout)?.as_slice()).into()),
+        Output::Canvas(canvas) => Ok(to_canvas(&symbol, &layout, canvas)?.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rxing::{
+        BinaryBitmap, DecodeHintValue, DecodeHints, Luma8LuminanceSource, RXingResultMetadataType,
+        RXingResultMetadataValue, Reader, common::HybridBinarizer, datamatrix::DataMatrixReader,
+    };
+
+    use super::*;
+
+    fn options() -> GenerateOptions {
+        GenerateOptions {
+            output: Output::Svg,
+            module_size: DEFAULT_MODULE_SIZE,
+            quiet_zone: None,
+            bar_height: None,
+            error_correction: None,
+            gs1: false,
+        }
+    }
+
+    /// Renders a 2D symbol with a two-module quiet zone at four pixels per
+    /// module and decodes it with rxing.
+    fn decode_data_matrix(symbol: &Symbol) -> (String, Option<String>) {
+        let (module, quiet) = (4, 2);
+        let (width, height) = (
+            (symbol.cols + 2 * quiet) * module,
+            (symbol.rows + 2 * quiet) * module,
+        );
+        let image = GrayImage::from_fn(width, height, |x, y| {
+            let (mx, my) = (x / module, y / module);
+            let dark = (quiet..symbol.cols + quiet).contains(&mx)
+                && (quiet..symbol.rows + quiet).contains(&my)
+                && symbol.is_dark(mx - quiet, my - quiet);
+            Luma([if dark { 0 } else { 255 }])
+        });
+
+        let source = Luma8LuminanceSource::new(image.into_raw(), width, height);
+        let mut bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+        let result = DataMatrixReader
+            .decode_with_hints(
+                &mut bitmap,
+                &DecodeHints::default().with(DecodeHintValue::PureBarcode(true)),
+            )
+            .unwrap();
+        let symbology = match result
+            .getRXingResultMetadata()
+            .get(&RXingResultMetadataType::SYMBOLOGY_IDENTIFIER)
+        {
+            Some(RXingResultMetadataValue::SymbologyIdentifier(id)) => Some(id.clone()),
+            _ => None,
+        };
+        (result.getText().to_string(), symbology)
+    }
+
+    #[test]
+    fn gs1_data_matrix_round_trips_through_rxing() {
+        let values = [
+            "0100012345678905".to_string(),
+            format!("0100012345678905{GROUP_SEPARATOR}10LOT-42{GROUP_SEPARATOR}21SERIAL7"),
+            format!(
+                "01000123456789051726123110ABC{GROUP_SEPARATOR}21{}",
+                "9".repeat(40)
+            ),
+            format!("0103453120000011{GROUP_SEPARATOR}10{}", "X1".repeat(60)),
+        ];
+        for value in values {
+            let symbol = encode(BarcodeFormat::DATA_MATRIX, &value, None, true).unwrap();
+            assert_eq!(symbol.cols, symbol.rows);
+
+            let (text, symbology) = decode_data_matrix(&symbol);
+            assert_eq!(text, value);
+            assert_eq!(symbology.as_deref(), Some("]d2"), "{value}");
+        }
+    }
+
+    #[test]
+    fn gs1_data_matrix_codewords_pair_digits_and_map_separators() {
+        let codewords = gs1_data_matrix_codewords(&format!("10A1{GROUP_SEPARATOR}21")).unwrap();
+        assert_eq!(
+            codewords,
+            [
+                DATA_MATRIX_FNC1,
+                140,
+                b'A' + 1,
+                b'1' + 1,
+                DATA_MATRIX_FNC1,
+                151
+            ]
+        );
+        assert!(gs1_data_matrix_codewords("10é").is_err());
+    }
+
+    #[test]
+    fn layout_applies_module_size_and_quiet_zone() {
+        let qr = encode(BarcodeFormat::QR_CODE, "wascan", None, false).unwrap();
+        let layout = qr.layout(&options()).unwrap();
+        assert_eq!(layout.width, (qr.cols + 8) * DEFAULT_MODULE_SIZE);
+        assert_eq!(layout.height, layout.width);
+        assert_eq!(layout.offset_y, 4 * DEFAULT_MODULE_SIZE);
+
+        let code_128 = encode(BarcodeFormat::CODE_128, "wascan", None, false).unwrap();
+        let layout = code_128
+            .layout(&GenerateOptions {
+                module_size: 2,
+                quiet_zone: Some(0),
+                bar_height: Some(30),
+                ..options()
+            })
+            .unwrap();
+        assert_eq!((layout.width, layout.height), (code_128.cols * 2, 30));
+    }
+
+    #[test]
+    fn oversized_layouts_are_rejected() {
+        let qr = encode(BarcodeFormat::QR_CODE, &"9".repeat(2000), None, false).unwrap();
+        let huge = GenerateOptions {
+            module_size: MAX_MODULE_SIZE,
+            quiet_zone: Some(MAX_QUIET_ZONE_MODULES),
+            ..options()
+        };
+        assert!(matches!(qr.layout(&huge), Err(Error::InvalidOptions)));
+
+        let code_128 = encode(BarcodeFormat::CODE_128, &"A".repeat(80), None, false).unwrap();
+        assert!(matches!(code_128.layout(&huge), Err(Error::InvalidOptions)));
+    }
+}
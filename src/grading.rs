@@ -0,0 +1,1488 @@
+use std::collections::HashSet;
+
+use image::GrayImage;
+use js_sys::{Object, Reflect};
+use rxing::{
+    BarcodeFormat, BinaryBitmap, DecodeHintValue, DecodeHints, Luma8LuminanceSource,
+    MultiFormatReader, Point, Reader,
+    common::{
+        BitMatrix, DetectorRXingResult, GlobalHistogramBinarizer, HybridBinarizer,
+        PerspectiveTransform, Quadrilateral,
+        reedsolomon::{PredefinedGenericGF, ReedSolomonDecoder, get_predefined_genericgf},
+    },
+    datamatrix, point, qrcode,
+};
+use wasm_bindgen::JsValue;
+use web_sys::ImageData;
+
+use crate::{
+    detector::{decode_image_bytes, format_name, rgba_to_gray},
+    error::Error,
+    generator,
+};
+
+/// Symbologies that can be graded: the linear ones rxing can also encode,
+/// whose element widths serve as the decodability reference, and the two
+/// matrix symbologies whose grids rxing's detectors expose.
+const LINEAR_FORMATS: [BarcodeFormat; 9] = [
+    BarcodeFormat::CODABAR,
+    BarcodeFormat::CODE_39,
+    BarcodeFormat::CODE_93,
+    BarcodeFormat::CODE_128,
+    BarcodeFormat::EAN_8,
+    BarcodeFormat::EAN_13,
+    BarcodeFormat::ITF,
+    BarcodeFormat::UPC_A,
+    BarcodeFormat::UPC_E,
+];
+const MATRIX_FORMATS: [BarcodeFormat; 2] = [BarcodeFormat::QR_CODE, BarcodeFormat::DATA_MATRIX];
+
+/// Scan lines per linear symbol, spread over 10-90% of the bar height as in
+/// ISO/IEC 15416.
+const SCAN_COUNT: usize = 10;
+/// A space wider than this many median elements is taken as a quiet zone.
+const QUIET_ZONE_RATIO: f64 = 3.0;
+
+// Grade thresholds for A, B, C and D.
+const SYMBOL_CONTRAST: [f64; 4] = [0.70, 0.55, 0.40, 0.20];
+const LINEAR_MODULATION: [f64; 4] = [0.70, 0.60, 0.50, 0.40];
+const DEFECTS: [f64; 4] = [0.15, 0.20, 0.25, 0.30];
+const DECODABILITY: [f64; 4] = [0.62, 0.50, 0.37, 0.25];
+const MATRIX_MODULATION: [f64; 4] = [0.50, 0.40, 0.30, 0.20];
+const AXIAL_NON_UNIFORMITY: [f64; 4] = [0.06, 0.08, 0.10, 0.12];
+const UNUSED_ERROR_CORRECTION: [f64; 4] = [0.62, 0.50, 0.37, 0.25];
+const MIN_EDGE_CONTRAST: f64 = 0.15;
+
+/// Share of matrix modules allowed below the modulation grade, standing in
+/// for the codeword-level evaluation of ISO/IEC 15415.
+const MODULATION_PERCENTILE: f64 = 0.05;
+
+/// Converts a grade value (A = 4 to F = 0) to its letter.
+fn letter(grade: f64) -> &'static str {
+    match grade {
+        g if g >= 3.5 => "A",
+        g if g >= 2.5 => "B",
+        g if g >= 1.5 => "C",
+        g if g >= 0.5 => "D",
+        _ => "F",
+    }
+}
+
+/// Grades a parameter for which higher values are better.
+fn grade_at_least(value: f64, thresholds: [f64; 4]) -> f64 {
+    thresholds
+        .iter()
+        .position(|t| value >= *t)
+        .map_or(0.0, |i| (4 - i) as f64)
+}
+
+/// Grades a parameter for which lower values are better.
+fn grade_at_most(value: f64, thresholds: [f64; 4]) -> f64 {
+    thresholds
+        .iter()
+        .position(|t| value <= *t)
+        .map_or(0.0, |i| (4 - i) as f64)
+}
+
+fn pass_fail(passed: bool) -> f64 {
+    if passed { 4.0 } else { 0.0 }
+}
+
+/// A graded parameter. `value` is absent for pass/fail checks without a
+/// measurement, such as decode.
+struct Parameter {
+    key: &'static str,
+    value: Option<f64>,
+    grade: f64,
+}
+
+impl Parameter {
+    fn new(key: &'static str, value: f64, grade: f64) -> Self {
+        Parameter {
+            key,
+            value: Some(value),
+            grade,
+        }
+    }
+}
+
+struct Grading {
+    grade: f64,
+    parameters: Vec<Parameter>,
+    scans: Option<usize>,
+}
+
+/// Bilinear sample of the image as a reflectance between 0 and 1, or `None`
+/// outside the image.
+fn reflectance(image: &GrayImage, x: f32, y: f32) -> Option<f64> {
+    let (max_x, max_y) = ((image.width() - 1) as f32, (image.height() - 1) as f32);
+    if !(0.0..=max_x).contains(&x) || !(0.0..=max_y).contains(&y) {
+        return None;
+    }
+
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = (
+        (x0 + 1).min(image.width() - 1),
+        (y0 + 1).min(image.height() - 1),
+    );
+    let (fx, fy) = ((x - x0 as f32) as f64, (y - y0 as f32) as f64);
+    let px = |x, y| image.get_pixel(x, y).0[0] as f64 / 255.0;
+
+    let top = px(x0, y0) * (1.0 - fx) + px(x1, y0) * fx;
+    let bottom = px(x0, y1) * (1.0 - fx) + px(x1, y1) * fx;
+    Some(top * (1.0 - fy) + bottom * fy)
+}
+
+/// A run of samples on one side of the threshold.
+struct Run {
+    start: usize,
+    end: usize,
+    dark: bool,
+}
+
+impl Run {
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+fn runs(values: &[f64], threshold: f64) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for (i, value) in values.iter().enumerate() {
+        let dark = *value < threshold;
+        match runs.last_mut() {
+            Some(run) if run.dark == dark => run.end = i + 1,
+            _ => runs.push(Run {
+                start: i,
+                end: i + 1,
+                dark,
+            }),
+        }
+    }
+    runs
+}
+
+fn midpoint(values: &[f64]) -> f64 {
+    let (min, max) = values.iter().fold((f64::MAX, f64::MIN), |(min, max), v| {
+        (min.min(*v), max.max(*v))
+    });
+    (min + max) / 2.0
+}
+
+/// A scan line through a linear symbol, following the direction of the
+/// result points.
+struct ScanLine<'a> {
+    image: &'a GrayImage,
+    origin: (f32, f32),
+    direction: (f32, f32),
+    normal: (f32, f32),
+    /// Distance between the result points, in pixels.
+    length: f32,
+}
+
+/// Reflectance samples one pixel apart; `values[i]` lies at distance
+/// `start + i` from the first result point.
+struct Profile {
+    values: Vec<f64>,
+    start: i32,
+}
+
+impl Profile {
+    /// Converts a distance along the scan line to a sample index.
+    fn index(&self, s: i32) -> usize {
+        (s - self.start).clamp(0, self.values.len() as i32 - 1) as usize
+    }
+}
+
+impl ScanLine<'_> {
+    /// Samples the line shifted by `offset` pixels along the bars, from one
+    /// edge of the image to the other.
+    fn profile(&self, offset: f32) -> Option<Profile> {
+        let sample = |s: i32| {
+            let (x, y) = (
+                self.origin.0 + self.direction.0 * s as f32 + self.normal.0 * offset,
+                self.origin.1 + self.direction.1 * s as f32 + self.normal.1 * offset,
+            );
+            reflectance(self.image, x, y)
+        };
+
+        let mut start = 0;
+        while sample(start - 1).is_some() {
+            start -= 1;
+        }
+        let values: Vec<f64> = (start..).map_while(sample).collect();
+        (values.len() as i32 > self.length.ceil() as i32 - start)
+            .then_some(Profile { values, start })
+    }
+}
+
+/// Finds the first and last bar of the symbol around the result points by
+/// walking outward until a space wide enough to be a quiet zone.
+fn find_symbol(profile: &Profile, length: i32) -> Option<(usize, usize)> {
+    let runs = runs(&profile.values, midpoint(&profile.values));
+    let (a, b) = (profile.index(0), profile.index(length));
+    let first = runs.iter().position(|run| run.end > a)?;
+    let last = runs.iter().rposition(|run| run.start <= b)?;
+
+    let mut widths: Vec<usize> = runs[first..=last].iter().map(Run::len).collect();
+    widths.sort_unstable();
+    let median = widths[widths.len() / 2] as f64;
+    let is_quiet_zone = |run: &Run| !run.dark && run.len() as f64 > QUIET_ZONE_RATIO * median;
+
+    let mut left = first;
+    while left > 0 && !is_quiet_zone(&runs[left - 1]) {
+        left -= 1;
+    }
+    while left < runs.len() && !runs[left].dark {
+        left += 1;
+    }
+    let mut right = last;
+    while right + 1 < runs.len() && !is_quiet_zone(&runs[right + 1]) {
+        right += 1;
+    }
+    while right > left && !runs[right].dark {
+        right -= 1;
+    }
+
+    (left < right).then(|| (runs[left].start, runs[right].end))
+}
+
+/// Counts the bars between two distances along a profile.
+fn bar_count(profile: &Profile, from: i32, to: i32) -> usize {
+    let values = &profile.values[profile.index(from)..profile.index(to)];
+    if values.is_empty() {
+        return 0;
+    }
+    runs(values, midpoint(values))
+        .iter()
+        .filter(|run| run.dark)
+        .count()
+}
+
+/// Minimum quiet zones in modules, left and right.
+fn quiet_zone_modules(format: BarcodeFormat) -> (f64, f64) {
+    match format {
+        BarcodeFormat::UPC_A => (9.0, 9.0),
+        BarcodeFormat::UPC_E => (9.0, 7.0),
+        BarcodeFormat::EAN_13 => (11.0, 7.0),
+        BarcodeFormat::EAN_8 => (7.0, 7.0),
+        _ => (10.0, 10.0),
+    }
+}
+
+/// Symbologies with only narrow and wide elements, where decodability
+/// measures the margin to the narrow/wide threshold.
+fn is_two_width(format: BarcodeFormat) -> bool {
+    matches!(
+        format,
+        BarcodeFormat::CODE_39 | BarcodeFormat::ITF | BarcodeFormat::CODABAR
+    )
+}
+
+/// Element widths in modules of the symbol as rxing would encode it.
+fn reference_elements(format: BarcodeFormat, value: &str) -> Option<Vec<u32>> {
+    let symbol = generator::encode(format, value, None, false).ok()?;
+    let mut widths: Vec<u32> = Vec::new();
+    let mut previous = None;
+    for col in 0..symbol.cols {
+        let dark = symbol.is_dark(col, 0);
+        if previous == Some(dark) {
+            if let Some(width) = widths.last_mut() {
+                *width += 1;
+            }
+        } else if widths.is_empty() && !dark {
+            continue;
+        } else {
+            widths.push(1);
+        }
+        previous = Some(dark);
+    }
+    // Trailing spaces belong to the quiet zone.
+    if symbol.cols > 0 && !symbol.is_dark(symbol.cols - 1, 0) {
+        widths.pop();
+    }
+    Some(widths)
+}
+
+/// The margin of element widths to the decision thresholds, from 1 for a
+/// perfect print down to 0.
+fn decodability(widths: &[f64], reference: Option<&[u32]>, two_width: bool) -> f64 {
+    let reference = reference.filter(|r| r.len() == widths.len());
+
+    if two_width {
+        let (min, max) = widths.iter().fold((f64::MAX, f64::MIN), |(min, max), w| {
+            (min.min(*w), max.max(*w))
+        });
+        let is_wide = |i: usize, w: f64| match reference {
+            Some(r) => r[i] > 1,
+            None => w > (min + max) / 2.0,
+        };
+        let mean = |wide: bool| {
+            let (sum, count) = widths
+                .iter()
+                .enumerate()
+                .filter(|(i, w)| is_wide(*i, **w) == wide)
+                .fold((0.0, 0), |(sum, count), (_, w)| (sum + w, count + 1));
+            (count > 0).then(|| sum / count as f64)
+        };
+        let (Some(narrow), Some(wide)) = (mean(false), mean(true)) else {
+            return 0.0;
+        };
+        let (threshold, half_gap) = ((narrow + wide) / 2.0, (wide - narrow) / 2.0);
+        if half_gap <= 0.0 {
+            return 0.0;
+        }
+
+        return widths
+            .iter()
+            .enumerate()
+            .map(|(i, w)| {
+                let margin = if is_wide(i, *w) {
+                    w - threshold
+                } else {
+                    threshold - w
+                };
+                (margin / half_gap).clamp(0.0, 1.0)
+            })
+            .fold(1.0, f64::min);
+    }
+
+    let total: f64 = widths.iter().sum();
+    let modules: Vec<f64> = match reference {
+        Some(r) => r.iter().map(|m| *m as f64).collect(),
+        None => {
+            // Estimate the module width from the narrowest element, refined
+            // against the whole symbol.
+            let mut x = widths.iter().cloned().fold(f64::MAX, f64::min);
+            for _ in 0..3 {
+                let count: f64 = widths.iter().map(|w| (w / x).round().max(1.0)).sum();
+                x = total / count;
+            }
+            widths.iter().map(|w| (w / x).round().max(1.0)).collect()
+        }
+    };
+    let x = total / modules.iter().sum::<f64>();
+
+    let worst = widths
+        .iter()
+        .zip(&modules)
+        .map(|(w, m)| (w / x - m).abs())
+        .fold(0.0, f64::max);
+    (1.0 - 2.0 * worst).max(0.0)
+}
+
+/// Decodes a single scan line with rxing, as a scanner would.
+fn decodes(values: &[f64], format: BarcodeFormat, expected: &str) -> bool {
+    let luma: Vec<u8> = values.iter().map(|r| (r * 255.0).round() as u8).collect();
+    let source = Luma8LuminanceSource::new(luma, values.len() as u32, 1);
+    let mut bitmap = BinaryBitmap::new(GlobalHistogramBinarizer::new(source));
+    let hints =
+        DecodeHints::default().with(DecodeHintValue::PossibleFormats(HashSet::from([format])));
+
+    MultiFormatReader::default()
+        .decode_with_hints(&mut bitmap, &hints)
+        .is_ok_and(|result| result.getText() == expected)
+}
+
+/// An element between two threshold crossings, with sub-pixel edges.
+struct Element {
+    width: f64,
+    /// Lowest reflectance of a bar or highest of a space.
+    reflectance: f64,
+    /// Reflectance non-uniformity inside the element.
+    non_uniformity: f64,
+    dark: bool,
+}
+
+/// Grades one scan profile of the symbol found between `left` and `right`.
+fn grade_scan(
+    profile: &Profile,
+    (left, right): (usize, usize),
+    format: BarcodeFormat,
+    value: &str,
+    reference: Option<&[u32]>,
+) -> Vec<Parameter> {
+    let values = &profile.values;
+    let threshold = midpoint(&values[left..right]);
+
+    // Crossing of the threshold between samples i - 1 and i.
+    let edge = |i: usize| {
+        let (a, b) = (values[i - 1], values[i]);
+        (i - 1) as f64 + ((threshold - a) / (b - a)).clamp(0.0, 1.0)
+    };
+    let symbol_runs = runs(&values[left..right], threshold);
+    let mut elements: Vec<Element> = Vec::with_capacity(symbol_runs.len());
+    for run in &symbol_runs {
+        let (start, end) = (left + run.start, left + run.end);
+        let start_edge = if start == 0 { 0.0 } else { edge(start) };
+        let end_edge = if end >= values.len() {
+            end as f64
+        } else {
+            edge(end)
+        };
+        let inner = &values[start..end];
+        let (min, max) = inner.iter().fold((f64::MAX, f64::MIN), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+        // Interior variation, leaving out the edge samples on the slopes.
+        let interior = if inner.len() > 2 {
+            &inner[1..inner.len() - 1]
+        } else {
+            inner
+        };
+        let (inner_min, inner_max) = interior.iter().fold((f64::MAX, f64::MIN), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+        elements.push(Element {
+            width: end_edge - start_edge,
+            reflectance: if run.dark { min } else { max },
+            non_uniformity: if run.dark {
+                inner_max - min
+            } else {
+                max - inner_min
+            },
+            dark: run.dark,
+        });
+    }
+
+    let widths: Vec<f64> = elements.iter().map(|e| e.width).collect();
+    let modules = reference.map_or_else(
+        || widths.len() as f64,
+        |r| r.iter().map(|m| *m as f64).sum(),
+    );
+    let x = widths.iter().sum::<f64>() / modules;
+
+    // Quiet zones: light samples outside the symbol, up to the required width.
+    let (left_modules, right_modules) = quiet_zone_modules(format);
+    let left_zone = values[..left]
+        .iter()
+        .rev()
+        .take((left_modules * x).ceil() as usize)
+        .take_while(|v| **v >= threshold)
+        .count();
+    let right_zone = values[right..]
+        .iter()
+        .take((right_modules * x).ceil() as usize)
+        .take_while(|v| **v >= threshold)
+        .count();
+    let quiet_zone = (left_zone as f64 / x).min(right_zone as f64 / x);
+    let quiet_zone_ok =
+        left_zone as f64 >= left_modules * x && right_zone as f64 >= right_modules * x;
+
+    let zone_max = |zone: &[f64]| zone.iter().cloned().fold(threshold, f64::max);
+    let left_space = zone_max(&values[left - left_zone..left]);
+    let right_space = zone_max(&values[right..right + right_zone]);
+
+    let r_max = elements
+        .iter()
+        .map(|e| e.reflectance)
+        .fold(left_space.max(right_space), f64::max);
+    let r_min = elements
+        .iter()
+        .map(|e| e.reflectance)
+        .fold(f64::MAX, f64::min);
+    let contrast = r_max - r_min;
+
+    // Edge contrast between each pair of neighbouring elements, with the
+    // quiet zones as the outermost spaces.
+    let mut sequence = vec![left_space];
+    sequence.extend(elements.iter().map(|e| e.reflectance));
+    sequence.push(right_space);
+    let min_edge_contrast = sequence
+        .windows(2)
+        .map(|pair| (pair[0] - pair[1]).abs())
+        .fold(f64::MAX, f64::min);
+
+    let modulation = if contrast > 0.0 {
+        min_edge_contrast / contrast
+    } else {
+        0.0
+    };
+    let defects = if contrast > 0.0 {
+        elements
+            .iter()
+            .map(|e| e.non_uniformity)
+            .fold(0.0, f64::max)
+            / contrast
+    } else {
+        1.0
+    };
+    let decodability = if elements.first().is_some_and(|e| e.dark) {
+        decodability(&widths, reference, is_two_width(format))
+    } else {
+        0.0
+    };
+
+    let lo = left.saturating_sub(left_zone);
+    let hi = (right + right_zone).min(values.len());
+    vec![
+        Parameter::new(
+            "symbolContrast",
+            contrast,
+            grade_at_least(contrast, SYMBOL_CONTRAST),
+        ),
+        Parameter::new("minReflectance", r_min, pass_fail(r_min <= 0.5 * r_max)),
+        Parameter::new(
+            "minEdgeContrast",
+            min_edge_contrast,
+            pass_fail(min_edge_contrast >= MIN_EDGE_CONTRAST),
+        ),
+        Parameter::new(
+            "modulation",
+            modulation,
+            grade_at_least(modulation, LINEAR_MODULATION),
+        ),
+        Parameter::new("defects", defects, grade_at_most(defects, DEFECTS)),
+        Parameter::new(
+            "decodability",
+            decodability,
+            grade_at_least(decodability, DECODABILITY),
+        ),
+        Parameter {
+            key: "decode",
+            value: None,
+            grade: pass_fail(decodes(&values[lo..hi], format, value)),
+        },
+        Parameter::new("quietZone", quiet_zone, pass_fail(quiet_zone_ok)),
+    ]
+}
+
+fn grade_linear(
+    gray: &GrayImage,
+    format: BarcodeFormat,
+    value: &str,
+    points: &[Point],
+) -> Result<Grading, Error> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Err(Error::NotDetected);
+    };
+    let (dx, dy) = (last.x - first.x, last.y - first.y);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < 1.0 {
+        return Err(Error::NotDetected);
+    }
+
+    let direction = (dx / length, dy / length);
+    let line = ScanLine {
+        image: gray,
+        origin: (first.x, first.y),
+        direction,
+        normal: (-direction.1, direction.0),
+        length,
+    };
+
+    let center = line.profile(0.0).ok_or(Error::NotDetected)?;
+    let (left, right) = find_symbol(&center, length.round() as i32).ok_or(Error::NotDetected)?;
+    let (from, to) = (
+        left as i32 + center.start - 2,
+        right as i32 + center.start + 2,
+    );
+    let bars = bar_count(&center, from, to);
+
+    // Bar height: the extent over which the scan line crosses as many bars.
+    let extent = |step: f32| {
+        let mut offset = 0.0;
+        while let Some(profile) = line.profile(offset + step) {
+            if bar_count(&profile, from, to) != bars || offset.abs() > 4.0 * length {
+                break;
+            }
+            offset += step;
+        }
+        offset
+    };
+    let (top, bottom) = (extent(-1.0), extent(1.0));
+    let height = bottom - top;
+
+    let reference = reference_elements(format, value);
+    let scans: Vec<Vec<Parameter>> = (0..SCAN_COUNT)
+        .filter_map(|i| {
+            let offset = top + height * (0.1 + 0.8 * i as f32 / (SCAN_COUNT - 1) as f32);
+            let profile = line.profile(offset)?;
+            let shift = (profile.start - center.start) as usize;
+            let bounds = (
+                left.checked_sub(shift)?,
+                right.checked_sub(shift)?.min(profile.values.len()),
+            );
+            (bounds.0 > 0 && bounds.0 < bounds.1)
+                .then(|| grade_scan(&profile, bounds, format, value, reference.as_deref()))
+        })
+        .collect();
+    if scans.is_empty() {
+        return Err(Error::NotDetected);
+    }
+
+    // Each parameter is reported as its mean over the scans; the symbol
+    // grade is the mean of the scan grades, each the lowest of its scan.
+    let count = scans.len() as f64;
+    let parameters = (0..scans[0].len())
+        .map(|i| Parameter {
+            key: scans[0][i].key,
+            value: scans[0][i]
+                .value
+                .map(|_| scans.iter().filter_map(|s| s[i].value).sum::<f64>() / count),
+            grade: scans.iter().map(|s| s[i].grade).sum::<f64>() / count,
+        })
+        .collect();
+    let grade = scans
+        .iter()
+        .map(|scan| scan.iter().map(|p| p.grade).fold(4.0, f64::min))
+        .sum::<f64>()
+        / count;
+
+    Ok(Grading {
+        grade,
+        parameters,
+        scans: Some(scans.len()),
+    })
+}
+
+/// The module grid of a matrix symbol as sampled by rxing's detector.
+struct Grid {
+    bits: BitMatrix,
+    transform: PerspectiveTransform,
+    /// How the symbol's upright grid maps to `bits`: a combination of
+    /// `SWAP_AXES`, `MIRROR_X` and `MIRROR_Y`.
+    orientation: u8,
+    width: u32,
+    height: u32,
+    /// Fixed patterns, each a list of module positions and expected colours.
+    fixed_patterns: Vec<Vec<(u32, u32, bool)>>,
+    unused_error_correction: Option<f64>,
+}
+
+const SWAP_AXES: u8 = 1;
+const MIRROR_X: u8 = 2;
+const MIRROR_Y: u8 = 4;
+
+impl Grid {
+    /// Module position in the detector's sampling grid.
+    fn sampled(&self, x: i32, y: i32) -> (i32, i32) {
+        let (mut sx, mut sy) = if self.orientation & SWAP_AXES != 0 {
+            (y, x)
+        } else {
+            (x, y)
+        };
+        if self.orientation & MIRROR_X != 0 {
+            sx = self.bits.getWidth() as i32 - 1 - sx;
+        }
+        if self.orientation & MIRROR_Y != 0 {
+            sy = self.bits.getHeight() as i32 - 1 - sy;
+        }
+        (sx, sy)
+    }
+
+    fn center(&self, x: i32, y: i32) -> Point {
+        let (sx, sy) = self.sampled(x, y);
+        self.transform
+            .transform_point(point(sx as f32 + 0.5, sy as f32 + 0.5))
+    }
+
+    fn is_dark(&self, x: u32, y: u32) -> bool {
+        let (sx, sy) = self.sampled(x as i32, y as i32);
+        self.bits.get(sx as u32, sy as u32)
+    }
+}
+
+/// Lowest unused share of the error correction capacity over the
+/// Reed-Solomon blocks, or `None` if a block cannot be corrected.
+fn unused_error_correction<'a>(
+    blocks: impl Iterator<Item = (&'a [u8], usize)>,
+    field: PredefinedGenericGF,
+) -> Option<f64> {
+    let decoder = ReedSolomonDecoder::new(get_predefined_genericgf(field));
+    blocks
+        .map(|(codewords, data)| {
+            let ec = codewords.len().checked_sub(data).filter(|ec| *ec > 0)?;
+            let mut received: Vec<i32> = codewords.iter().map(|c| *c as i32).collect();
+            let errors = decoder.decode(&mut received, ec as i32).ok()?;
+            Some((1.0 - 2.0 * errors as f64 / ec as f64).max(0.0))
+        })
+        .try_fold(1.0, |lowest: f64, uec| Some(lowest.min(uec?)))
+}
+
+fn qr_grid(matrix: &BitMatrix) -> Option<Grid> {
+    let result = qrcode::detector::Detector::new(matrix).detect().ok()?;
+    let bits = result.getBits().clone();
+    let points = result.getPoints();
+    let (bottom_left, top_left, top_right) = (*points.first()?, *points.get(1)?, *points.get(2)?);
+    let dimension = bits.getWidth();
+
+    // The transform rxing's detector samples with: finder pattern centres
+    // 3.5 modules from the edges, and the alignment pattern when found.
+    let far = dimension as f32 - 3.5;
+    let (bottom_right, source_bottom_right) = match points.get(3) {
+        Some(alignment) => (*alignment, far - 3.0),
+        None => (
+            point(
+                top_right.x - top_left.x + bottom_left.x,
+                top_right.y - top_left.y + bottom_left.y,
+            ),
+            far,
+        ),
+    };
+    let transform = PerspectiveTransform::quadrilateralToQuadrilateral(
+        Quadrilateral::new(
+            point(3.5, 3.5),
+            point(far, 3.5),
+            point(source_bottom_right, source_bottom_right),
+            point(3.5, far),
+        ),
+        Quadrilateral::new(top_left, top_right, bottom_right, bottom_left),
+    )
+    .ok()?;
+
+    let finder = |x0: u32, y0: u32| {
+        let ring = |d: u32| d == 0 || d == 6;
+        let core = |d: u32| (2..=4).contains(&d);
+        (0..7)
+            .flat_map(|dy| (0..7).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| {
+                let dark = ring(dx) || ring(dy) || (core(dx) && core(dy));
+                (x0 + dx, y0 + dy, dark)
+            })
+            .collect()
+    };
+    let timing = dimension.saturating_sub(16);
+    let fixed_patterns = vec![
+        finder(0, 0),
+        finder(dimension - 7, 0),
+        finder(0, dimension - 7),
+        (0..timing).map(|i| (8 + i, 6, i % 2 == 0)).collect(),
+        (0..timing).map(|i| (6, 8 + i, i % 2 == 0)).collect(),
+    ];
+
+    let unused_error_correction = (|| {
+        let mut parser = qrcode::decoder::BitMatrixParser::new(bits.clone()).ok()?;
+        let version = parser.readVersion().ok()?;
+        let level = parser
+            .readFormatInformation()
+            .ok()?
+            .getErrorCorrectionLevel();
+        let codewords = parser.readCodewords().ok()?;
+        let blocks = qrcode::decoder::DataBlock::getDataBlocks(&codewords, version, level).ok()?;
+        unused_error_correction(
+            blocks
+                .iter()
+                .map(|b| (b.getCodewords(), b.getNumDataCodewords() as usize)),
+            PredefinedGenericGF::QrCodeField256,
+        )
+    })();
+
+    Some(Grid {
+        bits,
+        transform,
+        orientation: 0,
+        width: dimension,
+        height: dimension,
+        fixed_patterns,
+        unused_error_correction,
+    })
+}
+
+/// The L-shaped finder (left column, bottom row) and the clock tracks (top
+/// row, right column) of a Data Matrix symbol.
+fn data_matrix_patterns(width: u32, height: u32) -> Vec<Vec<(u32, u32, bool)>> {
+    vec![
+        (0..height).map(|y| (0, y, true)).collect(),
+        (0..width).map(|x| (x, height - 1, true)).collect(),
+        (0..width).map(|x| (x, 0, x % 2 == 0)).collect(),
+        (0..height - 1)
+            .map(|y| (width - 1, y, y % 2 == 1))
+            .collect(),
+    ]
+}
+
+/// Orients a sampled Data Matrix grid so its finder pattern is at the left
+/// and bottom, and reads its error correction.
+fn data_matrix_grid(bits: BitMatrix, transform: PerspectiveTransform) -> Option<Grid> {
+    let (width, height) = (bits.getWidth(), bits.getHeight());
+    let mut grid = Grid {
+        bits,
+        transform,
+        orientation: 0,
+        width,
+        height,
+        fixed_patterns: Vec::new(),
+        unused_error_correction: None,
+    };
+
+    // Keep the orientation whose fixed patterns match the sampled bits best.
+    let mut best = None;
+    for orientation in 0..8 {
+        grid.orientation = orientation;
+        (grid.width, grid.height) = if orientation & SWAP_AXES != 0 {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        grid.fixed_patterns = data_matrix_patterns(grid.width, grid.height);
+        let matches = grid
+            .fixed_patterns
+            .iter()
+            .flatten()
+            .filter(|(x, y, dark)| grid.is_dark(*x, *y) == *dark)
+            .count();
+        if best.is_none_or(|(_, most)| matches > most) {
+            best = Some((orientation, matches));
+        }
+    }
+    let (orientation, _) = best?;
+    grid.orientation = orientation;
+    (grid.width, grid.height) = if orientation & SWAP_AXES != 0 {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    grid.fixed_patterns = data_matrix_patterns(grid.width, grid.height);
+
+    let mut upright = BitMatrix::new(grid.width, grid.height).ok()?;
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if grid.is_dark(x, y) {
+                upright.set(x, y);
+            }
+        }
+    }
+    grid.unused_error_correction = (|| {
+        let mut parser = datamatrix::decoder::BitMatrixParser::new(&upright).ok()?;
+        let codewords = parser.readCodewords().ok()?;
+        let blocks =
+            datamatrix::decoder::DataBlock::getDataBlocks(&codewords, parser.getVersion(), false)
+                .ok()?;
+        unused_error_correction(
+            blocks
+                .iter()
+                .map(|b| (b.getCodewords(), b.getNumDataCodewords() as usize)),
+            PredefinedGenericGF::DataMatrixField256,
+        )
+    })();
+
+    Some(grid)
+}
+
+/// Finds the Data Matrix grid the way rxing's reader does: candidates from
+/// the newer detector first, then the classic one, keeping the first whose
+/// error correction can be read.
+fn data_matrix_grids(matrix: &BitMatrix) -> Option<Grid> {
+    let mut grids = Vec::new();
+
+    // The newer detector samples between the symbol's outer edges.
+    for result in
+        datamatrix::detector::zxing_cpp_detector::detect(matrix, true, true).unwrap_or_default()
+    {
+        let bits = result.getBits().clone();
+        let (w, h) = (bits.getWidth() as f32, bits.getHeight() as f32);
+        let &[top_left, top_right, bottom_right, bottom_left] = result.getPoints() else {
+            continue;
+        };
+        let Ok(transform) = PerspectiveTransform::quadrilateralToQuadrilateral(
+            Quadrilateral::new(point(0.0, 0.0), point(w, 0.0), point(w, h), point(0.0, h)),
+            Quadrilateral::new(top_left, top_right, bottom_right, bottom_left),
+        ) else {
+            continue;
+        };
+        grids.extend(data_matrix_grid(bits, transform));
+    }
+
+    // The classic detector samples between the centres of corner modules.
+    if let Ok(result) = datamatrix::detector::Detector::new(matrix).and_then(|d| d.detect()) {
+        let bits = result.getBits().clone();
+        let (w, h) = (bits.getWidth() as f32, bits.getHeight() as f32);
+        if let &[top_left, bottom_left, bottom_right, top_right] = result.getPoints()
+            && let Ok(transform) = PerspectiveTransform::quadrilateralToQuadrilateral(
+                Quadrilateral::new(
+                    point(0.5, 0.5),
+                    point(w - 0.5, 0.5),
+                    point(w - 0.5, h - 0.5),
+                    point(0.5, h - 0.5),
+                ),
+                Quadrilateral::new(top_right, top_left, bottom_right, bottom_left),
+            )
+        {
+            grids.extend(data_matrix_grid(bits, transform));
+        }
+    }
+
+    match grids
+        .iter()
+        .position(|g| g.unused_error_correction.is_some())
+    {
+        Some(i) => Some(grids.swap_remove(i)),
+        None => grids.into_iter().next(),
+    }
+}
+
+/// Decodes the module colours measured at the grid with rxing's decoder,
+/// as the reference decode of ISO/IEC 15415 does.
+fn decodes_grid(
+    grid: &Grid,
+    format: BarcodeFormat,
+    expected: &str,
+    is_dark: impl Fn(u32, u32) -> bool,
+) -> bool {
+    let Ok(mut bits) = BitMatrix::new(grid.width, grid.height) else {
+        return false;
+    };
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if is_dark(x, y) {
+                bits.set(x, y);
+            }
+        }
+    }
+
+    let result = match format {
+        BarcodeFormat::QR_CODE => qrcode::decoder::qrcode_decoder::decode_bitmatrix(&bits),
+        _ => datamatrix::decoder::Decoder::new().decode(&bits),
+    };
+    result.is_ok_and(|result| result.getText() == expected)
+}
+
+fn grade_matrix(
+    gray: &GrayImage,
+    grid: &Grid,
+    format: BarcodeFormat,
+    value: &str,
+) -> Result<Grading, Error> {
+    let distance = |a: Point, b: Point| ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt() as f64;
+    let (w, h) = (grid.width as i32, grid.height as i32);
+
+    // Average module pitch along each axis, through the middle of the symbol.
+    let pitch_x = distance(grid.center(0, h / 2), grid.center(w - 1, h / 2)) / (w - 1) as f64;
+    let pitch_y = distance(grid.center(w / 2, 0), grid.center(w / 2, h - 1)) / (h - 1) as f64;
+
+    // Reflectance over a synthetic aperture of 0.4 modules.
+    let radius = (0.2 * pitch_x.min(pitch_y)).floor() as i32;
+    let measure = |x: i32, y: i32| {
+        let center = grid.center(x, y);
+        let samples: Vec<f64> = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter_map(|(dx, dy)| reflectance(gray, center.x + dx as f32, center.y + dy as f32))
+            .collect();
+        (!samples.is_empty()).then(|| samples.iter().sum::<f64>() / samples.len() as f64)
+    };
+
+    let mut modules = Vec::with_capacity((w * h) as usize);
+    for y in 0..h {
+        for x in 0..w {
+            let r = measure(x, y).ok_or(Error::NotDetected)?;
+            modules.push((x as u32, y as u32, r));
+        }
+    }
+    // The quiet zone next to the symbol counts toward the highest reflectance.
+    let quiet_zone = (-1..=w)
+        .flat_map(|x| [(x, -1), (x, h)])
+        .chain((0..h).flat_map(|y| [(-1, y), (w, y)]))
+        .filter_map(|(x, y)| measure(x, y));
+
+    let r_min = modules.iter().map(|m| m.2).fold(f64::MAX, f64::min);
+    let r_max = modules
+        .iter()
+        .map(|m| m.2)
+        .chain(quiet_zone)
+        .fold(f64::MIN, f64::max);
+    let contrast = r_max - r_min;
+    let threshold = (r_max + r_min) / 2.0;
+    let measured_dark = |r: f64| r < threshold;
+
+    // Modules on the wrong side of the threshold have no modulation.
+    let mut modulations: Vec<f64> = modules
+        .iter()
+        .map(|(x, y, r)| {
+            if contrast <= 0.0 || measured_dark(*r) != grid.is_dark(*x, *y) {
+                0.0
+            } else {
+                2.0 * (r - threshold).abs() / contrast
+            }
+        })
+        .collect();
+    modulations.sort_by(f64::total_cmp);
+    let modulation = modulations[(modulations.len() as f64 * MODULATION_PERCENTILE) as usize];
+
+    let axial_non_uniformity = (pitch_x - pitch_y).abs() / ((pitch_x + pitch_y) / 2.0);
+
+    let reflectance_at = |x: u32, y: u32| modules[(y * grid.width + x) as usize].2;
+    let damage = grid
+        .fixed_patterns
+        .iter()
+        .map(|pattern| {
+            pattern
+                .iter()
+                .filter(|(x, y, dark)| measured_dark(reflectance_at(*x, *y)) != *dark)
+                .count()
+        })
+        .max()
+        .unwrap_or(0);
+
+    let mut parameters = vec![
+        Parameter::new(
+            "symbolContrast",
+            contrast,
+            grade_at_least(contrast, SYMBOL_CONTRAST),
+        ),
+        Parameter::new(
+            "modulation",
+            modulation,
+            grade_at_least(modulation, MATRIX_MODULATION),
+        ),
+        Parameter::new(
+            "axialNonUniformity",
+            axial_non_uniformity,
+            grade_at_most(axial_non_uniformity, AXIAL_NON_UNIFORMITY),
+        ),
+        // A: no damaged module in any pattern, down to F at four or more.
+        Parameter::new(
+            "fixedPatternDamage",
+            damage as f64,
+            4.0 - damage.min(4) as f64,
+        ),
+    ];
+    if let Some(uec) = grid.unused_error_correction {
+        parameters.push(Parameter::new(
+            "unusedErrorCorrection",
+            uec,
+            grade_at_least(uec, UNUSED_ERROR_CORRECTION),
+        ));
+    }
+    parameters.push(Parameter {
+        key: "decode",
+        value: None,
+        grade: pass_fail(decodes_grid(grid, format, value, |x, y| {
+            measured_dark(reflectance_at(x, y))
+        })),
+    });
+
+    // ISO/IEC 15415: the symbol grade is the lowest parameter grade.
+    let grade = parameters.iter().map(|p| p.grade).fold(4.0, f64::min);
+
+    Ok(Grading {
+        grade,
+        parameters,
+        scans: None,
+    })
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+fn grading_to_js(value: &str, format: BarcodeFormat, grading: &Grading) -> JsValue {
+    let set = |target: &Object, key: &str, value: JsValue| {
+        let _ = Reflect::set(target, &JsValue::from_str(key), &value);
+    };
+
+    let obj = Object::new();
+    set(&obj, "value", JsValue::from_str(value));
+    set(&obj, "format", JsValue::from_str(format_name(&format)));
+    set(&obj, "grade", JsValue::from_str(letter(grading.grade)));
+    set(&obj, "gradeValue", JsValue::from_f64(round(grading.grade)));
+
+    let parameters = Object::new();
+    for parameter in &grading.parameters {
+        let entry = Object::new();
+        if let Some(value) = parameter.value {
+            set(&entry, "value", JsValue::from_f64(round(value)));
+        }
+        set(&entry, "grade", JsValue::from_str(letter(parameter.grade)));
+        set(&parameters, parameter.key, entry.into());
+    }
+    set(&obj, "parameters", parameters.into());
+
+    if let Some(scans) = grading.scans {
+        set(&obj, "scans", JsValue::from(scans as u32));
+    }
+
+    obj.into()
+}
+
+/// Decodes the symbol in the image and grades it.
+fn grade(gray: &GrayImage) -> Result<(BarcodeFormat, String, Grading), Error> {
+    let (width, height) = gray.dimensions();
+    let source = Luma8LuminanceSource::new(gray.as_raw().clone(), width, height);
+    let mut bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+
+    let formats: HashSet<BarcodeFormat> = LINEAR_FORMATS
+        .iter()
+        .chain(MATRIX_FORMATS.iter())
+        .copied()
+        .collect();
+    let hints = DecodeHints::default()
+        .with(DecodeHintValue::TryHarder(true))
+        .with(DecodeHintValue::PossibleFormats(formats));
+    let result = MultiFormatReader::default()
+        .decode_with_hints(&mut bitmap, &hints)
+        .map_err(|_| Error::NotDetected)?;
+    let (format, value) = (*result.getBarcodeFormat(), result.getText());
+
+    let grading = if MATRIX_FORMATS.contains(&format) {
+        let matrix = bitmap.get_black_matrix();
+        let grid = match format {
+            BarcodeFormat::QR_CODE => qr_grid(matrix),
+            _ => data_matrix_grids(matrix),
+        }
+        .ok_or(Error::NotDetected)?;
+        grade_matrix(gray, &grid, format, value)?
+    } else {
+        let points: Vec<Point> = result.getPoints().iter().map(|p| point(p.x, p.y)).collect();
+        grade_linear(gray, format, value, &points)?
+    };
+
+    Ok((format, value.to_string(), grading))
+}
+
+fn grade_gray_image(gray: &GrayImage) -> Result<JsValue, Error> {
+    let (format, value, grading) = grade(gray)?;
+    Ok(grading_to_js(&value, format, &grading))
+}
+
+/// Grades the symbol in an encoded image (PNG, JPEG, ...).
+pub fn grade_bytes(bytes: &[u8]) -> Result<JsValue, Error> {
+    grade_gray_image(&decode_image_bytes(bytes)?)
+}
+
+/// Grades the symbol in the pixels of an `ImageData`.
+pub fn grade_image_data(image_data: &ImageData) -> Result<JsValue, Error> {
+    let gray = GrayImage::from_raw(
+        image_data.width(),
+        image_data.height(),
+        rgba_to_gray(&image_data.data()),
+    )
+    .ok_or(Error::Internal)?;
+
+    grade_gray_image(&gray)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{
+        Luma,
+        imageops::{self, FilterType},
+    };
+
+    use super::*;
+    use crate::detector::tests::qr_image;
+
+    const VALUE: &str = "WASCAN-042";
+
+    /// A scan profile across a Code 128 symbol printed with `x` samples per
+    /// module, returned with the symbol's bounds.
+    fn code_128(
+        x: usize,
+        quiet_zone: (usize, usize),
+        bar: f64,
+        space: f64,
+    ) -> (Vec<f64>, usize, usize) {
+        let elements = reference_elements(BarcodeFormat::CODE_128, VALUE).unwrap();
+        let mut values = vec![space; quiet_zone.0 * x];
+        for (i, modules) in elements.iter().enumerate() {
+            let reflectance = if i % 2 == 0 { bar } else { space };
+            values.extend(std::iter::repeat_n(reflectance, *modules as usize * x));
+        }
+        let (left, right) = (quiet_zone.0 * x, values.len());
+        values.extend(std::iter::repeat_n(space, quiet_zone.1 * x));
+        (values, left, right)
+    }
+
+    /// Grades a profile, returning each parameter's value and grade.
+    fn grade_profile(
+        values: Vec<f64>,
+        bounds: (usize, usize),
+        value: &str,
+    ) -> Vec<(&'static str, Option<f64>, f64)> {
+        let reference = reference_elements(BarcodeFormat::CODE_128, VALUE);
+        let profile = Profile { values, start: 0 };
+        grade_scan(
+            &profile,
+            bounds,
+            BarcodeFormat::CODE_128,
+            value,
+            reference.as_deref(),
+        )
+        .into_iter()
+        .map(|p| (p.key, p.value, p.grade))
+        .collect()
+    }
+
+    fn parameter(parameters: &[(&'static str, Option<f64>, f64)], key: &str) -> (f64, f64) {
+        let (_, value, grade) = parameters.iter().find(|p| p.0 == key).unwrap();
+        (value.unwrap_or(f64::NAN), *grade)
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn grades_change_at_the_band_edges() {
+        for (value, grade) in [
+            (0.70, 4.0),
+            (0.6999, 3.0),
+            (0.55, 3.0),
+            (0.40, 2.0),
+            (0.20, 1.0),
+            (0.1999, 0.0),
+        ] {
+            assert_eq!(grade_at_least(value, SYMBOL_CONTRAST), grade, "{value}");
+        }
+        for (value, grade) in [
+            (0.0, 4.0),
+            (0.15, 4.0),
+            (0.1501, 3.0),
+            (0.25, 2.0),
+            (0.30, 1.0),
+            (0.3001, 0.0),
+        ] {
+            assert_eq!(grade_at_most(value, DEFECTS), grade, "{value}");
+        }
+        for (grade, expected) in [
+            (4.0, "A"),
+            (3.5, "A"),
+            (3.49, "B"),
+            (2.5, "B"),
+            (1.5, "C"),
+            (0.5, "D"),
+            (0.49, "F"),
+        ] {
+            assert_eq!(letter(grade), expected, "{grade}");
+        }
+    }
+
+    #[test]
+    fn a_clean_linear_profile_grades_a() {
+        let (values, left, right) = code_128(4, (12, 12), 0.05, 0.85);
+        let parameters = grade_profile(values, (left, right), VALUE);
+
+        assert_eq!(parameters.len(), 8);
+        for (key, _, grade) in &parameters {
+            assert_eq!(*grade, 4.0, "{key}");
+        }
+        assert_close(parameter(&parameters, "symbolContrast").0, 0.80);
+        assert_close(parameter(&parameters, "minReflectance").0, 0.05);
+        assert_close(parameter(&parameters, "modulation").0, 1.0);
+        assert_close(parameter(&parameters, "defects").0, 0.0);
+        assert_close(parameter(&parameters, "decodability").0, 1.0);
+        assert_close(parameter(&parameters, "quietZone").0, 10.0);
+    }
+
+    #[test]
+    fn symbol_contrast_and_min_reflectance_follow_the_reflectances() {
+        let (values, left, right) = code_128(4, (12, 12), 0.35, 0.80);
+        let parameters = grade_profile(values, (left, right), VALUE);
+        assert_eq!(
+            parameter(&parameters, "symbolContrast"),
+            (0.45000000000000007, 2.0)
+        );
+        assert_eq!(parameter(&parameters, "minReflectance").1, 4.0);
+
+        // Bars lighter than half the lightest space fail outright.
+        let (values, left, right) = code_128(4, (12, 12), 0.50, 0.95);
+        let parameters = grade_profile(values, (left, right), VALUE);
+        assert_eq!(parameter(&parameters, "minReflectance"), (0.5, 0.0));
+    }
+
+    #[test]
+    fn a_grey_space_lowers_modulation_and_edge_contrast() {
+        let (mut values, left, right) = code_128(4, (12, 12), 0.10, 0.90);
+        // The first space of the start character.
+        let space = left + 2 * 4;
+        values[space..space + 4].fill(0.55);
+        let parameters = grade_profile(values, (left, right), VALUE);
+
+        let (edge_contrast, grade) = parameter(&parameters, "minEdgeContrast");
+        assert_close(edge_contrast, 0.45);
+        assert_eq!(grade, 4.0);
+        let (modulation, grade) = parameter(&parameters, "modulation");
+        assert_close(modulation, 0.5625);
+        assert_eq!(grade, 2.0);
+    }
+
+    #[test]
+    fn a_void_in_a_bar_is_a_defect() {
+        let (mut values, left, right) = code_128(4, (12, 12), 0.10, 0.90);
+        // The start character begins with a two-module bar.
+        values[left + 4] = 0.30;
+        let parameters = grade_profile(values, (left, right), VALUE);
+
+        let (defects, grade) = parameter(&parameters, "defects");
+        assert_close(defects, 0.25);
+        assert_eq!(grade, 2.0);
+        assert_eq!(parameter(&parameters, "decode").1, 4.0);
+    }
+
+    #[test]
+    fn ink_spread_lowers_decodability() {
+        let x = 10;
+        let (values, left, right) = code_128(x, (12, 12), 0.10, 0.90);
+        // Every bar grows by a tenth of a module on each side.
+        let spread: Vec<f64> = (0..values.len())
+            .map(|i| {
+                let near = i.saturating_sub(1)..(i + 2).min(values.len());
+                if (left - 1..right + 1).contains(&i) && values[near].contains(&0.10) {
+                    0.10
+                } else {
+                    values[i]
+                }
+            })
+            .collect();
+        let parameters = grade_profile(spread, (left - 1, right + 1), VALUE);
+
+        let (decodability, grade) = parameter(&parameters, "decodability");
+        assert!((0.55..0.62).contains(&decodability), "{decodability}");
+        assert_eq!(grade, 3.0);
+        assert_eq!(parameter(&parameters, "decode").1, 4.0);
+    }
+
+    #[test]
+    fn a_short_quiet_zone_fails() {
+        let (values, left, right) = code_128(4, (5, 12), 0.05, 0.85);
+        let parameters = grade_profile(values, (left, right), VALUE);
+        assert_eq!(parameter(&parameters, "quietZone"), (5.0, 0.0));
+    }
+
+    #[test]
+    fn decode_fails_when_the_scan_reads_another_value() {
+        let (values, left, right) = code_128(4, (12, 12), 0.05, 0.85);
+        let parameters = grade_profile(values, (left, right), "WASCAN-043");
+        assert_eq!(parameter(&parameters, "decode").1, 0.0);
+    }
+
+    /// Maps black and white to the given reflectances.
+    fn with_reflectances(image: &GrayImage, dark: f64, light: f64) -> GrayImage {
+        GrayImage::from_fn(image.width(), image.height(), |x, y| {
+            let r = if image.get_pixel(x, y).0[0] < 128 {
+                dark
+            } else {
+                light
+            };
+            Luma([(r * 255.0).round() as u8])
+        })
+    }
+
+    fn grade_of(grading: &Grading, key: &str) -> (Option<f64>, f64) {
+        let parameter = grading.parameters.iter().find(|p| p.key == key).unwrap();
+        (parameter.value, parameter.grade)
+    }
+
+    #[test]
+    fn linear_images_grade_over_the_scan_lines() {
+        let symbol = generator::encode(BarcodeFormat::CODE_128, VALUE, None, false).unwrap();
+        let (module, quiet) = (3, 12);
+        let image = GrayImage::from_fn((symbol.cols + 2 * quiet) * module, 60, |x, y| {
+            let col = (x / module).wrapping_sub(quiet);
+            let dark = (10..50).contains(&y) && col < symbol.cols && symbol.is_dark(col, 0);
+            Luma([if dark { 64 } else { 224 }])
+        });
+
+        let (format, value, grading) = grade(&image).unwrap();
+        assert_eq!((format, value.as_str()), (BarcodeFormat::CODE_128, VALUE));
+        assert_eq!(grading.scans, Some(SCAN_COUNT));
+        // Contrast (224 - 64) / 255 = 0.63 is a B, and the lowest grade.
+        assert_eq!(grade_of(&grading, "symbolContrast").1, 3.0);
+        assert_eq!(grading.grade, 3.0);
+    }
+
+    #[test]
+    fn a_clean_qr_code_grades_a() {
+        let (format, value, grading) = grade(&qr_image("wascan grading", 6)).unwrap();
+        assert_eq!(
+            (format, value.as_str()),
+            (BarcodeFormat::QR_CODE, "wascan grading")
+        );
+        for parameter in &grading.parameters {
+            assert_eq!(parameter.grade, 4.0, "{}", parameter.key);
+        }
+        assert_eq!(grade_of(&grading, "unusedErrorCorrection").0, Some(1.0));
+        assert_eq!(grade_of(&grading, "axialNonUniformity").0, Some(0.0));
+        assert_eq!(grading.grade, 4.0);
+    }
+
+    #[test]
+    fn matrix_contrast_sets_the_overall_grade() {
+        let image = with_reflectances(&qr_image("wascan grading", 6), 0.40, 0.85);
+        let (_, _, grading) = grade(&image).unwrap();
+
+        let (contrast, grade) = grade_of(&grading, "symbolContrast");
+        assert!((contrast.unwrap() - 0.45).abs() < 0.01);
+        assert_eq!(grade, 2.0);
+        assert_eq!(grading.grade, 2.0);
+    }
+
+    #[test]
+    fn stretched_matrix_symbols_lose_axial_uniformity() {
+        let image = qr_image("wascan grading", 6);
+        let stretched = imageops::resize(
+            &image,
+            image.width() * 110 / 100,
+            image.height(),
+            FilterType::Nearest,
+        );
+        let (_, _, grading) = grade(&stretched).unwrap();
+
+        let (non_uniformity, grade) = grade_of(&grading, "axialNonUniformity");
+        assert!(
+            (0.08..0.10).contains(&non_uniformity.unwrap()),
+            "{non_uniformity:?}"
+        );
+        assert_eq!(grade, 2.0);
+    }
+
+    #[test]
+    fn damaged_timing_modules_are_counted() {
+        let mut image = qr_image("wascan grading", 6);
+        // Invert two dark modules of the horizontal timing pattern on row 6,
+        // behind the writer's and the image's 4-module quiet zones.
+        for module in [8, 12] {
+            for y in 0..6 {
+                for x in 0..6 {
+                    image.put_pixel((8 + module) * 6 + x, (8 + 6) * 6 + y, Luma([255]));
+                }
+            }
+        }
+        let (_, _, grading) = grade(&image).unwrap();
+
+        assert_eq!(grade_of(&grading, "fixedPatternDamage"), (Some(2.0), 2.0));
+        assert_eq!(grade_of(&grading, "decode").1, 4.0);
+    }
+
+    #[test]
+    fn matrix_decode_uses_the_measured_modules() {
+        let image = qr_image("wascan grading", 6);
+        let (width, height) = image.dimensions();
+        let source = Luma8LuminanceSource::new(image.into_raw(), width, height);
+        let bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+        let grid = qr_grid(bitmap.get_black_matrix()).unwrap();
+
+        let format = BarcodeFormat::QR_CODE;
+        assert!(decodes_grid(&grid, format, "wascan grading", |x, y| grid.is_dark(x, y)));
+        assert!(!decodes_grid(&grid, format, "another value", |x, y| grid.is_dark(x, y)));
+        // Too many modules misread for the error correction to recover.
+        assert!(!decodes_grid(&grid, format, "wascan grading", |x, y| {
+            grid.is_dark(x, y) != (x > 8 && y > 8 && (x + y) % 3 == 0)
+        }));
+    }
+
+    #[test]
+    fn data_matrix_grades_with_a_decode() {
+        let symbol =
+            generator::encode(BarcodeFormat::DATA_MATRIX, "WASCAN 043", None, false).unwrap();
+        let (module, quiet) = (6, 4);
+        let side = |n: u32| (n + 2 * quiet) * module;
+        let image = GrayImage::from_fn(side(symbol.cols), side(symbol.rows), |x, y| {
+            let (col, row) = (
+                (x / module).wrapping_sub(quiet),
+                (y / module).wrapping_sub(quiet),
+            );
+            let dark = col < symbol.cols && row < symbol.rows && symbol.is_dark(col, row);
+            Luma([if dark { 0 } else { 255 }])
+        });
+
+        let (format, value, grading) = grade(&image).unwrap();
+        assert_eq!(
+            (format, value.as_str()),
+            (BarcodeFormat::DATA_MATRIX, "WASCAN 043")
+        );
+        assert_eq!(grade_of(&grading, "decode").1, 4.0);
+        assert_eq!(grade_of(&grading, "fixedPatternDamage").0, Some(0.0));
+        assert_eq!(grading.grade, 4.0);
+    }
+}
@@ -0,0 +1,113 @@
+/// ASCII group separator, which ends variable-length GS1 element strings.
+pub const GS: char = '\u{1d}';
+
+/// Two-digit AI prefixes whose element strings have a predefined length,
+/// including the AI itself, per the GS1 General Specifications.
+const PREDEFINED_LENGTHS: [(&str, usize); 19] = [
+    ("00", 20),
+    ("01", 16),
+    ("02", 16),
+    ("03", 16),
+    ("04", 18),
+    ("11", 8),
+    ("12", 8),
+    ("13", 8),
+    ("14", 8),
+    ("15", 8),
+    ("16", 8),
+    ("17", 8),
+    ("18", 8),
+    ("19", 8),
+    ("20", 4),
+    ("31", 10),
+    ("32", 10),
+    ("33", 10),
+    ("34", 10),
+];
+
+/// Returns the length, AI included, of an element string starting with
+/// `prefix`, or `None` if it is variable-length and ends with a GS.
+pub fn predefined_length(prefix: &str) -> Option<usize> {
+    PREDEFINED_LENGTHS
+        .iter()
+        .find(|(ai, _)| *ai == prefix)
+        .map(|(_, len)| *len)
+}
+
+/// Converts the human-readable `(01)...(10)...` form to an element string,
+/// with a GS after each variable-length field that is not the last.
+pub fn element_string_from_hri(hri: &str) -> Option<String> {
+    let elements = hri
+        .strip_prefix('(')?
+        .split('(')
+        .map(|element| element.split_once(')'))
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut out = String::new();
+    for (i, (ai, data)) in elements.iter().enumerate() {
+        if !(2..=4).contains(&ai.len()) || !ai.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        out.push_str(ai);
+        out.push_str(data);
+        if i + 1 < elements.len() && predefined_length(&ai[..2]).is_none() {
+            out.push(GS);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn predefined_lengths_follow_the_ai_prefix() {
+        assert_eq!(predefined_length("01"), Some(16));
+        assert_eq!(predefined_length("17"), Some(8));
+        assert_eq!(predefined_length("31"), Some(10));
+        assert_eq!(predefined_length("10"), None);
+        assert_eq!(predefined_length("21"), None);
+    }
+
+    #[test]
+    fn separators_follow_variable_length_fields_that_are_not_last() {
+        assert_eq!(
+            element_string_from_hri("(01)00312345678906").as_deref(),
+            Some("0100312345678906")
+        );
+        assert_eq!(
+            element_string_from_hri("(01)00312345678906(17)261231(10)AB-12").as_deref(),
+            Some("01003123456789061726123110AB-12")
+        );
+        assert_eq!(
+            element_string_from_hri("(01)00312345678906(10)AB-12(21)0042").as_deref(),
+            Some("010031234567890610AB-12\u{1d}210042")
+        );
+        // Four-digit AIs take the length of their two-digit prefix.
+        assert_eq!(
+            element_string_from_hri("(3103)000250(392)0199").as_deref(),
+            Some("31030002503920199")
+        );
+        assert_eq!(
+            element_string_from_hri("(8200)https://example.com(10)A").as_deref(),
+            Some("8200https://example.com\u{1d}10A")
+        );
+    }
+
+    #[test]
+    fn malformed_hri_is_rejected() {
+        for hri in [
+            "",
+            "0100312345678906",
+            "(01",
+            "(1)5",
+            "(01234)5",
+            "(0A)5",
+            "(01)00312345678906(10",
+            "x(01)00312345678906",
+        ] {
+            assert_eq!(element_string_from_hri(hri), None, "{hri}");
+        }
+    }
+}
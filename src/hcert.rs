@@ -0,0 +1,522 @@
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+use js_sys::{Array, Date, Object, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::{
+    cbor::{self, Value},
+    crypto::{Algorithm, PublicKey, base64_decode, base64_encode, sha256},
+    error::Error,
+    options,
+};
+
+const HC1_PREFIX: &str = "HC1:";
+const BASE45_ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Largest inflated payload accepted, to bound the memory a crafted code can
+/// claim.
+const MAX_PAYLOAD_LEN: u64 = 1 << 20;
+
+const COSE_SIGN1_TAG: u64 = 18;
+const COSE_HEADER_ALG: i128 = 1;
+const COSE_HEADER_KID: i128 = 4;
+const COSE_ALG_ES256: i128 = -7;
+const COSE_ALG_PS256: i128 = -37;
+
+const CWT_ISSUER: i128 = 1;
+const CWT_EXPIRES_AT: i128 = 4;
+const CWT_ISSUED_AT: i128 = 6;
+const CWT_HCERT: i128 = -260;
+const HCERT_EU_DCC: i128 = 1;
+
+/// Length of a key identifier derived from a certificate's SHA-256 hash.
+const KID_LEN: usize = 8;
+
+/// A trusted signing key and the key identifier it is looked up by.
+struct TrustedKey {
+    kid: Vec<u8>,
+    key: PublicKey,
+}
+
+fn parse_trust_list(options: &JsValue) -> Result<Vec<TrustedKey>, Error> {
+    let obj = options::as_object(options)?;
+    let Some(list) = options::get(&obj, "trustList") else {
+        return Ok(Vec::new());
+    };
+    let list = list
+        .dyn_into::<Array>()
+        .map_err(|_| Error::InvalidOptions)?;
+
+    list.iter()
+        .map(|entry| {
+            let entry = options::as_object(&entry)?;
+            let public_key =
+                options::get_string(&entry, "publicKey")?.ok_or(Error::InvalidOptions)?;
+            trusted_key(&public_key, options::get_string(&entry, "kid")?.as_deref())
+        })
+        .collect()
+}
+
+/// Reads a trust list entry. Certificates are identified by the start of
+/// their hash, as in the EU DCC trust lists; bare keys need an explicit kid.
+fn trusted_key(public_key: &str, kid: Option<&str>) -> Result<TrustedKey, Error> {
+    let (key, certificate) = PublicKey::from_pem_or_base64(public_key)?;
+    let kid = match kid {
+        Some(kid) => base64_decode(kid).ok_or(Error::InvalidOptions)?,
+        None => sha256(&certificate.ok_or(Error::InvalidOptions)?)[..KID_LEN].to_vec(),
+    };
+    Ok(TrustedKey { kid, key })
+}
+
+/// Decodes Base45 (RFC 9285), where each group of three characters encodes
+/// two bytes and a trailing pair encodes one.
+fn base45_decode(text: &str) -> Option<Vec<u8>> {
+    let values: Vec<u32> = text
+        .bytes()
+        .map(|c| {
+            BASE45_ALPHABET
+                .iter()
+                .position(|a| *a == c)
+                .map(|v| v as u32)
+        })
+        .collect::<Option<_>>()?;
+
+    let mut out = Vec::with_capacity(values.len() * 2 / 3 + 1);
+    for chunk in values.chunks(3) {
+        match *chunk {
+            [c, d, e] => {
+                let n = c + d * 45 + e * 45 * 45;
+                if n > 0xffff {
+                    return None;
+                }
+                out.extend((n as u16).to_be_bytes());
+            }
+            [c, d] => {
+                let n = c + d * 45;
+                if n > 0xff {
+                    return None;
+                }
+                out.push(n as u8);
+            }
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+/// Inflates a zlib stream. Uncompressed payloads, which the specification
+/// also allows, are returned as they are.
+fn inflate(data: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if data.first() != Some(&0x78) {
+        return Ok(data);
+    }
+
+    let mut out = Vec::new();
+    ZlibDecoder::new(data.as_slice())
+        .take(MAX_PAYLOAD_LEN + 1)
+        .read_to_end(&mut out)
+        .map_err(|_| Error::PayloadDecodeFailed)?;
+    if out.len() as u64 > MAX_PAYLOAD_LEN {
+        return Err(Error::PayloadDecodeFailed);
+    }
+    Ok(out)
+}
+
+/// The parts of a COSE_Sign1 message (RFC 9052).
+struct CoseSign1 {
+    protected: Vec<u8>,
+    protected_headers: Value,
+    unprotected_headers: Value,
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    fn parse(data: &[u8]) -> Result<Self, Error> {
+        let message = match cbor::parse(data)? {
+            Value::Tag(COSE_SIGN1_TAG, inner) => *inner,
+            Value::Tag(..) => return Err(Error::PayloadDecodeFailed),
+            other => other,
+        };
+        let Value::Array(items) = message else {
+            return Err(Error::PayloadDecodeFailed);
+        };
+        let [protected, unprotected, payload, signature] = items.as_slice() else {
+            return Err(Error::PayloadDecodeFailed);
+        };
+
+        let bytes = |value: &Value| {
+            value
+                .as_bytes()
+                .map(<[u8]>::to_vec)
+                .ok_or(Error::PayloadDecodeFailed)
+        };
+        let protected = bytes(protected)?;
+        // An empty protected header stands for an empty map.
+        let protected_headers = if protected.is_empty() {
+            Value::Map(Vec::new())
+        } else {
+            cbor::parse(&protected)?
+        };
+
+        Ok(CoseSign1 {
+            protected,
+            protected_headers,
+            unprotected_headers: unprotected.clone(),
+            payload: bytes(payload)?,
+            signature: bytes(signature)?,
+        })
+    }
+
+    /// Looks up a header, preferring the protected bucket.
+    fn header(&self, label: i128) -> Option<&Value> {
+        self.protected_headers
+            .get(label)
+            .or_else(|| self.unprotected_headers.get(label))
+    }
+
+    /// Builds the `Sig_structure` the signature is computed over.
+    fn signed_data(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.protected.len() + self.payload.len() + 20);
+        cbor::write_head(&mut out, 4, 4);
+        cbor::write_text(&mut out, "Signature1");
+        cbor::write_bytes(&mut out, &self.protected);
+        cbor::write_bytes(&mut out, &[]);
+        cbor::write_bytes(&mut out, &self.payload);
+        out
+    }
+}
+
+fn algorithm(value: Option<&Value>) -> Option<Algorithm> {
+    match value?.as_integer()? {
+        COSE_ALG_ES256 => Some(Algorithm::Es256),
+        COSE_ALG_PS256 => Some(Algorithm::Ps256),
+        _ => None,
+    }
+}
+
+fn algorithm_to_js(value: Option<&Value>) -> JsValue {
+    match algorithm(value) {
+        Some(Algorithm::Es256) => JsValue::from_str("ES256"),
+        Some(Algorithm::Ps256) => JsValue::from_str("PS256"),
+        None => value.map_or(JsValue::NULL, Value::to_js),
+    }
+}
+
+/// Checks the signature against the trust list, returning the
+/// `verification` status reported to the caller.
+fn verify(message: &CoseSign1, trust_list: &[TrustedKey]) -> &'static str {
+    if trust_list.is_empty() {
+        return "unchecked";
+    }
+    let Some(algorithm) = algorithm(message.header(COSE_HEADER_ALG)) else {
+        return "unsupported_algorithm";
+    };
+    let kid = message.header(COSE_HEADER_KID).and_then(Value::as_bytes);
+    let mut candidates = trust_list
+        .iter()
+        .filter(|trusted| kid.is_none_or(|kid| trusted.kid == kid))
+        .peekable();
+    if candidates.peek().is_none() {
+        return "unknown_key";
+    }
+
+    let signed = message.signed_data();
+    if candidates.any(|trusted| trusted.key.verify(algorithm, &signed, &message.signature)) {
+        "valid"
+    } else {
+        "invalid"
+    }
+}
+
+/// Decodes an `HC1:` health certificate, optionally verifying its signature
+/// against the trust list in `options`.
+pub fn decode_health_certificate(value: &str, options: &JsValue) -> Result<JsValue, Error> {
+    let trust_list = parse_trust_list(options)?;
+
+    let body = value
+        .trim()
+        .strip_prefix(HC1_PREFIX)
+        .ok_or(Error::UnsupportedFormat)?;
+    let compressed = base45_decode(body).ok_or(Error::PayloadDecodeFailed)?;
+    let message = CoseSign1::parse(&inflate(compressed)?)?;
+    let claims = cbor::parse(&message.payload)?;
+    let certificate = claims
+        .get(CWT_HCERT)
+        .and_then(|hcert| hcert.get(HCERT_EU_DCC))
+        .ok_or(Error::PayloadDecodeFailed)?;
+
+    let expires_at = claims.get(CWT_EXPIRES_AT).and_then(Value::as_integer);
+    let expired = expires_at.is_some_and(|exp| (exp as f64) * 1000.0 < Date::now());
+    let kid = message
+        .header(COSE_HEADER_KID)
+        .and_then(Value::as_bytes)
+        .map(base64_encode);
+
+    let obj = Object::new();
+    let integer =
+        |value: Option<i128>| value.map_or(JsValue::NULL, |n| JsValue::from_f64(n as f64));
+    for (key, value) in [
+        (
+            "kid",
+            kid.map_or(JsValue::NULL, |kid| JsValue::from_str(&kid)),
+        ),
+        (
+            "algorithm",
+            algorithm_to_js(message.header(COSE_HEADER_ALG)),
+        ),
+        (
+            "issuer",
+            claims
+                .get(CWT_ISSUER)
+                .and_then(Value::as_text)
+                .map_or(JsValue::NULL, JsValue::from_str),
+        ),
+        (
+            "issuedAt",
+            integer(claims.get(CWT_ISSUED_AT).and_then(Value::as_integer)),
+        ),
+        ("expiresAt", integer(expires_at)),
+        ("expired", JsValue::from_bool(expired)),
+        ("certificate", certificate.to_js()),
+        ("claims", claims.to_js()),
+        (
+            "verification",
+            JsValue::from_str(verify(&message, &trust_list)),
+        ),
+    ] {
+        let _ = Reflect::set(&obj, &JsValue::from_str(key), &value);
+    }
+    Ok(obj.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::ZlibEncoder};
+
+    use super::*;
+    use crate::crypto::tests::{certificate, es256, pem, signing_key};
+
+    fn base45_encode(bytes: &[u8]) -> String {
+        let mut out = String::new();
+        for chunk in bytes.chunks(2) {
+            let (mut n, digits) = match *chunk {
+                [a, b] => ((a as usize) << 8 | b as usize, 3),
+                [a] => (a as usize, 2),
+                _ => unreachable!(),
+            };
+            for _ in 0..digits {
+                out.push(BASE45_ALPHABET[n % 45] as char);
+                n /= 45;
+            }
+        }
+        out
+    }
+
+    /// CWT claims holding a minimal EU DCC.
+    fn claims() -> Vec<u8> {
+        let mut out = Vec::new();
+        cbor::write_head(&mut out, 5, 4);
+        cbor::write_head(&mut out, 0, CWT_ISSUER as u64);
+        cbor::write_text(&mut out, "AT");
+        cbor::write_head(&mut out, 0, CWT_EXPIRES_AT as u64);
+        cbor::write_head(&mut out, 0, 1_830_297_600);
+        cbor::write_head(&mut out, 0, CWT_ISSUED_AT as u64);
+        cbor::write_head(&mut out, 0, 1_767_225_600);
+        cbor::write_head(&mut out, 1, (-1 - CWT_HCERT) as u64);
+        cbor::write_head(&mut out, 5, 1);
+        cbor::write_head(&mut out, 0, HCERT_EU_DCC as u64);
+        cbor::write_head(&mut out, 5, 1);
+        cbor::write_text(&mut out, "ver");
+        cbor::write_text(&mut out, "1.3.0");
+        out
+    }
+
+    /// A tagged COSE_Sign1 message with the algorithm and kid protected.
+    fn cose_sign1(alg: i128, kid: &[u8], sign: impl Fn(&[u8]) -> Vec<u8>) -> Vec<u8> {
+        let mut protected = Vec::new();
+        cbor::write_head(&mut protected, 5, 2);
+        cbor::write_head(&mut protected, 0, COSE_HEADER_ALG as u64);
+        cbor::write_head(&mut protected, 1, (-1 - alg) as u64);
+        cbor::write_head(&mut protected, 0, COSE_HEADER_KID as u64);
+        cbor::write_bytes(&mut protected, kid);
+        let payload = claims();
+
+        let mut signed = Vec::new();
+        cbor::write_head(&mut signed, 4, 4);
+        cbor::write_text(&mut signed, "Signature1");
+        cbor::write_bytes(&mut signed, &protected);
+        cbor::write_bytes(&mut signed, &[]);
+        cbor::write_bytes(&mut signed, &payload);
+
+        let mut out = Vec::new();
+        cbor::write_head(&mut out, 6, COSE_SIGN1_TAG);
+        cbor::write_head(&mut out, 4, 4);
+        cbor::write_bytes(&mut out, &protected);
+        cbor::write_head(&mut out, 5, 0);
+        cbor::write_bytes(&mut out, &payload);
+        cbor::write_bytes(&mut out, &sign(&signed));
+        out
+    }
+
+    fn hc1(cose: &[u8]) -> String {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(cose).unwrap();
+        format!("HC1:{}", base45_encode(&encoder.finish().unwrap()))
+    }
+
+    /// Decodes an `HC1:` value up to the signature check, as
+    /// `decode_health_certificate` does.
+    fn decode(value: &str) -> Result<CoseSign1, Error> {
+        let body = value.strip_prefix(HC1_PREFIX).unwrap();
+        let compressed = base45_decode(body).ok_or(Error::PayloadDecodeFailed)?;
+        CoseSign1::parse(&inflate(compressed)?)
+    }
+
+    /// The first eight bytes of the certificate's SHA-256 hash.
+    fn certificate_kid() -> Vec<u8> {
+        sha256(&certificate(&signing_key()))[..KID_LEN].to_vec()
+    }
+
+    fn trust_list() -> Vec<TrustedKey> {
+        let certificate = pem("CERTIFICATE", &certificate(&signing_key()));
+        vec![trusted_key(&certificate, None).unwrap()]
+    }
+
+    #[test]
+    fn base45_decodes_the_rfc_9285_examples() {
+        for (text, bytes) in [
+            ("", ""),
+            ("BB8", "AB"),
+            ("%69 VD92EX0", "Hello!!"),
+            ("UJCLQE7W581", "base-45"),
+            ("QED8WEX0", "ietf!"),
+        ] {
+            assert_eq!(base45_decode(text).unwrap(), bytes.as_bytes(), "{text}");
+            assert_eq!(base45_encode(bytes.as_bytes()), text);
+        }
+    }
+
+    #[test]
+    fn base45_rejects_malformed_input() {
+        // The largest group values, then one past them.
+        assert_eq!(base45_decode("FGW").unwrap(), [0xff, 0xff]);
+        assert_eq!(base45_decode("U5").unwrap(), [0xff]);
+        assert_eq!(base45_decode("GGW"), None);
+        assert_eq!(base45_decode("V5"), None);
+        // A lone trailing character.
+        assert_eq!(base45_decode("BB8A"), None);
+        // Characters outside the alphabet, including lowercase.
+        assert_eq!(base45_decode("bb8"), None);
+        assert_eq!(base45_decode("BB8\n"), None);
+        assert_eq!(base45_decode("BB8#"), None);
+    }
+
+    #[test]
+    fn certificate_kids_are_derived_from_the_hash() {
+        let trust_list = trust_list();
+        assert_eq!(trust_list[0].kid, certificate_kid());
+
+        // An explicit kid is used as given.
+        let certificate = pem("CERTIFICATE", &certificate(&signing_key()));
+        let explicit = trusted_key(&certificate, Some("AQIDBA==")).unwrap();
+        assert_eq!(explicit.kid, [1, 2, 3, 4]);
+        assert!(trusted_key(&certificate, Some("AQ-D")).is_err());
+
+        // A bare key has no certificate to hash.
+        let spki = crate::crypto::tests::spki(&signing_key());
+        assert!(trusted_key(&pem("PUBLIC KEY", &spki), None).is_err());
+        assert!(trusted_key(&pem("PUBLIC KEY", &spki), Some("AQIDBA==")).is_ok());
+    }
+
+    #[test]
+    fn valid_signatures_verify() {
+        let key = signing_key();
+        let value = hc1(&cose_sign1(COSE_ALG_ES256, &certificate_kid(), |data| {
+            es256(&key, data)
+        }));
+        let message = decode(&value).unwrap();
+
+        assert_eq!(verify(&message, &trust_list()), "valid");
+        assert_eq!(verify(&message, &[]), "unchecked");
+
+        let claims = cbor::parse(&message.payload).unwrap();
+        assert_eq!(claims.get(CWT_ISSUER).and_then(Value::as_text), Some("AT"));
+        assert_eq!(
+            claims.get(CWT_EXPIRES_AT).and_then(Value::as_integer),
+            Some(1_830_297_600)
+        );
+        let dcc = claims.get(CWT_HCERT).and_then(|h| h.get(HCERT_EU_DCC));
+        assert!(matches!(dcc, Some(Value::Map(entries)) if entries.len() == 1));
+    }
+
+    #[test]
+    fn tampered_signatures_are_invalid() {
+        let key = signing_key();
+        let value = hc1(&cose_sign1(COSE_ALG_ES256, &certificate_kid(), |data| {
+            let mut signature = es256(&key, data);
+            signature[0] ^= 0x80;
+            signature
+        }));
+        assert_eq!(verify(&decode(&value).unwrap(), &trust_list()), "invalid");
+
+        // A signature over other claims.
+        let value = hc1(&cose_sign1(COSE_ALG_ES256, &certificate_kid(), |data| {
+            es256(&key, &data[..data.len() - 1])
+        }));
+        assert_eq!(verify(&decode(&value).unwrap(), &trust_list()), "invalid");
+    }
+
+    #[test]
+    fn unknown_kids_and_algorithms_are_reported() {
+        let key = signing_key();
+        let sign = |data: &[u8]| es256(&key, data);
+
+        let value = hc1(&cose_sign1(COSE_ALG_ES256, b"unknown!", sign));
+        assert_eq!(
+            verify(&decode(&value).unwrap(), &trust_list()),
+            "unknown_key"
+        );
+
+        // ES384 is not supported.
+        let value = hc1(&cose_sign1(-35, &certificate_kid(), sign));
+        assert_eq!(
+            verify(&decode(&value).unwrap(), &trust_list()),
+            "unsupported_algorithm"
+        );
+    }
+
+    #[test]
+    fn malformed_messages_fail_to_decode() {
+        let key = signing_key();
+        let cose = cose_sign1(COSE_ALG_ES256, &certificate_kid(), |data| es256(&key, data));
+
+        // Uncompressed payloads are allowed.
+        assert!(decode(&format!("HC1:{}", base45_encode(&cose))).is_ok());
+        // Truncated messages, inside the COSE structure or the zlib stream.
+        assert!(decode(&format!("HC1:{}", base45_encode(&cose[..cose.len() - 5]))).is_err());
+        let value = hc1(&cose);
+        assert!(decode(&value[..value.len() - 6]).is_err());
+        // Tags other than COSE_Sign1, and arrays of the wrong shape.
+        let mut other_tag = cose.clone();
+        other_tag[0] = 0xd1;
+        assert!(decode(&hc1(&other_tag)).is_err());
+        assert!(decode(&hc1(&[0x83, 0x40, 0xa0, 0x40])).is_err());
+        // Headers that are not byte strings.
+        assert!(decode(&hc1(&[0x84, 0x00, 0xa0, 0x40, 0x40])).is_err());
+    }
+
+    #[test]
+    fn inflation_is_bounded() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder
+            .write_all(&vec![0; MAX_PAYLOAD_LEN as usize + 1])
+            .unwrap();
+        let bomb = encoder.finish().unwrap();
+        assert!(bomb.len() < 4096);
+        assert!(matches!(inflate(bomb), Err(Error::PayloadDecodeFailed)));
+    }
+}
@@ -0,0 +1,730 @@
+use std::{fmt::Write as _, io::Cursor};
+
+use image::{GrayImage, ImageFormat, Luma};
+use js_sys::{Array, Object, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::{
+    detector::format_from_name,
+    error::Error,
+    font,
+    generator::{self, Symbol},
+    options,
+};
+
+const GROUP_SEPARATOR: char = '\u{1d}';
+
+const DEFAULT_DPI: u32 = 203;
+/// Gap between labels, in dots, for EPL's `Q` command.
+const DEFAULT_GAP: u32 = 24;
+const MAX_LABEL_DOTS: u32 = 8000;
+
+/// EPL2 resident fonts 1-5 as `(width, height)` in dots.
+const EPL_FONTS_203_DPI: [(u32, u32); 5] = [(8, 12), (10, 16), (12, 20), (14, 24), (32, 48)];
+const EPL_FONTS_300_DPI: [(u32, u32); 5] = [(12, 20), (16, 28), (20, 36), (24, 44), (48, 80)];
+
+enum Output {
+    Zpl,
+    Epl,
+    Png,
+}
+
+struct Text {
+    x: u32,
+    y: u32,
+    text: String,
+    height: u32,
+    width: Option<u32>,
+}
+
+struct Barcode {
+    x: u32,
+    y: u32,
+    symbol: Symbol,
+    module_width: u32,
+    /// Bar height of linear symbols; 2D symbols use square modules.
+    height: u32,
+}
+
+/// A rectangle outline, filled when the thickness reaches its middle.
+struct Frame {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    thickness: u32,
+}
+
+enum Element {
+    Text(Text),
+    Barcode(Barcode),
+    Frame(Frame),
+}
+
+/// A label layout with positions and sizes in printer dots.
+struct Label {
+    width: u32,
+    height: u32,
+    dpi: u32,
+    gap: u32,
+    elements: Vec<Element>,
+}
+
+/// A 1-bit image in printer dots.
+struct Bitmap {
+    width: u32,
+    height: u32,
+    dots: Vec<bool>,
+}
+
+impl Bitmap {
+    fn new(width: u32, height: u32) -> Self {
+        Bitmap {
+            width,
+            height,
+            dots: vec![false; (width * height) as usize],
+        }
+    }
+
+    /// Sets the dots of a rectangle, clipped to the bitmap.
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        let (x_end, y_end) = (
+            x.saturating_add(width).min(self.width),
+            y.saturating_add(height).min(self.height),
+        );
+        for row in y.min(y_end)..y_end {
+            let start = (row * self.width) as usize;
+            self.dots[start + x.min(x_end) as usize..start + x_end as usize].fill(true);
+        }
+    }
+
+    /// Packs each row into bytes, most significant bit first, as ZPL and EPL
+    /// graphics expect. Returns the bytes per row and the packed rows.
+    fn pack(&self) -> (usize, Vec<u8>) {
+        let bytes_per_row = self.width.div_ceil(8) as usize;
+        let mut packed = vec![0u8; bytes_per_row * self.height as usize];
+        for (i, _) in self.dots.iter().enumerate().filter(|(_, dark)| **dark) {
+            let (x, y) = (i % self.width as usize, i / self.width as usize);
+            packed[y * bytes_per_row + x / 8] |= 0x80 >> (x % 8);
+        }
+        (bytes_per_row, packed)
+    }
+}
+
+impl Barcode {
+    fn width(&self) -> u32 {
+        self.symbol.cols * self.module_width
+    }
+
+    fn row_height(&self) -> u32 {
+        if self.symbol.linear {
+            self.height
+        } else {
+            self.module_width
+        }
+    }
+
+    fn total_height(&self) -> u32 {
+        self.symbol.rows * self.row_height()
+    }
+
+    fn draw(&self, bitmap: &mut Bitmap, x: u32, y: u32) {
+        let row_height = self.row_height();
+        for row in 0..self.symbol.rows {
+            for (col, len) in self.symbol.dark_runs(row) {
+                bitmap.fill(
+                    x + col * self.module_width,
+                    y + row * row_height,
+                    len * self.module_width,
+                    row_height,
+                );
+            }
+        }
+    }
+
+    /// Renders the symbol on its own, clipped to the label. Returns `None`
+    /// when it lies entirely outside.
+    fn to_bitmap(&self, label: &Label) -> Option<Bitmap> {
+        let width = self.width().min(label.width.saturating_sub(self.x));
+        let height = self.total_height().min(label.height.saturating_sub(self.y));
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let mut bitmap = Bitmap::new(width, height);
+        self.draw(&mut bitmap, 0, 0);
+        Some(bitmap)
+    }
+}
+
+fn mm_to_dots(mm: f64, dpi: u32) -> u32 {
+    ((mm * dpi as f64 / 25.4).round() as u32).max(1)
+}
+
+/// Reads a position in dots, which must lie within the largest label.
+fn get_position(obj: &Object, key: &str) -> Result<u32, Error> {
+    match options::get_u32(obj, key)? {
+        Some(dots) if dots > MAX_LABEL_DOTS => Err(Error::InvalidOptions),
+        dots => Ok(dots.unwrap_or(0)),
+    }
+}
+
+/// Reads a size in dots, which must be positive and at most the largest
+/// label when present.
+fn get_dots(obj: &Object, key: &str) -> Result<Option<u32>, Error> {
+    match options::get_u32(obj, key)? {
+        Some(dots) if dots == 0 || dots > MAX_LABEL_DOTS => Err(Error::InvalidOptions),
+        dots => Ok(dots),
+    }
+}
+
+fn parse_text(obj: &Object, dpi: u32) -> Result<Text, Error> {
+    Ok(Text {
+        x: get_position(obj, "x")?,
+        y: get_position(obj, "y")?,
+        text: options::get_string(obj, "text")?.ok_or(Error::InvalidOptions)?,
+        height: get_dots(obj, "height")?.unwrap_or_else(|| mm_to_dots(3.0, dpi)),
+        width: get_dots(obj, "width")?,
+    })
+}
+
+/// Parses a barcode field, adding its human-readable line as a text field
+/// below the bars of linear symbols.
+fn parse_barcode(obj: &Object, dpi: u32, elements: &mut Vec<Element>) -> Result<(), Error> {
+    let format = options::get_string(obj, "format")?
+        .as_deref()
+        .and_then(format_from_name)
+        .ok_or(Error::InvalidOptions)?;
+    let value = options::get_string(obj, "value")?.ok_or(Error::InvalidOptions)?;
+    let error_correction = generator::parse_error_correction(obj)?;
+    let gs1 = options::get_bool(obj, "gs1")?.unwrap_or(false);
+
+    let barcode = Barcode {
+        x: get_position(obj, "x")?,
+        y: get_position(obj, "y")?,
+        symbol: generator::encode(format, &value, error_correction.as_deref(), gs1)?,
+        module_width: get_dots(obj, "moduleWidth")?.unwrap_or((dpi / 100).max(1)),
+        height: get_dots(obj, "height")?.unwrap_or_else(|| mm_to_dots(10.0, dpi)),
+    };
+    let human_readable =
+        barcode.symbol.linear && options::get_bool(obj, "humanReadable")?.unwrap_or(true);
+
+    if human_readable {
+        let height = mm_to_dots(2.5, dpi);
+        let text = Text {
+            x: barcode.x,
+            y: barcode.y + barcode.total_height() + height / 4,
+            text: value.chars().filter(|c| *c != GROUP_SEPARATOR).collect(),
+            height,
+            width: None,
+        };
+        elements.push(Element::Barcode(barcode));
+        elements.push(Element::Text(text));
+    } else {
+        elements.push(Element::Barcode(barcode));
+    }
+
+    Ok(())
+}
+
+fn parse_frame(obj: &Object, dpi: u32) -> Result<Frame, Error> {
+    Ok(Frame {
+        x: get_position(obj, "x")?,
+        y: get_position(obj, "y")?,
+        width: get_dots(obj, "width")?.ok_or(Error::InvalidOptions)?,
+        height: get_dots(obj, "height")?.ok_or(Error::InvalidOptions)?,
+        thickness: get_dots(obj, "thickness")?.unwrap_or((dpi / 100).max(1)),
+    })
+}
+
+fn parse_label(label: &JsValue) -> Result<Label, Error> {
+    let obj = options::as_object(label)?;
+    let dpi = get_dots(&obj, "dpi")?.unwrap_or(DEFAULT_DPI);
+    let width = get_dots(&obj, "width")?.ok_or(Error::InvalidOptions)?;
+    let height = get_dots(&obj, "height")?.ok_or(Error::InvalidOptions)?;
+
+    let fields = match options::get(&obj, "fields") {
+        Some(value) => value
+            .dyn_into::<Array>()
+            .map_err(|_| Error::InvalidOptions)?,
+        None => Array::new(),
+    };
+
+    let mut elements = Vec::new();
+    for field in fields.iter() {
+        let field = field
+            .dyn_into::<Object>()
+            .map_err(|_| Error::InvalidOptions)?;
+        match options::get_string(&field, "type")?.as_deref() {
+            Some("text") => elements.push(Element::Text(parse_text(&field, dpi)?)),
+            Some("barcode") => parse_barcode(&field, dpi, &mut elements)?,
+            Some("box") => elements.push(Element::Frame(parse_frame(&field, dpi)?)),
+            _ => return Err(Error::InvalidOptions),
+        }
+    }
+
+    Ok(Label {
+        width,
+        height,
+        dpi,
+        gap: options::get_u32(&obj, "gap")?.unwrap_or(DEFAULT_GAP),
+        elements,
+    })
+}
+
+/// Escapes field data for `^FH`: ZPL command prefixes, the hex indicator and
+/// control characters become `_XX`.
+fn zpl_field_data(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '^' | '~' | '_') || c.is_ascii_control() {
+            let _ = write!(escaped, "_{:02X}", c as u32);
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Encodes a bitmap as a `^GF` graphic field in compressed ASCII hex, where
+/// `,` is an empty row and `:` repeats the previous row.
+fn zpl_graphic(bitmap: &Bitmap) -> String {
+    let (bytes_per_row, packed) = bitmap.pack();
+    let mut data = String::new();
+    let mut previous: Option<&[u8]> = None;
+    for row in packed.chunks(bytes_per_row) {
+        if previous == Some(row) {
+            data.push(':');
+        } else if row.iter().all(|byte| *byte == 0) {
+            data.push(',');
+        } else {
+            for byte in row {
+                let _ = write!(data, "{byte:02X}");
+            }
+        }
+        previous = Some(row);
+    }
+
+    format!(
+        "^GFA,{total},{total},{bytes_per_row},{data}",
+        total = packed.len()
+    )
+}
+
+fn to_zpl(label: &Label, copies: u32) -> String {
+    let mut zpl = String::new();
+    // ^CI28 selects UTF-8 for field data.
+    let _ = write!(zpl, "^XA^CI28^PW{}^LL{}", label.width, label.height);
+
+    for element in &label.elements {
+        match element {
+            Element::Text(text) => {
+                let _ = write!(zpl, "^FO{},{}^A0N,{}", text.x, text.y, text.height);
+                if let Some(width) = text.width {
+                    let _ = write!(zpl, ",{width}");
+                }
+                let _ = write!(zpl, "^FH^FD{}^FS", zpl_field_data(&text.text));
+            }
+            Element::Barcode(barcode) => {
+                if let Some(bitmap) = barcode.to_bitmap(label) {
+                    let graphic = zpl_graphic(&bitmap);
+                    let _ = write!(zpl, "^FO{},{}{graphic}^FS", barcode.x, barcode.y);
+                }
+            }
+            Element::Frame(frame) => {
+                let _ = write!(
+                    zpl,
+                    "^FO{},{}^GB{},{},{}^FS",
+                    frame.x, frame.y, frame.width, frame.height, frame.thickness
+                );
+            }
+        }
+    }
+
+    if copies > 1 {
+        let _ = write!(zpl, "^PQ{copies}");
+    }
+    zpl.push_str("^XZ");
+    zpl
+}
+
+/// Picks the EPL font and multipliers closest to the requested size,
+/// preferring a larger font over a larger magnification.
+fn epl_font(text: &Text, dpi: u32) -> (usize, u32, u32) {
+    let fonts = if dpi >= 300 {
+        &EPL_FONTS_300_DPI
+    } else {
+        &EPL_FONTS_203_DPI
+    };
+
+    let (index, v_mult) = (0..fonts.len())
+        .flat_map(|font| (1..=9).map(move |mult| (font, mult)))
+        .min_by_key(|&(font, mult)| ((fonts[font].1 * mult).abs_diff(text.height), mult))
+        .unwrap_or((0, 1));
+    let h_mult = match text.width {
+        Some(width) => (width as f64 / fonts[index].0 as f64).round() as u32,
+        None => v_mult,
+    };
+
+    (index + 1, h_mult.clamp(1, 6), v_mult)
+}
+
+/// EPL strings are quoted; text outside ASCII is replaced, as the printer's
+/// code page is unknown.
+fn epl_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            ' '..='~' => quoted.push(c),
+            _ => quoted.push('?'),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// EPL output is binary because `GW` graphics are sent as raw bytes.
+fn to_epl(label: &Label, copies: u32) -> Vec<u8> {
+    // The leading newline ends any command left open by a previous job.
+    let mut epl = format!("\nN\nq{}\nQ{},{}\n", label.width, label.height, label.gap).into_bytes();
+
+    for element in &label.elements {
+        match element {
+            Element::Text(text) => {
+                let (font, h_mult, v_mult) = epl_font(text, label.dpi);
+                let line = format!(
+                    "A{},{},0,{font},{h_mult},{v_mult},N,{}\n",
+                    text.x,
+                    text.y,
+                    epl_string(&text.text)
+                );
+                epl.extend_from_slice(line.as_bytes());
+            }
+            Element::Barcode(barcode) => {
+                let Some(bitmap) = barcode.to_bitmap(label) else {
+                    continue;
+                };
+                let (bytes_per_row, packed) = bitmap.pack();
+                let header = format!(
+                    "GW{},{},{bytes_per_row},{},",
+                    barcode.x, barcode.y, bitmap.height
+                );
+                epl.extend_from_slice(header.as_bytes());
+                // EPL graphics print a dot for each cleared bit.
+                epl.extend(packed.iter().map(|byte| !byte));
+                epl.push(b'\n');
+            }
+            Element::Frame(frame) => {
+                let line = format!(
+                    "X{},{},{},{},{}\n",
+                    frame.x,
+                    frame.y,
+                    frame.thickness,
+                    frame.x + frame.width,
+                    frame.y + frame.height
+                );
+                epl.extend_from_slice(line.as_bytes());
+            }
+        }
+    }
+
+    epl.extend_from_slice(format!("P{copies}\n").as_bytes());
+    epl
+}
+
+/// Approximates a printer font by scaling the built-in bitmap font to the
+/// requested character cell.
+fn draw_text(bitmap: &mut Bitmap, text: &Text) {
+    let cell_height = text.height;
+    let cell_width = text.width.unwrap_or(text.height * 3 / 4).max(1);
+
+    for (i, c) in text.text.chars().enumerate() {
+        let x = text.x + i as u32 * cell_width;
+        if x >= bitmap.width {
+            break;
+        }
+        for dy in 0..cell_height.min(bitmap.height.saturating_sub(text.y)) {
+            for dx in 0..cell_width.min(bitmap.width - x) {
+                let col = dx * font::CELL_WIDTH / cell_width;
+                let row = dy * font::CELL_HEIGHT / cell_height;
+                if font::is_set(c, col, row) {
+                    bitmap.fill(x + dx, text.y + dy, 1, 1);
+                }
+            }
+        }
+    }
+}
+
+fn draw_frame(bitmap: &mut Bitmap, frame: &Frame) {
+    let thickness = frame.thickness.min(frame.width).min(frame.height);
+    let (x, y) = (frame.x, frame.y);
+    bitmap.fill(x, y, frame.width, thickness);
+    bitmap.fill(x, y + frame.height - thickness, frame.width, thickness);
+    bitmap.fill(x, y, thickness, frame.height);
+    bitmap.fill(x + frame.width - thickness, y, thickness, frame.height);
+}
+
+fn to_png(label: &Label) -> Result<Vec<u8>, Error> {
+    let mut bitmap = Bitmap::new(label.width, label.height);
+    for element in &label.elements {
+        match element {
+            Element::Text(text) => draw_text(&mut bitmap, text),
+            Element::Barcode(barcode) => barcode.draw(&mut bitmap, barcode.x, barcode.y),
+            Element::Frame(frame) => draw_frame(&mut bitmap, frame),
+        }
+    }
+
+    let image = GrayImage::from_fn(label.width, label.height, |x, y| {
+        let dark = bitmap.dots[(y * label.width + x) as usize];
+        Luma([if dark { 0 } else { 255 }])
+    });
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|_| Error::Internal)?;
+    Ok(png)
+}
+
+pub fn render_label(label: &JsValue, options: &JsValue) -> Result<JsValue, Error> {
+    let label = parse_label(label)?;
+    let obj = options::as_object(options)?;
+
+    let output = match options::get_string(&obj, "output")?.as_deref() {
+        Some("zpl") | None => Output::Zpl,
+        Some("epl") => Output::Epl,
+        Some("png") => Output::Png,
+        Some(_) => return Err(Error::InvalidOptions),
+    };
+    let copies = options::get_u32(&obj, "copies")?.unwrap_or(1).max(1);
+
+    match output {
+        Output::Zpl => Ok(JsValue::from_str(&to_zpl(&label, copies))),
+        Output::Epl => Ok(Uint8Array::from(to_epl(&label, copies).as_slice()).into()),
+        Output::Png => Ok(Uint8Array::from(to_png(&label)?.as_slice()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rxing::BarcodeFormat;
+
+    use super::*;
+
+    /// A 3 by 2 module checkerboard.
+    fn checkerboard() -> Symbol {
+        Symbol {
+            format: BarcodeFormat::QR_CODE,
+            modules: vec![true, false, true, false, true, false],
+            cols: 3,
+            rows: 2,
+            linear: false,
+        }
+    }
+
+    fn label(elements: Vec<Element>) -> Label {
+        Label {
+            width: 100,
+            height: 100,
+            dpi: DEFAULT_DPI,
+            gap: DEFAULT_GAP,
+            elements,
+        }
+    }
+
+    fn barcode(x: u32, y: u32, symbol: Symbol) -> Element {
+        Element::Barcode(Barcode {
+            x,
+            y,
+            symbol,
+            module_width: 4,
+            height: 30,
+        })
+    }
+
+    /// Expands a compressed `^GFA` field, checking its declared sizes.
+    fn expand_graphic(zpl: &str) -> (usize, Vec<Vec<u8>>) {
+        let start = zpl.find("^GFA,").unwrap() + 5;
+        let end = start + zpl[start..].find("^FS").unwrap();
+        let mut fields = zpl[start..end].splitn(4, ',');
+        let mut number = || fields.next().unwrap().parse::<usize>().unwrap();
+        let (total, _, bytes_per_row) = (number(), number(), number());
+        let data = fields.next().unwrap();
+
+        let mut rows: Vec<Vec<u8>> = Vec::new();
+        let mut hex = String::new();
+        for c in data.chars() {
+            match c {
+                ',' => rows.push(vec![0; bytes_per_row]),
+                ':' => rows.push(rows.last().unwrap().clone()),
+                _ => {
+                    hex.push(c);
+                    if hex.len() == 2 * bytes_per_row {
+                        rows.push(
+                            (0..bytes_per_row)
+                                .map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+                                .collect(),
+                        );
+                        hex.clear();
+                    }
+                }
+            }
+        }
+        assert!(hex.is_empty());
+        assert_eq!(rows.len() * bytes_per_row, total);
+        (bytes_per_row, rows)
+    }
+
+    #[test]
+    fn zpl_matches_known_output() {
+        let label = label(vec![
+            Element::Text(Text {
+                x: 1,
+                y: 2,
+                text: "A^B_c".into(),
+                height: 20,
+                width: None,
+            }),
+            barcode(10, 20, checkerboard()),
+            Element::Frame(Frame {
+                x: 5,
+                y: 5,
+                width: 50,
+                height: 40,
+                thickness: 3,
+            }),
+        ]);
+
+        assert_eq!(
+            to_zpl(&label, 2),
+            "^XA^CI28^PW100^LL100\
+             ^FO1,2^A0N,20^FH^FDA_5EB_5Fc^FS\
+             ^FO10,20^GFA,16,16,2,F0F0:::0F00:::^FS\
+             ^FO5,5^GB50,40,3^FS\
+             ^PQ2^XZ"
+        );
+    }
+
+    #[test]
+    fn zpl_graphic_compresses_blank_and_repeated_rows() {
+        let symbol = Symbol {
+            format: BarcodeFormat::QR_CODE,
+            modules: vec![false, false, true, true, true, false],
+            cols: 2,
+            rows: 3,
+            linear: false,
+        };
+        let bitmap = Barcode {
+            x: 0,
+            y: 0,
+            symbol,
+            module_width: 1,
+            height: 1,
+        }
+        .to_bitmap(&label(Vec::new()))
+        .unwrap();
+
+        assert_eq!(zpl_graphic(&bitmap), "^GFA,3,3,1,,C080");
+    }
+
+    #[test]
+    fn zpl_graphic_stride_covers_every_row_of_a_linear_barcode() {
+        let symbol = generator::encode(BarcodeFormat::CODE_128, "MRN0012345", None, false).unwrap();
+        let cols = symbol.cols;
+        let label = Label {
+            width: 812,
+            height: 203,
+            ..label(vec![barcode(20, 10, symbol)])
+        };
+
+        let (bytes_per_row, rows) = expand_graphic(&to_zpl(&label, 1));
+        assert_eq!(bytes_per_row, (cols * 4).div_ceil(8) as usize);
+        assert_eq!(rows.len(), 30);
+        assert!(rows.windows(2).all(|pair| pair[0] == pair[1]));
+        // Code 128 starts with a two-module bar: eight dots.
+        assert_eq!(rows[0][0], 0xff);
+    }
+
+    #[test]
+    fn barcodes_are_clipped_to_the_label() {
+        let label = Label {
+            width: 16,
+            height: 6,
+            ..label(vec![barcode(10, 0, checkerboard())])
+        };
+
+        let (bytes_per_row, rows) = expand_graphic(&to_zpl(&label, 1));
+        assert_eq!(bytes_per_row, 1);
+        assert_eq!(rows, [[0xf0], [0xf0], [0xf0], [0xf0], [0x0c], [0x0c]]);
+    }
+
+    #[test]
+    fn epl_matches_known_output_with_inverted_graphics() {
+        let label = label(vec![
+            Element::Text(Text {
+                x: 1,
+                y: 2,
+                text: "Dose \"5\" µg".into(),
+                height: 20,
+                width: None,
+            }),
+            barcode(10, 20, checkerboard()),
+            Element::Frame(Frame {
+                x: 5,
+                y: 5,
+                width: 50,
+                height: 40,
+                thickness: 3,
+            }),
+        ]);
+
+        let mut expected =
+            b"\nN\nq100\nQ100,24\nA1,2,0,3,1,1,N,\"Dose \\\"5\\\" ?g\"\nGW10,20,2,8,".to_vec();
+        for _ in 0..4 {
+            expected.extend_from_slice(&[0x0f, 0x0f]);
+        }
+        for _ in 0..4 {
+            expected.extend_from_slice(&[0xf0, 0xff]);
+        }
+        expected.extend_from_slice(b"\nX5,5,3,55,45\nP1\n");
+
+        assert_eq!(to_epl(&label, 1), expected);
+    }
+
+    #[test]
+    fn epl_font_picks_the_closest_size() {
+        let text = |height, width| Text {
+            x: 0,
+            y: 0,
+            text: String::new(),
+            height,
+            width,
+        };
+        assert_eq!(epl_font(&text(24, None), 203), (4, 1, 1));
+        assert_eq!(epl_font(&text(40, None), 203), (3, 2, 2));
+        assert_eq!(epl_font(&text(96, Some(32)), 203), (5, 1, 2));
+        assert_eq!(epl_font(&text(44, None), 300), (4, 1, 1));
+    }
+
+    #[test]
+    fn png_frames_are_outlines() {
+        let mut bitmap = Bitmap::new(10, 10);
+        draw_frame(
+            &mut bitmap,
+            &Frame {
+                x: 1,
+                y: 1,
+                width: 8,
+                height: 8,
+                thickness: 2,
+            },
+        );
+        assert!(bitmap.dots[11] && bitmap.dots[22]);
+        assert!(!bitmap.dots[33] && !bitmap.dots[0]);
+        assert_eq!(bitmap.dots.iter().filter(|dark| **dark).count(), 64 - 16);
+    }
+}
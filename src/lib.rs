@@ -1,13 +1,46 @@
+mod backend;
+mod batch;
+mod cbor;
+mod composite;
+mod content;
+mod crypto;
+mod databar;
 mod detector;
 mod error;
 mod event;
+mod feedback;
+mod font;
+mod generator;
+mod grading;
+mod gs1;
+mod hcert;
+mod label;
+mod ndc;
+mod options;
+mod overlay;
+mod pdf;
+mod pharmacode;
 mod reader;
 mod scanner;
+mod shc;
+mod stats;
+mod structured_append;
+mod validator;
+mod wedge;
 
-use wasm_bindgen::{JsValue, prelude::wasm_bindgen};
+use js_sys::{Object, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue, prelude::wasm_bindgen};
+
+use crate::error::Error;
+use web_sys::{
+    Blob, Element, EventTarget, HtmlCanvasElement, HtmlImageElement, HtmlInputElement, ImageData,
+};
 
 // Re-export event callbacks for documentation
-pub use event::{on_detect, on_start, on_stop};
+pub use event::{
+    on_batch_complete, on_detect, on_drop_hover, on_start, on_stats, on_stop,
+    on_structured_append_progress,
+};
 
 /// WASM entry point
 #[wasm_bindgen(start)]
@@ -33,6 +66,145 @@ pub fn read_from_image() -> Result<(), JsValue> {
     reader::read_from_image()
 }
 
+/// Configures batch scanning of multiple files.
+///
+/// When enabled, the file dialog opened by `read_from_image` accepts multiple
+/// files, and every file selected or dropped is scanned. Each image produces
+/// its own `on_detect` call tagged with `fileName` and `index`, and each
+/// scanned PDF page one additionally tagged with `page`, followed by a single
+/// `on_batch_complete` summary and `on_stop`.
+///
+/// ## Arguments
+/// * `options` - An object with the following optional fields:
+///   - `enabled: boolean` - turn batch mode on or off (default `true`)
+///   - `concurrency: number` - number of files read at once, 1 to 8 (default `2`)
+#[wasm_bindgen]
+pub fn set_batch_options(options: JsValue) -> Result<(), JsValue> {
+    batch::set_batch_options(&options).map_err(JsValue::from)
+}
+
+/// Scans images selected through an app-provided `<input type="file">`.
+#[wasm_bindgen]
+pub fn bind_file_input(input: HtmlInputElement) -> Result<(), JsValue> {
+    reader::bind_file_input(&input)
+}
+
+/// Scans images dropped onto `element`.
+///
+/// Drag hover state is reported through `on_drop_hover`.
+#[wasm_bindgen]
+pub fn bind_drop_zone(element: Element) -> Result<(), JsValue> {
+    reader::bind_drop_zone(&element)
+}
+
+/// Scans images pasted from the clipboard into `target`.
+///
+/// ## Arguments
+/// * `target` - The element to listen on; defaults to the document when omitted
+#[wasm_bindgen]
+pub fn enable_paste_scan(target: Option<EventTarget>) -> Result<(), JsValue> {
+    let target = match target {
+        Some(target) => target,
+        None => web_sys::window()
+            .ok_or(Error::WindowNotFound)?
+            .document()
+            .ok_or(Error::DocumentNotFound)?
+            .into(),
+    };
+
+    reader::enable_paste_scan(&target)
+}
+
+/// Scans an encoded image (PNG, JPEG, GIF, WebP, ...) from raw bytes.
+///
+/// Results are delivered through `on_start`, `on_detect` and `on_stop`.
+#[wasm_bindgen]
+pub fn scan_bytes(bytes: Uint8Array) {
+    reader::scan_bytes(&bytes);
+}
+
+/// Scans an encoded image from a `Blob` or `File`.
+///
+/// Results are delivered through `on_start`, `on_detect` and `on_stop`.
+#[wasm_bindgen]
+pub fn scan_blob(blob: Blob) {
+    reader::scan_blob(&blob);
+}
+
+/// Scans the pixels of an `ImageData`.
+///
+/// Results are delivered through `on_start`, `on_detect` and `on_stop`.
+#[wasm_bindgen]
+pub fn scan_image_data(image_data: ImageData) {
+    reader::scan_image_data(&image_data);
+}
+
+/// Scans the current contents of a canvas element.
+///
+/// Results are delivered through `on_start`, `on_detect` and `on_stop`.
+#[wasm_bindgen]
+pub fn scan_canvas(canvas: HtmlCanvasElement) {
+    reader::scan_canvas(&canvas);
+}
+
+/// Scans a loaded `<img>` element at its natural size.
+///
+/// Cross-origin images must be served with CORS headers, otherwise the
+/// browser refuses to expose their pixels.
+///
+/// Results are delivered through `on_start`, `on_detect` and `on_stop`.
+#[wasm_bindgen]
+pub fn scan_image_element(img: HtmlImageElement) {
+    reader::scan_image_element(&img);
+}
+
+/// Scans the images embedded in a PDF document (e.g. scanned forms).
+///
+/// Each page in the range configured with `set_pdf_options` produces its own
+/// `on_detect` call tagged with its 1-based `page` number, followed by `on_stop`.
+/// PDFs selected through the file dialog, bound inputs or drop zones are scanned
+/// the same way.
+#[wasm_bindgen]
+pub fn scan_pdf(bytes: Uint8Array) {
+    reader::scan_pdf(&bytes);
+}
+
+/// Configures which pages of a PDF document are scanned.
+///
+/// ## Arguments
+/// * `options` - An object with the following optional fields:
+///   - `firstPage: number` - first page to scan, 1-based (default `1`)
+///   - `lastPage: number` - last page to scan (default: the last page)
+#[wasm_bindgen]
+pub fn set_pdf_options(options: JsValue) -> Result<(), JsValue> {
+    pdf::set_pdf_options(&options).map_err(JsValue::from)
+}
+
+/// Selects the detection backend used by stream scanning.
+///
+/// Browsers implementing the Shape Detection API (e.g. Chrome on Android)
+/// provide a hardware-accelerated `BarcodeDetector`. It is used for QR Code
+/// and for UPC-A unless `requireAddOn` is set, since native results carry no
+/// add-on. While structured append reassembly is on, a native QR Code hit is
+/// decoded again with rxing to read its sequence header. UPC-E, GS1 DataBar
+/// and Pharmacode are always decoded with rxing, which also runs on frames
+/// where the native detector finds nothing. Native results outside the stream
+/// region are ignored, and results are validated and reported in the same
+/// shape as rxing results, with an added `backend: "native" | "rxing"` field.
+///
+/// ## Arguments
+/// * `options` - An object with the following optional fields:
+///   - `policy: string` - `"native-first"` (default) uses `BarcodeDetector` when it supports
+///     one of those formats and rxing for the rest, `"rxing-only"` never uses it, and
+///     `"both-and-compare"` runs both on every frame, preferring the rxing result and
+///     adding `backendsAgree: boolean`
+///   - `formats: string[]` - restricts the formats requested from `BarcodeDetector`
+///     (default: every eligible scan format)
+#[wasm_bindgen]
+pub fn set_detection_backend(options: JsValue) -> Result<(), JsValue> {
+    backend::set_detection_backend(&options).map_err(JsValue::from)
+}
+
 /// Starts the stream-based barcode scanning from the camera.
 ///
 /// ## Arguments
@@ -47,3 +219,375 @@ pub fn start_stream_scan(video_element_id: &str) -> Result<(), JsValue> {
 pub fn stop_stream_scan() {
     scanner::stop_stream_scan();
 }
+
+/// Configures audio and vibration feedback for scan results.
+///
+/// A beep synthesized with Web Audio and a `navigator.vibrate` pattern are
+/// played when a camera or single-image scan reports a barcode, and a distinct
+/// cue when a value is rejected by the filter. Call this from a user gesture
+/// (e.g. a click handler) so browsers allow audio playback.
+///
+/// ## Arguments
+/// * `options` - An object with the following optional fields:
+///   - `enabled: boolean` - turn feedback on or off (default `true`)
+///   - `sound: boolean` / `vibrate: boolean` - toggle audio / vibration (default `true`)
+///   - `success`, `rejected: { frequency, duration, volume, type } | false` - the tone
+///     played for each outcome, in Hz, milliseconds, 0 to 1 and an oscillator type
+///   - `successVibration`, `rejectedVibration: number | number[] | false` - vibration patterns in milliseconds
+#[wasm_bindgen]
+pub fn set_feedback_options(options: JsValue) -> Result<(), JsValue> {
+    feedback::set_feedback_options(&options).map_err(JsValue::from)
+}
+
+/// Configures the overlay drawn over the camera video while stream scanning.
+///
+/// The overlay shows the region searched for barcodes, an animated scanning
+/// line and the result points of decoded barcodes, mapped to the video's
+/// on-screen position including its CSS `object-fit`. Unless `canvas` is given,
+/// a canvas is inserted after the video element and absolutely positioned over
+/// it, so the video's parent should be a positioned element.
+///
+/// ## Arguments
+/// * `options` - An object with the following optional fields:
+///   - `enabled: boolean` - turn the overlay on or off (default `true`)
+///   - `canvas: HTMLCanvasElement` - draw on this canvas instead of creating one
+///   - `viewfinder: boolean` / `scanLine: boolean` - draw the viewfinder corners / scanning line (default `true`)
+///   - `viewfinderColor`, `scanLineColor`, `pointColor: string` - CSS colors
+///   - `maskColor: string | false` - color dimming the area outside the viewfinder, `false` to disable
+///   - `lineWidth: number` - stroke width in CSS pixels (default `3`)
+///   - `highlightMs: number` - how long result points stay visible (default `600`)
+#[wasm_bindgen]
+pub fn set_overlay_options(options: JsValue) -> Result<(), JsValue> {
+    overlay::set_overlay_options(&options).map_err(JsValue::from)
+}
+
+/// Returns statistics for the current or most recent camera scan session,
+/// or `null` if no session has been started.
+///
+/// The object has the following fields:
+/// - `framesCaptured: number` - frames grabbed from the video stream
+/// - `framesDecoded: number` - frames in which a barcode was decoded
+/// - `detections: number` - confirmed results reported through `on_detect`
+/// - `durationMs: number` - session length so far
+/// - `effectiveFps: number` - frames captured per second
+/// - `timeToFirstDetectionMs: number | null` - time from start to the first confirmed result
+/// - `decodeTimeMs: { p50, p90, p99, max }` - decode time percentiles over recent frames
+/// - `resolution: { width, height }` - capture resolution of the last frame
+#[wasm_bindgen]
+pub fn get_scan_stats() -> JsValue {
+    stats::get_scan_stats()
+}
+
+/// Captures barcodes typed by USB/Bluetooth handheld scanners in keyboard
+/// (HID wedge) mode.
+///
+/// Keystrokes that arrive faster than `maxInterKeyMs` apart and end with the
+/// terminator key are treated as a scan; slower typing is left alone. Each scan
+/// is reported through `on_detect` like a camera result, tagged with
+/// `source: "keyboard"`. AIM symbology identifiers (e.g. `]C1`) are used to
+/// determine the format when the scanner sends them.
+///
+/// ## Arguments
+/// * `options` - An object with the following optional fields:
+///   - `target: EventTarget` - where keystrokes are captured (default: the document)
+///   - `maxInterKeyMs: number` - longest gap between scanner keystrokes (default `35`)
+///   - `minLength: number` - shortest accepted value (default `4`)
+///   - `prefix: string` / `suffix: string` - characters the scanner adds around each value
+///   - `terminator: string` - key that ends a scan, as a `KeyboardEvent.key` (default `"Enter"`)
+///   - `fnc1: string` - character the scanner sends for GS1 FNC1 / group separators
+///   - `aimIds: boolean` - strip and interpret AIM symbology identifiers (default `true`)
+///   - `preventDefault: boolean` - keep scanner keystrokes out of focused inputs (default `false`);
+///     keys typed into a field are held back for `maxInterKeyMs` and re-typed
+///     if no scan follows, which IME composition cannot survive
+#[wasm_bindgen]
+pub fn start_keyboard_wedge_capture(options: JsValue) -> Result<(), JsValue> {
+    let target = match options::as_object(&options)
+        .ok()
+        .and_then(|obj| options::get(&obj, "target"))
+    {
+        Some(target) => target
+            .dyn_into::<EventTarget>()
+            .map_err(|_| JsValue::from(Error::InvalidOptions))?,
+        None => web_sys::window()
+            .ok_or(Error::WindowNotFound)?
+            .document()
+            .ok_or(Error::DocumentNotFound)?
+            .into(),
+    };
+
+    wedge::start_keyboard_wedge_capture(&target, &options)
+}
+
+/// Stops capturing keyboard-wedge scanner input.
+#[wasm_bindgen]
+pub fn stop_keyboard_wedge_capture() {
+    wedge::stop_keyboard_wedge_capture();
+}
+
+/// Encodes `value` as a barcode image.
+///
+/// ## Arguments
+/// * `format` - The symbology, e.g. `"qr_code"`, `"code_128"`, `"data_matrix"`, `"ean_13"`, `"pdf_417"`
+/// * `value` - The text to encode; GS1 field separators are written as ASCII 29 (GS)
+/// * `options` - An object with the following optional fields:
+///   - `output: "svg" | "png" | "canvas"` - returns an SVG string, PNG bytes as a `Uint8Array`,
+///     or a canvas (default `"svg"`, or `"canvas"` when `canvas` is given)
+///   - `canvas: HTMLCanvasElement` - canvas to draw on; it is resized to fit the symbol
+///   - `moduleSize: number` - size of one module in pixels, 1 to 100 (default `4`)
+///   - `quietZone: number` - margin in modules, up to 50 (default: the symbology's minimum, e.g. 4 for QR, 10 for linear codes)
+///   - `height: number` - bar height in pixels for linear codes, up to 4096 (default 50 modules)
+///   - `errorCorrection: string | number` - e.g. `"L"`, `"M"`, `"Q"`, `"H"` for QR codes
+///   - `gs1: boolean` - encode as GS1 data using FNC1 (Code 128, QR Code, Data Matrix)
+///
+/// Options that would make the image wider or taller than 8192 pixels are
+/// rejected with `ERR_INVALID_OPTIONS`.
+#[wasm_bindgen]
+pub fn generate(format: &str, value: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    generator::generate(format, value, &options).map_err(JsValue::from)
+}
+
+/// Renders a label layout as ZPL II, EPL2 or a PNG preview.
+///
+/// Barcodes are encoded by rxing and sent as graphics (`^GF` / `GW`) at
+/// whole-dot module widths, so any supported symbology prints on any printer
+/// and matches the preview dot for dot. Text uses the printer's fonts and is
+/// approximated in the preview.
+///
+/// ## Arguments
+/// * `label` - An object with positions and sizes in printer dots:
+///   - `width: number`, `height: number` - label size
+///   - `dpi: number` - printer resolution, used for default sizes and EPL fonts (default `203`)
+///   - `gap: number` - gap between labels for EPL, `0` for continuous media (default `24`)
+///   - `fields: object[]` - `{ type: "text", x, y, text, height?, width? }`,
+///     `{ type: "barcode", x, y, format, value, moduleWidth?, height?, humanReadable?, errorCorrection?, gs1? }`
+///     or `{ type: "box", x, y, width, height, thickness? }`
+/// * `options` - An object with the following optional fields:
+///   - `output: "zpl" | "epl" | "png"` - a ZPL string, EPL bytes or PNG bytes (default `"zpl"`)
+///   - `copies: number` - number of labels to print (default `1`)
+#[wasm_bindgen]
+pub fn render_label(label: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    label::render_label(&label, &options).map_err(JsValue::from)
+}
+
+/// Estimates the print quality of the barcode in an encoded image (PNG, JPEG, ...).
+///
+/// Parameters follow ISO/IEC 15416 for linear symbols, averaged over ten scan
+/// lines across the bar height, and ISO/IEC 15415 for QR Code and Data Matrix.
+/// Reflectance is taken from the image's gray levels rather than a calibrated
+/// reader, so grades are an estimate useful for comparing prints and catching
+/// defects, not a verification report.
+///
+/// Returns `{ value, format, grade, gradeValue, parameters, scans? }`, where
+/// `grade` is `"A"` to `"F"`, `gradeValue` is 4.0 to 0.0 and each entry of
+/// `parameters` is `{ value?, grade }`.
+///
+/// ## Arguments
+/// * `bytes` - The encoded image
+#[wasm_bindgen]
+pub fn grade_bytes(bytes: Uint8Array) -> Result<JsValue, JsValue> {
+    grading::grade_bytes(&bytes.to_vec()).map_err(JsValue::from)
+}
+
+/// Estimates the print quality of the barcode in the pixels of an `ImageData`.
+///
+/// See `grade_bytes` for the result.
+///
+/// ## Arguments
+/// * `image_data` - The pixels to grade, e.g. from `CanvasRenderingContext2D.getImageData`
+#[wasm_bindgen]
+pub fn grade_image_data(image_data: ImageData) -> Result<JsValue, JsValue> {
+    grading::grade_image_data(&image_data).map_err(JsValue::from)
+}
+
+/// Configures reassembly of QR Code structured append sequences.
+///
+/// Symbols carrying a structured append header are buffered across frames,
+/// images and files until every symbol of the sequence has been read; the
+/// concatenated message is then checked against the sequence's parity byte
+/// and reported through `on_detect`. Progress is reported through
+/// `on_structured_append_progress`, and a still image holding an incomplete
+/// part yields `ERR_SEQUENCE_INCOMPLETE`.
+///
+/// ## Arguments
+/// * `options` - An object with the following optional fields:
+///   - `enabled: boolean` - reassemble sequences; when `false`, each symbol is reported on its
+///     own, tagged with `structuredAppend` (default `true`)
+///   - `timeoutMs: number` - discard a partial sequence when no new symbol has been read for
+///     this long, `0` to keep it until it is complete (default `0`)
+#[wasm_bindgen]
+pub fn set_structured_append_options(options: JsValue) -> Result<(), JsValue> {
+    structured_append::set_structured_append_options(&options).map_err(JsValue::from)
+}
+
+/// Discards every partially read structured append sequence.
+#[wasm_bindgen]
+pub fn clear_structured_append() {
+    structured_append::clear_structured_append();
+}
+
+/// Decodes an EU Digital COVID Certificate style health certificate.
+///
+/// The `HC1:` payload is Base45-decoded, inflated and parsed as a COSE_Sign1
+/// message whose CWT claims carry the certificate. Everything runs offline;
+/// the signature is only checked against the keys in `trustList`.
+///
+/// Returns `{ kid, algorithm, issuer, issuedAt, expiresAt, expired,
+/// certificate, claims, verification }`, where `issuedAt` and `expiresAt` are
+/// Unix timestamps in seconds, byte strings are base64-encoded and
+/// `verification` is `"valid"`, `"invalid"`, `"unknown_key"`,
+/// `"unsupported_algorithm"` or `"unchecked"` when no trust list is given.
+///
+/// ## Arguments
+/// * `value` - The scanned `HC1:` string
+/// * `options` - An object with the following optional fields:
+///   - `trustList: { kid?: string, publicKey: string }[]` - trusted ES256 (P-256) or PS256
+///     (RSA) signing keys, each a PEM or base64 DER public key or X.509 certificate; `kid` is
+///     the base64 key identifier, derived from the certificate's SHA-256 hash when omitted
+#[wasm_bindgen]
+pub fn decode_health_certificate(value: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    hcert::decode_health_certificate(value, &options).map_err(JsValue::from)
+}
+
+/// Decodes a SMART Health Card and verifies its signature against a key set
+/// supplied by the application.
+///
+/// The `shc:/` numeric encoding is decoded, the chunks of a multi-QR card are
+/// joined in order, and the ES256-signed JWS payload is inflated and parsed.
+/// No keys are fetched; the signature is only checked against `jwks`.
+///
+/// Returns `{ issuer, kid, algorithm, issuedAt, expiresAt, expired, types,
+/// bundle, verification }`, where `bundle` is the FHIR bundle, `issuedAt` and
+/// `expiresAt` are Unix timestamps in seconds and `verification` is
+/// `"valid"`, `"invalid"`, `"unknown_key"`, `"unsupported_algorithm"` or
+/// `"unchecked"` when no key set is given.
+///
+/// ## Arguments
+/// * `value` - The scanned `shc:/` string, or an array with every chunk of a multi-QR card
+///   in any order
+/// * `options` - An object with the following optional fields:
+///   - `jwks: { keys: object[] } | object[]` - the issuers' JSON Web Key Sets; EC P-256 keys
+///     are matched to the card by `kid`
+#[wasm_bindgen]
+pub fn decode_smart_health_card(value: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    shc::decode_smart_health_card(&value, &options).map_err(JsValue::from)
+}
+
+/// Extracts the National Drug Code from a UPC-A, EAN-13, GTIN-14 or GS1
+/// element string value, e.g. a GS1 DataMatrix on a drug package.
+///
+/// The 10-digit NDC inside a `03` GTIN is normalized to the 11-digit 5-4-2
+/// form in each of the 4-4-2, 5-3-2 and 5-4-1 configurations. The value alone
+/// cannot tell them apart; the configuration found in the product list set
+/// with `set_ndc_products` is the resolved one.
+///
+/// Returns `{ gtin, ndc10, ndc, ndc11, configuration, product, candidates }`,
+/// or `null` if the value carries no NDC. `ndc` is hyphenated, and `ndc`,
+/// `ndc11`, `configuration` and `product` are `null` unless exactly one
+/// candidate is in the product list. Each entry of `candidates` is
+/// `{ ndc, ndc11, configuration, product }`.
+///
+/// ## Arguments
+/// * `value` - A decoded barcode value
+#[wasm_bindgen]
+pub fn extract_ndc(value: &str) -> JsValue {
+    ndc::extract_ndc(value)
+}
+
+/// Sets the product list used to resolve NDC configurations.
+///
+/// ## Arguments
+/// * `products` - An array of hyphenated or 11-digit NDCs, or of objects with an `ndc` field
+///   that are returned as `product` when matched. Pass `null` to clear the list.
+#[wasm_bindgen]
+pub fn set_ndc_products(products: JsValue) -> Result<(), JsValue> {
+    ndc::set_ndc_products(&products).map_err(JsValue::from)
+}
+
+/// Sets the formats rxing and wascan's own readers try on every image and
+/// frame, in order.
+///
+/// ## Arguments
+/// * `formats` - Format names, from `"upc_a"`, `"upc_e"`, `"qr_code"`, `"rss_14"`,
+///   `"rss_expanded"` and `"pharmacode"` (default `["upc_a", "qr_code"]`). "rss_14"
+///   merges a CC-A/CC-B composite component above the symbol into its element string.
+///   GS1 DataBar Limited is not decoded.
+#[wasm_bindgen]
+pub fn set_scan_formats(formats: JsValue) -> Result<(), JsValue> {
+    detector::set_scan_formats(&formats).map_err(JsValue::from)
+}
+
+/// Configures the UPC-A and UPC-E readers. EAN-2 and EAN-5 add-ons are always
+/// read when present and reported as `addOn`.
+///
+/// ## Arguments
+/// * `options` - An object with the following optional fields:
+///   - `requireAddOn: boolean` - only accept symbols followed by an add-on (default `false`)
+#[wasm_bindgen]
+pub fn set_upc_options(options: JsValue) -> Result<(), JsValue> {
+    detector::set_upc_options(&options).map_err(JsValue::from)
+}
+
+/// Configures the one-track Pharmacode reader enabled with `set_scan_formats`.
+///
+/// ## Arguments
+/// * `options` - An object with the following optional fields:
+///   - `direction: "forward" | "reverse"` - `"forward"` reads the rightmost bar as the least
+///     significant, as printed; `"reverse"` reads codes seen upside down (default `"forward"`)
+///   - `minBars: number` - fewest bars accepted, 2 to 16 (default `2`); values below 15
+///     need fewer than 4 bars
+#[wasm_bindgen]
+pub fn set_pharmacode_options(options: JsValue) -> Result<(), JsValue> {
+    pharmacode::set_pharmacode_options(&options).map_err(JsValue::from)
+}
+
+/// Configures validation applied to every decoded value before it is emitted.
+///
+/// ## Arguments
+/// * `options` - An object with the following optional fields:
+///   - `checkDigit: boolean` - verify UPC/EAN/ITF-14 check digits (default `true`)
+///   - `normalize: "upc_a" | "ean_13" | "gtin_14"` - emit UPC-E values expanded to UPC-A,
+///     UPC/EAN-8 values padded to EAN-13, or UPC/EAN/ITF-14 values as 14-digit GTINs
+///   - `minLength: number` / `maxLength: number` - accepted value length range
+///   - `pattern: string | RegExp` - pattern the value must match
+#[wasm_bindgen]
+pub fn set_validation_options(options: JsValue) -> Result<(), JsValue> {
+    validator::set_validation_options(&options).map_err(JsValue::from)
+}
+
+/// Registers a filter that decides which decoded values are accepted.
+///
+/// Rejected values never reach `on_detect` and, in stream mode, do not count
+/// toward the consecutive detections required to confirm a result.
+///
+/// ## Arguments
+/// * `filter` - Either a predicate `({ value, format }) => boolean`, or an object with
+///   the optional fields `pattern: string | RegExp`, `prefix: string` and
+///   `formats: string[]`. Pass `null` to remove the filter.
+#[wasm_bindgen]
+pub fn set_filter(filter: JsValue) -> Result<(), JsValue> {
+    validator::set_filter(&filter).map_err(JsValue::from)
+}
+
+/// Enables or disables content classification of detected values.
+///
+/// When enabled, successful `on_detect` results carry `contentType` and `content`
+/// for recognized payloads (URLs, Wi-Fi configs, contacts, geo locations, email,
+/// phone and SMS links, EMV payment codes and SMART Health Cards).
+#[wasm_bindgen]
+pub fn set_content_parsing(enabled: bool) {
+    content::set_content_parsing(enabled);
+}
+
+/// Classifies a value and returns `{ contentType, content }`, or `null` if the
+/// value is not a recognized content type.
+#[wasm_bindgen]
+pub fn parse_content(value: &str) -> JsValue {
+    match content::classify(value) {
+        Some(parsed) => {
+            let obj = Object::new();
+            parsed.attach_to(&obj);
+            obj.into()
+        }
+        None => JsValue::NULL,
+    }
+}
@@ -0,0 +1,311 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::{
+    error::Error,
+    gs1::{self, GS},
+    options,
+    validator::has_valid_check_digit,
+};
+
+/// GS1 prefix under which US drug GTINs carry the NDC.
+const NDC_GTIN_PREFIX: &str = "03";
+const NDC_10_LENGTH: usize = 10;
+const NDC_11_LENGTH: usize = 11;
+
+/// Labeler, product and package segment lengths of the 10-digit NDC
+/// configurations.
+const CONFIGURATIONS: [(&str, [usize; 3]); 3] = [
+    ("4-4-2", [4, 4, 2]),
+    ("5-3-2", [5, 3, 2]),
+    ("5-4-1", [5, 4, 1]),
+];
+
+thread_local! {
+    /// Known products keyed by their 11-digit NDC, with the entry the
+    /// application supplied.
+    static PRODUCTS: RefCell<HashMap<String, JsValue>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the GTIN in AI (01) of a GS1 element string, given either with
+/// FNC1 separators or in the human-readable `(01)...` form.
+fn gs1_gtin(value: &str) -> Option<&str> {
+    if value.starts_with('(') {
+        return value.split('(').skip(1).find_map(|element| {
+            let (ai, data) = element.split_once(')')?;
+            (ai == "01").then_some(data)
+        });
+    }
+
+    let mut rest = value.trim_start_matches(GS);
+    while rest.len() >= 2 {
+        let prefix = rest.get(..2)?;
+        let len = gs1::predefined_length(prefix);
+        if prefix == "01" {
+            return rest.get(2..16);
+        }
+        rest = match len {
+            Some(len) => rest.get(len..)?,
+            None => rest.split_once(GS)?.1,
+        };
+        rest = rest.trim_start_matches(GS);
+    }
+    None
+}
+
+/// Returns the GTIN-14 carried by a UPC-A, EAN-13 or GTIN-14 value or by a
+/// GS1 element string.
+fn gtin14(value: &str) -> Option<String> {
+    let value = value.trim();
+    // Longer digit strings are element strings whose AIs are all fixed-length.
+    let digits = if value.len() <= 14 && value.bytes().all(|b| b.is_ascii_digit()) {
+        value
+    } else {
+        gs1_gtin(value)?
+    };
+
+    (matches!(digits.len(), 12..=14)
+        && digits.bytes().all(|b| b.is_ascii_digit())
+        && has_valid_check_digit(digits))
+    .then(|| format!("{digits:0>14}"))
+}
+
+/// Splits `digits` into segments of the given lengths.
+fn segments<'a>(digits: &'a str, lengths: &[usize; 3]) -> [&'a str; 3] {
+    let (labeler, rest) = digits.split_at(lengths[0]);
+    let (product, package) = rest.split_at(lengths[1]);
+    [labeler, product, package]
+}
+
+/// Pads the segments of an NDC to the 11-digit 5-4-2 form.
+fn to_ndc11([labeler, product, package]: [&str; 3]) -> String {
+    format!("{labeler:0>5}{product:0>4}{package:0>2}")
+}
+
+/// Normalizes a product list entry to its 11-digit NDC. Unhyphenated
+/// 10-digit codes are ambiguous and rejected.
+fn normalize(ndc: &str) -> Option<String> {
+    let parts: Vec<&str> = ndc.trim().split('-').collect();
+    if !parts
+        .iter()
+        .all(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return None;
+    }
+
+    match parts.as_slice() {
+        [digits] if digits.len() == NDC_11_LENGTH => Some(digits.to_string()),
+        [labeler, product, package] => {
+            let lengths = [labeler.len(), product.len(), package.len()];
+            (lengths == [5, 4, 2] || CONFIGURATIONS.iter().any(|(_, l)| *l == lengths))
+                .then(|| to_ndc11([labeler, product, package]))
+        }
+        _ => None,
+    }
+}
+
+/// Replaces the product list used to resolve ambiguous NDC configurations.
+pub fn set_ndc_products(products: &JsValue) -> Result<(), Error> {
+    let mut parsed = HashMap::new();
+    if !products.is_undefined() && !products.is_null() {
+        let products = products.dyn_ref::<Array>().ok_or(Error::InvalidOptions)?;
+        for product in products.iter() {
+            let ndc = match product.as_string() {
+                Some(ndc) => ndc,
+                None => options::get_string(&options::as_object(&product)?, "ndc")?
+                    .ok_or(Error::InvalidOptions)?,
+            };
+            parsed.insert(normalize(&ndc).ok_or(Error::InvalidOptions)?, product);
+        }
+    }
+
+    PRODUCTS.with(|slot| *slot.borrow_mut() = parsed);
+    Ok(())
+}
+
+/// A reading of the 10-digit NDC in one configuration.
+struct Candidate {
+    configuration: &'static str,
+    ndc: String,
+    ndc11: String,
+    product: Option<JsValue>,
+}
+
+impl Candidate {
+    fn set_on(&self, obj: &Object) {
+        for (key, value) in [
+            ("ndc", JsValue::from_str(&self.ndc)),
+            ("ndc11", JsValue::from_str(&self.ndc11)),
+            ("configuration", JsValue::from_str(self.configuration)),
+            ("product", self.product.clone().unwrap_or(JsValue::NULL)),
+        ] {
+            let _ = Reflect::set(obj, &JsValue::from_str(key), &value);
+        }
+    }
+}
+
+/// Extracts the NDC from a pharmaceutical barcode value.
+///
+/// Returns `null` when the value does not carry a US drug GTIN. The 10-digit
+/// NDC is read in every configuration, and the result is resolved to the one
+/// configuration found in the product list; without exactly one match, the
+/// resolved fields are `null` and only `candidates` are reported.
+pub fn extract_ndc(value: &str) -> JsValue {
+    let Some(gtin) = gtin14(value) else {
+        return JsValue::NULL;
+    };
+    if &gtin[1..3] != NDC_GTIN_PREFIX {
+        return JsValue::NULL;
+    }
+    let ndc10 = &gtin[3..3 + NDC_10_LENGTH];
+
+    let candidates: Vec<Candidate> = PRODUCTS.with(|products| {
+        let products = products.borrow();
+        CONFIGURATIONS
+            .iter()
+            .map(|(configuration, lengths)| {
+                let parts = segments(ndc10, lengths);
+                let ndc11 = to_ndc11(parts);
+                Candidate {
+                    configuration,
+                    ndc: parts.join("-"),
+                    product: products.get(&ndc11).cloned(),
+                    ndc11,
+                }
+            })
+            .collect()
+    });
+
+    let obj = Object::new();
+    let _ = Reflect::set(&obj, &JsValue::from_str("gtin"), &JsValue::from_str(&gtin));
+    let _ = Reflect::set(&obj, &JsValue::from_str("ndc10"), &JsValue::from_str(ndc10));
+
+    let mut matches = candidates.iter().filter(|c| c.product.is_some());
+    match (matches.next(), matches.next()) {
+        (Some(resolved), None) => resolved.set_on(&obj),
+        _ => {
+            for key in ["ndc", "ndc11", "configuration", "product"] {
+                let _ = Reflect::set(&obj, &JsValue::from_str(key), &JsValue::NULL);
+            }
+        }
+    }
+
+    let list: Array = candidates
+        .iter()
+        .map(|candidate| {
+            let entry = Object::new();
+            candidate.set_on(&entry);
+            JsValue::from(entry)
+        })
+        .collect();
+    let _ = Reflect::set(&obj, &JsValue::from_str("candidates"), &list);
+    obj.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// NDC 0777-3105-02 (4-4-2) as a GTIN-14.
+    const GTIN: &str = "00307773105025";
+
+    /// Reads `ndc10` in the configuration named `name`, as `extract_ndc` does.
+    fn reading(ndc10: &str, name: &str) -> (String, String) {
+        let (_, lengths) = CONFIGURATIONS.iter().find(|(n, _)| *n == name).unwrap();
+        let parts = segments(ndc10, lengths);
+        (parts.join("-"), to_ndc11(parts))
+    }
+
+    #[test]
+    fn gtins_are_read_from_upc_ean_and_gtin_14() {
+        for value in ["307773105025", "0307773105025", GTIN, " 307773105025\n"] {
+            assert_eq!(gtin14(value).as_deref(), Some(GTIN), "{value:?}");
+        }
+        assert_eq!(gtin14("350090347012").as_deref(), Some("00350090347012"));
+        assert_eq!(gtin14("360505014118").as_deref(), Some("00360505014118"));
+    }
+
+    #[test]
+    fn malformed_gtins_are_rejected() {
+        for value in [
+            "307773105026",
+            "00307773105024",
+            "30777310502",
+            "000307773105025",
+            "3077731050a5",
+            "",
+            "(01)00307773105024",
+        ] {
+            assert_eq!(gtin14(value), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn gtins_are_found_in_gs1_element_strings() {
+        let fnc1 = [
+            format!("{GS}01{GTIN}17261231"),
+            format!("01{GTIN}10LOT{GS}21SERIAL"),
+            format!("17261231{GS}01{GTIN}"),
+            // A fixed-length AI needs no separator before the next one.
+            format!("1726123101{GTIN}"),
+            format!("10LOT{GS}01{GTIN}"),
+        ];
+        for value in &fnc1 {
+            assert_eq!(gs1_gtin(value), Some(GTIN), "{value:?}");
+            assert_eq!(gtin14(value).as_deref(), Some(GTIN), "{value:?}");
+        }
+
+        for value in [
+            format!("(01){GTIN}(17)261231"),
+            format!("(17)261231(10)LOT(01){GTIN}"),
+        ] {
+            assert_eq!(gs1_gtin(&value), Some(GTIN), "{value:?}");
+        }
+
+        // No AI (01), or a variable-length AI never terminated.
+        for value in ["(17)261231(10)LOT", "10LOT0100307773105025", "17261231"] {
+            assert_eq!(gs1_gtin(value), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn each_configuration_pads_to_5_4_2() {
+        let ndc10 = &GTIN[3..13];
+        assert_eq!(ndc10, "0777310502");
+        assert_eq!(
+            reading(ndc10, "4-4-2"),
+            ("0777-3105-02".into(), "00777310502".into())
+        );
+        assert_eq!(
+            reading("5009034701", "5-3-2"),
+            ("50090-347-01".into(), "50090034701".into())
+        );
+        assert_eq!(
+            reading("6050501411", "5-4-1"),
+            ("60505-0141-1".into(), "60505014101".into())
+        );
+    }
+
+    #[test]
+    fn product_list_entries_normalize_to_11_digits() {
+        assert_eq!(normalize("0777-3105-02").as_deref(), Some("00777310502"));
+        assert_eq!(normalize("50090-347-01").as_deref(), Some("50090034701"));
+        assert_eq!(normalize("60505-0141-1").as_deref(), Some("60505014101"));
+        assert_eq!(normalize("00777-3105-02").as_deref(), Some("00777310502"));
+        assert_eq!(normalize(" 00777310502 ").as_deref(), Some("00777310502"));
+
+        for ndc in [
+            "0777310502",
+            "777-3105-02",
+            "0777-3105-2",
+            "0777--02",
+            "0777-31O5-02",
+            "0777-3105-02-1",
+            "",
+        ] {
+            assert_eq!(normalize(ndc), None, "{ndc:?}");
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use js_sys::{Object, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::error::Error;
+
+/// Returns the options object, treating `undefined`/`null` as an empty object.
+pub fn as_object(options: &JsValue) -> Result<Object, Error> {
+    if options.is_undefined() || options.is_null() {
+        return Ok(Object::new());
+    }
+
+    options
+        .clone()
+        .dyn_into::<Object>()
+        .map_err(|_| Error::InvalidOptions)
+}
+
+pub fn get(obj: &Object, key: &str) -> Option<JsValue> {
+    Reflect::get(obj, &JsValue::from_str(key))
+        .ok()
+        .filter(|v| !v.is_undefined() && !v.is_null())
+}
+
+pub fn get_bool(obj: &Object, key: &str) -> Result<Option<bool>, Error> {
+    match get(obj, key) {
+        Some(v) => v.as_bool().map(Some).ok_or(Error::InvalidOptions),
+        None => Ok(None),
+    }
+}
+
+pub fn get_u32(obj: &Object, key: &str) -> Result<Option<u32>, Error> {
+    match get(obj, key) {
+        Some(v) => match v.as_f64() {
+            Some(n) if n >= 0.0 && n <= u32::MAX as f64 && n.fract() == 0.0 => Ok(Some(n as u32)),
+            _ => Err(Error::InvalidOptions),
+        },
+        None => Ok(None),
+    }
+}
+
+pub fn get_f64(obj: &Object, key: &str) -> Result<Option<f64>, Error> {
+    match get(obj, key) {
+        Some(v) => match v.as_f64() {
+            Some(n) if n.is_finite() => Ok(Some(n)),
+            _ => Err(Error::InvalidOptions),
+        },
+        None => Ok(None),
+    }
+}
+
+pub fn get_string(obj: &Object, key: &str) -> Result<Option<String>, Error> {
+    match get(obj, key) {
+        Some(v) => v.as_string().map(Some).ok_or(Error::InvalidOptions),
+        None => Ok(None),
+    }
+}
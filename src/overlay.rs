@@ -0,0 +1,397 @@
+use std::{cell::RefCell, f64::consts::PI};
+
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlVideoElement};
+
+use crate::{
+    detector::{Detection, canvas_context, stream_region},
+    error::Error,
+    options,
+    stats::now_precise,
+};
+
+const SCAN_LINE_PERIOD_MS: f64 = 2000.0;
+
+/// Styling of the overlay drawn over the camera video.
+#[derive(Clone)]
+struct OverlayOptions {
+    canvas: Option<HtmlCanvasElement>,
+    viewfinder: bool,
+    scan_line: bool,
+    viewfinder_color: String,
+    scan_line_color: String,
+    point_color: String,
+    mask_color: Option<String>,
+    line_width: f64,
+    highlight_ms: f64,
+}
+
+impl Default for OverlayOptions {
+    fn default() -> Self {
+        OverlayOptions {
+            canvas: None,
+            viewfinder: true,
+            scan_line: true,
+            viewfinder_color: "rgba(255, 255, 255, 0.9)".into(),
+            scan_line_color: "rgba(255, 64, 64, 0.8)".into(),
+            point_color: "#00e676".into(),
+            mask_color: Some("rgba(0, 0, 0, 0.35)".into()),
+            line_width: 3.0,
+            highlight_ms: 600.0,
+        }
+    }
+}
+
+/// The canvas currently drawn on and whether it was created by the library.
+struct ActiveOverlay {
+    canvas: HtmlCanvasElement,
+    ctx: CanvasRenderingContext2d,
+    owned: bool,
+    layout: (i32, i32, i32, i32),
+}
+
+/// Maps video frame pixels to CSS pixels of the video element box.
+#[derive(Clone, Copy)]
+struct FrameMapping {
+    scale_x: f64,
+    scale_y: f64,
+    offset_x: f64,
+    offset_y: f64,
+}
+
+impl FrameMapping {
+    fn map(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            x * self.scale_x + self.offset_x,
+            y * self.scale_y + self.offset_y,
+        )
+    }
+}
+
+/// Result points of the latest detection and when they were decoded.
+struct Highlight {
+    points: Vec<(f32, f32)>,
+    shown_at: f64,
+}
+
+thread_local! {
+    static OVERLAY_OPTIONS: RefCell<Option<OverlayOptions>> = const { RefCell::new(None) };
+    static ACTIVE: RefCell<Option<ActiveOverlay>> = const { RefCell::new(None) };
+    static LAST_POINTS: RefCell<Option<Highlight>> = const { RefCell::new(None) };
+}
+
+pub fn set_overlay_options(options: &JsValue) -> Result<(), Error> {
+    let obj = options::as_object(options)?;
+    if !options::get_bool(&obj, "enabled")?.unwrap_or(true) {
+        OVERLAY_OPTIONS.with(|slot| *slot.borrow_mut() = None);
+        return Ok(());
+    }
+
+    let defaults = OverlayOptions::default();
+    let canvas = match options::get(&obj, "canvas") {
+        Some(value) => Some(
+            value
+                .dyn_into::<HtmlCanvasElement>()
+                .map_err(|_| Error::InvalidOptions)?,
+        ),
+        None => None,
+    };
+    let mask_color = match options::get(&obj, "maskColor") {
+        Some(value) if value.as_bool() == Some(false) => None,
+        Some(value) => Some(value.as_string().ok_or(Error::InvalidOptions)?),
+        None => defaults.mask_color,
+    };
+
+    let parsed = OverlayOptions {
+        canvas,
+        viewfinder: options::get_bool(&obj, "viewfinder")?.unwrap_or(defaults.viewfinder),
+        scan_line: options::get_bool(&obj, "scanLine")?.unwrap_or(defaults.scan_line),
+        viewfinder_color: options::get_string(&obj, "viewfinderColor")?
+            .unwrap_or(defaults.viewfinder_color),
+        scan_line_color: options::get_string(&obj, "scanLineColor")?
+            .unwrap_or(defaults.scan_line_color),
+        point_color: options::get_string(&obj, "pointColor")?.unwrap_or(defaults.point_color),
+        mask_color,
+        line_width: options::get_f64(&obj, "lineWidth")?
+            .filter(|width| *width > 0.0)
+            .unwrap_or(defaults.line_width),
+        highlight_ms: options::get_u32(&obj, "highlightMs")?
+            .map_or(defaults.highlight_ms, |ms| ms as f64),
+    };
+
+    OVERLAY_OPTIONS.with(|slot| *slot.borrow_mut() = Some(parsed));
+    Ok(())
+}
+
+fn current_options() -> Option<OverlayOptions> {
+    OVERLAY_OPTIONS.with(|slot| slot.borrow().clone())
+}
+
+/// Prepares the overlay canvas for a camera session, creating one next to
+/// the video element when the app did not provide its own.
+pub fn attach(video: &HtmlVideoElement) {
+    detach();
+
+    let Some(options) = current_options() else {
+        return;
+    };
+
+    let (canvas, owned) = match options.canvas {
+        Some(canvas) => (canvas, false),
+        None => {
+            let Some(canvas) = video
+                .owner_document()
+                .and_then(|doc| doc.create_element("canvas").ok())
+                .and_then(|el| el.dyn_into::<HtmlCanvasElement>().ok())
+            else {
+                return;
+            };
+            canvas
+                .set_attribute("style", "position: absolute; pointer-events: none;")
+                .ok();
+
+            let Some(parent) = video.parent_node() else {
+                return;
+            };
+            if parent
+                .insert_before(&canvas, video.next_sibling().as_ref())
+                .is_err()
+            {
+                return;
+            }
+            (canvas, true)
+        }
+    };
+
+    let Ok(ctx) = canvas_context(&canvas) else {
+        return;
+    };
+
+    LAST_POINTS.with(|points| *points.borrow_mut() = None);
+    ACTIVE.with(|active| {
+        *active.borrow_mut() = Some(ActiveOverlay {
+            canvas,
+            ctx,
+            owned,
+            layout: (0, 0, 0, 0),
+        });
+    });
+}
+
+/// Clears the overlay and removes the canvas if the library created it.
+pub fn detach() {
+    let Some(active) = ACTIVE.with(|active| active.borrow_mut().take()) else {
+        return;
+    };
+
+    if active.owned {
+        active.canvas.remove();
+    } else {
+        active.ctx.clear_rect(
+            0.0,
+            0.0,
+            active.canvas.width() as f64,
+            active.canvas.height() as f64,
+        );
+    }
+}
+
+/// Highlights the result points of a detection on the next frames.
+pub fn show_detection(detection: &Detection) {
+    if detection.points.is_empty() {
+        return;
+    }
+
+    let highlight = Highlight {
+        points: detection.points.clone(),
+        shown_at: now_precise(),
+    };
+    LAST_POINTS.with(|points| *points.borrow_mut() = Some(highlight));
+}
+
+/// Computes how the browser lays out the video frame inside the element box,
+/// following the CSS `object-fit` of the video (`object-position` is assumed
+/// to be centered).
+fn frame_mapping(video: &HtmlVideoElement, box_w: f64, box_h: f64) -> Option<FrameMapping> {
+    let (vw, vh) = (video.video_width() as f64, video.video_height() as f64);
+    if vw == 0.0 || vh == 0.0 {
+        return None;
+    }
+
+    let object_fit = web_sys::window()
+        .and_then(|window| window.get_computed_style(video).ok().flatten())
+        .and_then(|style| style.get_property_value("object-fit").ok())
+        .unwrap_or_default();
+
+    let contain = (box_w / vw).min(box_h / vh);
+    let (scale_x, scale_y) = match object_fit.as_str() {
+        "fill" => (box_w / vw, box_h / vh),
+        "cover" => {
+            let scale = (box_w / vw).max(box_h / vh);
+            (scale, scale)
+        }
+        "none" => (1.0, 1.0),
+        "scale-down" => (contain.min(1.0), contain.min(1.0)),
+        // Video elements letterbox by default.
+        _ => (contain, contain),
+    };
+
+    Some(FrameMapping {
+        scale_x,
+        scale_y,
+        offset_x: (box_w - vw * scale_x) / 2.0,
+        offset_y: (box_h - vh * scale_y) / 2.0,
+    })
+}
+
+/// Keeps the canvas aligned with the video element and sized for the
+/// device pixel ratio.
+fn sync_layout(active: &mut ActiveOverlay, video: &HtmlVideoElement) -> Option<(f64, f64)> {
+    let (box_w, box_h) = (video.client_width(), video.client_height());
+    if box_w == 0 || box_h == 0 {
+        return None;
+    }
+
+    let layout = (video.offset_left(), video.offset_top(), box_w, box_h);
+    if layout != active.layout {
+        active.layout = layout;
+        if active.owned {
+            let style = format!(
+                "position: absolute; pointer-events: none; left: {}px; top: {}px; width: {}px; height: {}px;",
+                layout.0, layout.1, box_w, box_h
+            );
+            active.canvas.set_attribute("style", &style).ok();
+        }
+    }
+
+    let dpr = web_sys::window().map_or(1.0, |window| window.device_pixel_ratio());
+    let (pixel_w, pixel_h) = (
+        (box_w as f64 * dpr).round() as u32,
+        (box_h as f64 * dpr).round() as u32,
+    );
+    if active.canvas.width() != pixel_w || active.canvas.height() != pixel_h {
+        active.canvas.set_width(pixel_w);
+        active.canvas.set_height(pixel_h);
+    }
+    active.ctx.set_transform(dpr, 0.0, 0.0, dpr, 0.0, 0.0).ok();
+
+    Some((box_w as f64, box_h as f64))
+}
+
+/// Draws one overlay frame. Called on every animation frame of a session.
+pub fn render(video: &HtmlVideoElement) {
+    let Some(options) = current_options() else {
+        return;
+    };
+
+    ACTIVE.with(|active| {
+        let mut active = active.borrow_mut();
+        let Some(active) = active.as_mut() else {
+            return;
+        };
+
+        let Some((box_w, box_h)) = sync_layout(active, video) else {
+            return;
+        };
+        let ctx = &active.ctx;
+        ctx.clear_rect(0.0, 0.0, box_w, box_h);
+
+        let Some(mapping) = frame_mapping(video, box_w, box_h) else {
+            return;
+        };
+
+        let (rx, ry, rw, rh) = stream_region(video.video_width(), video.video_height());
+        let (left, top) = mapping.map(rx as f64, ry as f64);
+        let (right, bottom) = mapping.map((rx + rw) as f64, (ry + rh) as f64);
+
+        if let Some(mask_color) = &options.mask_color {
+            ctx.set_fill_style_str(mask_color);
+            ctx.fill_rect(0.0, 0.0, box_w, top);
+            ctx.fill_rect(0.0, bottom, box_w, box_h - bottom);
+            ctx.fill_rect(0.0, top, left, bottom - top);
+            ctx.fill_rect(right, top, box_w - right, bottom - top);
+        }
+
+        ctx.set_line_width(options.line_width);
+
+        if options.viewfinder {
+            draw_corners(ctx, &options.viewfinder_color, left, top, right, bottom);
+        }
+
+        if options.scan_line {
+            let phase = (now_precise() % SCAN_LINE_PERIOD_MS) / SCAN_LINE_PERIOD_MS;
+            let y = top + (bottom - top) * (0.5 - 0.5 * (phase * 2.0 * PI).cos());
+            ctx.set_stroke_style_str(&options.scan_line_color);
+            ctx.begin_path();
+            ctx.move_to(left, y);
+            ctx.line_to(right, y);
+            ctx.stroke();
+        }
+
+        draw_points(ctx, &options, &mapping);
+    });
+}
+
+fn draw_corners(
+    ctx: &CanvasRenderingContext2d,
+    color: &str,
+    left: f64,
+    top: f64,
+    right: f64,
+    bottom: f64,
+) {
+    let arm = ((right - left).min(bottom - top) / 8.0).max(8.0);
+
+    ctx.set_stroke_style_str(color);
+    ctx.begin_path();
+    for (x, y, dx, dy) in [
+        (left, top, 1.0, 1.0),
+        (right, top, -1.0, 1.0),
+        (left, bottom, 1.0, -1.0),
+        (right, bottom, -1.0, -1.0),
+    ] {
+        ctx.move_to(x + arm * dx, y);
+        ctx.line_to(x, y);
+        ctx.line_to(x, y + arm * dy);
+    }
+    ctx.stroke();
+}
+
+fn draw_points(ctx: &CanvasRenderingContext2d, options: &OverlayOptions, mapping: &FrameMapping) {
+    let now = now_precise();
+    let points: Vec<(f64, f64)> = LAST_POINTS.with(|last| match &*last.borrow() {
+        Some(highlight) if now - highlight.shown_at <= options.highlight_ms => highlight
+            .points
+            .iter()
+            .map(|&(x, y)| mapping.map(x as f64, y as f64))
+            .collect(),
+        _ => Vec::new(),
+    });
+    if points.is_empty() {
+        return;
+    }
+
+    ctx.set_stroke_style_str(&options.point_color);
+    ctx.set_fill_style_str(&options.point_color);
+
+    // Linear barcodes report the two ends of a row; 2D codes report their
+    // finder patterns, which are connected into an outline.
+    if points.len() >= 2 {
+        ctx.begin_path();
+        ctx.move_to(points[0].0, points[0].1);
+        for &(x, y) in &points[1..] {
+            ctx.line_to(x, y);
+        }
+        if points.len() > 2 {
+            ctx.close_path();
+        }
+        ctx.stroke();
+    }
+
+    let radius = options.line_width * 1.5;
+    for &(x, y) in &points {
+        ctx.begin_path();
+        ctx.arc(x, y, radius, 0.0, 2.0 * PI).ok();
+        ctx.fill();
+    }
+}
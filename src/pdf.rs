@@ -0,0 +1,1570 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    io::Read,
+};
+
+use flate2::read::ZlibDecoder;
+use image::{GrayImage, ImageFormat};
+use js_sys::Uint8Array;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{JsFuture, spawn_local};
+use web_sys::Blob;
+
+use crate::{
+    detector::{Detection, detect_from_gray_image, filter_result},
+    error::Error,
+    event::{invoke_on_detect, invoke_on_detect_tagged, invoke_on_stop},
+    options,
+};
+
+pub const PDF_MIME: &str = "application/pdf";
+
+/// Form XObjects can nest; scanned pages rarely wrap images more than once or twice.
+const MAX_FORM_DEPTH: u32 = 4;
+const MAX_PAGE_TREE_DEPTH: u32 = 32;
+/// Bounds on embedded image sizes, generous for a 600 dpi A3 scan while
+/// keeping a crafted PDF from forcing an allocation the wasm heap cannot hold.
+const MAX_IMAGE_DIMENSION: u32 = 16_384;
+const MAX_IMAGE_PIXELS: usize = 1 << 26;
+/// Bound on a decoded stream whose size the dictionary does not imply, such
+/// as an object stream or a compressed JPEG.
+const MAX_STREAM_BYTES: usize = MAX_IMAGE_PIXELS * 4;
+/// An Indexed lookup holds at most 256 entries of up to four components.
+const MAX_LOOKUP_BYTES: usize = 256 * 4;
+
+#[derive(Clone, Copy)]
+struct PdfOptions {
+    first_page: u32,
+    last_page: Option<u32>,
+}
+
+thread_local! {
+    static PDF_OPTIONS: Cell<PdfOptions> = const {
+        Cell::new(PdfOptions {
+            first_page: 1,
+            last_page: None,
+        })
+    };
+}
+
+pub fn set_pdf_options(options: &JsValue) -> Result<(), Error> {
+    let obj = options::as_object(options)?;
+
+    let first_page = options::get_u32(&obj, "firstPage")?.unwrap_or(1);
+    let last_page = options::get_u32(&obj, "lastPage")?;
+    if first_page == 0 || last_page.is_some_and(|last| last < first_page) {
+        return Err(Error::InvalidOptions);
+    }
+
+    PDF_OPTIONS.with(|opts| {
+        opts.set(PdfOptions {
+            first_page,
+            last_page,
+        })
+    });
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+enum Obj {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Name(String),
+    Str(Vec<u8>),
+    Array(Vec<Obj>),
+    Dict(Dict),
+    Ref(u32),
+    Stream(Dict, Vec<u8>),
+}
+
+type Dict = HashMap<String, Obj>;
+
+impl Obj {
+    fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Obj::Dict(d) | Obj::Stream(d, _) => Some(d),
+            _ => None,
+        }
+    }
+
+    fn as_name(&self) -> Option<&str> {
+        match self {
+            Obj::Name(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    fn as_num(&self) -> Option<f64> {
+        match self {
+            Obj::Num(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&[Obj]> {
+        match self {
+            Obj::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b) = self.peek() {
+            if is_whitespace(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while let Some(b) = self.peek() {
+                    if b == b'\r' || b == b'\n' {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn starts_with(&self, token: &[u8]) -> bool {
+        self.data
+            .get(self.pos..)
+            .is_some_and(|rest| rest.starts_with(token))
+    }
+
+    fn regular_token(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while let Some(b) = self.peek() {
+            if is_whitespace(b) || is_delimiter(b) {
+                break;
+            }
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    fn parse_unsigned(&mut self) -> Option<u32> {
+        let token = self.regular_token();
+        if token.is_empty() || !token.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        std::str::from_utf8(token).ok()?.parse().ok()
+    }
+
+    fn parse(&mut self) -> Option<Obj> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'/' => {
+                self.pos += 1;
+                Some(Obj::Name(self.parse_name()))
+            }
+            b'<' if self.starts_with(b"<<") => {
+                self.pos += 2;
+                self.parse_dict().map(Obj::Dict)
+            }
+            b'<' => {
+                self.pos += 1;
+                Some(Obj::Str(self.parse_hex_string()))
+            }
+            b'(' => {
+                self.pos += 1;
+                Some(Obj::Str(self.parse_literal_string()))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek()? {
+                        b']' => {
+                            self.pos += 1;
+                            return Some(Obj::Array(items));
+                        }
+                        _ => items.push(self.parse()?),
+                    }
+                }
+            }
+            _ => self.parse_keyword_or_number(),
+        }
+    }
+
+    fn parse_name(&mut self) -> String {
+        let token = self.regular_token();
+        let mut out = Vec::with_capacity(token.len());
+        let mut i = 0;
+        while i < token.len() {
+            let hex = token
+                .get(i + 1..i + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok());
+            match (token[i], hex) {
+                (b'#', Some(b)) => {
+                    out.push(b);
+                    i += 3;
+                }
+                (b, _) => {
+                    out.push(b);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    fn parse_dict(&mut self) -> Option<Dict> {
+        let mut dict = Dict::new();
+        loop {
+            self.skip_whitespace();
+            if self.starts_with(b">>") {
+                self.pos += 2;
+                return Some(dict);
+            }
+            let Obj::Name(key) = self.parse()? else {
+                return None;
+            };
+            let value = self.parse()?;
+            dict.insert(key, value);
+        }
+    }
+
+    fn parse_hex_string(&mut self) -> Vec<u8> {
+        let mut nibbles = Vec::new();
+        while let Some(b) = self.peek() {
+            self.pos += 1;
+            if b == b'>' {
+                break;
+            }
+            if let Some(n) = (b as char).to_digit(16) {
+                nibbles.push(n as u8);
+            }
+        }
+        if nibbles.len() % 2 == 1 {
+            nibbles.push(0);
+        }
+        nibbles.chunks(2).map(|p| (p[0] << 4) | p[1]).collect()
+    }
+
+    fn parse_literal_string(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut depth = 1;
+        while let Some(b) = self.peek() {
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    out.push(b);
+                }
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    out.push(b);
+                }
+                b'\\' => {
+                    let Some(esc) = self.peek() else {
+                        break;
+                    };
+                    self.pos += 1;
+                    match esc {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0c),
+                        b'0'..=b'7' => {
+                            let mut value = (esc - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(value as u8);
+                        }
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => out.push(other),
+                    }
+                }
+                _ => out.push(b),
+            }
+        }
+        out
+    }
+
+    fn parse_keyword_or_number(&mut self) -> Option<Obj> {
+        let start = self.pos;
+        let token = self.regular_token();
+        match token {
+            b"true" => return Some(Obj::Bool(true)),
+            b"false" => return Some(Obj::Bool(false)),
+            b"null" => return Some(Obj::Null),
+            _ => {}
+        }
+
+        let text = std::str::from_utf8(token).ok()?;
+        let number: f64 = text.parse().ok()?;
+
+        // `num gen R` is an indirect reference.
+        if token.iter().all(u8::is_ascii_digit) {
+            let after_num = self.pos;
+            self.skip_whitespace();
+            if self.parse_unsigned().is_some() {
+                self.skip_whitespace();
+                if self.peek() == Some(b'R')
+                    && self
+                        .data
+                        .get(self.pos + 1)
+                        .is_none_or(|&b| is_whitespace(b) || is_delimiter(b))
+                {
+                    self.pos += 1;
+                    return Some(Obj::Ref(number as u32));
+                }
+            }
+            self.pos = after_num;
+        }
+
+        if self.pos == start {
+            return None;
+        }
+        Some(Obj::Num(number))
+    }
+
+    /// Parses the body of an indirect object, attaching stream data if present.
+    fn parse_indirect_body(&mut self) -> Option<Obj> {
+        let obj = self.parse()?;
+        let Obj::Dict(dict) = obj else {
+            return Some(obj);
+        };
+
+        self.skip_whitespace();
+        if !self.starts_with(b"stream") {
+            return Some(Obj::Dict(dict));
+        }
+        self.pos += b"stream".len();
+        if self.starts_with(b"\r\n") {
+            self.pos += 2;
+        } else if self.peek() == Some(b'\n') || self.peek() == Some(b'\r') {
+            self.pos += 1;
+        }
+
+        let start = self.pos;
+        let declared_end = dict
+            .get("Length")
+            .and_then(Obj::as_num)
+            .and_then(|len| start.checked_add(len as usize))
+            .filter(|&end| {
+                let mut p = Parser::new(self.data, end.min(self.data.len()));
+                p.skip_whitespace();
+                p.starts_with(b"endstream")
+            });
+
+        // An indirect or wrong `/Length` is common enough that the data is
+        // delimited by the `endstream` keyword instead.
+        let end = match declared_end {
+            Some(end) => end,
+            None => {
+                let rel = find(&self.data[start..], b"endstream")?;
+                let mut end = start + rel;
+                if end > start && self.data[end - 1] == b'\n' {
+                    end -= 1;
+                }
+                if end > start && self.data[end - 1] == b'\r' {
+                    end -= 1;
+                }
+                end
+            }
+        };
+
+        let data = self.data[start..end].to_vec();
+        self.pos = end;
+        Some(Obj::Stream(dict, data))
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Scans forward from `from` for the next `num gen obj` header, returning the
+/// object number and the offset just past `obj`.
+fn next_object_header(data: &[u8], from: usize) -> Option<(u32, usize)> {
+    let mut search = from;
+    loop {
+        let idx = search + find(&data[search..], b"obj")?;
+        search = idx + 3;
+
+        if data
+            .get(idx + 3)
+            .is_some_and(|&b| !is_whitespace(b) && !is_delimiter(b))
+        {
+            continue;
+        }
+
+        // Walk back over `<num> <gen> `.
+        let mut p = idx;
+        let skip_ws_back = |p: &mut usize| {
+            while *p > 0 && is_whitespace(data[*p - 1]) {
+                *p -= 1;
+            }
+        };
+        let digits_back = |p: &mut usize| -> Option<u32> {
+            let end = *p;
+            while *p > 0 && data[*p - 1].is_ascii_digit() {
+                *p -= 1;
+            }
+            std::str::from_utf8(&data[*p..end]).ok()?.parse().ok()
+        };
+
+        skip_ws_back(&mut p);
+        if p == idx || digits_back(&mut p).is_none() {
+            continue;
+        }
+        let gen_start = p;
+        skip_ws_back(&mut p);
+        if p == gen_start {
+            continue;
+        }
+        let Some(num) = digits_back(&mut p) else {
+            continue;
+        };
+        if p > 0 && !is_whitespace(data[p - 1]) && !is_delimiter(data[p - 1]) {
+            continue;
+        }
+
+        return Some((num, idx + 3));
+    }
+}
+
+struct Document {
+    objects: HashMap<u32, Obj>,
+}
+
+impl Document {
+    /// Loads every indirect object by scanning the file body, which also
+    /// copes with damaged or missing cross-reference tables.
+    fn load(data: &[u8]) -> Result<Self, Error> {
+        if find(&data[..data.len().min(1024)], b"%PDF-").is_none() {
+            return Err(Error::UnsupportedFormat);
+        }
+
+        let mut objects = HashMap::new();
+        let mut pos = 0;
+        while let Some((num, body_start)) = next_object_header(data, pos) {
+            let mut parser = Parser::new(data, body_start);
+            match parser.parse_indirect_body() {
+                Some(obj) => {
+                    // Later definitions win, matching incremental updates.
+                    objects.insert(num, obj);
+                    pos = parser.pos;
+                }
+                None => pos = body_start,
+            }
+        }
+
+        let mut doc = Self { objects };
+        doc.expand_object_streams();
+
+        Ok(doc)
+    }
+
+    fn expand_object_streams(&mut self) {
+        let streams: Vec<Obj> = self
+            .objects
+            .values()
+            .filter(|obj| {
+                obj.as_dict()
+                    .and_then(|d| d.get("Type"))
+                    .and_then(Obj::as_name)
+                    == Some("ObjStm")
+            })
+            .cloned()
+            .collect();
+
+        for stream in streams {
+            let Obj::Stream(dict, raw) = &stream else {
+                continue;
+            };
+            let Some(data) = self.decode_stream(dict, raw, MAX_STREAM_BYTES) else {
+                continue;
+            };
+            let count = dict.get("N").and_then(Obj::as_num).unwrap_or(0.0) as usize;
+            let first = dict.get("First").and_then(Obj::as_num).unwrap_or(0.0) as usize;
+
+            let mut header = Parser::new(&data, 0);
+            for _ in 0..count {
+                header.skip_whitespace();
+                let Some(num) = header.parse_unsigned() else {
+                    break;
+                };
+                header.skip_whitespace();
+                let Some(offset) = header.parse_unsigned() else {
+                    break;
+                };
+
+                if self.objects.contains_key(&num) {
+                    continue;
+                }
+                let mut body = Parser::new(&data, first.saturating_add(offset as usize));
+                if let Some(obj) = body.parse() {
+                    self.objects.insert(num, obj);
+                }
+            }
+        }
+    }
+
+    fn resolve<'a>(&'a self, obj: &'a Obj) -> &'a Obj {
+        let mut current = obj;
+        // Bounded to avoid looping on reference cycles.
+        for _ in 0..8 {
+            match current {
+                Obj::Ref(num) => match self.objects.get(num) {
+                    Some(target) => current = target,
+                    None => return &Obj::Null,
+                },
+                _ => return current,
+            }
+        }
+        &Obj::Null
+    }
+
+    fn get<'a>(&'a self, dict: &'a Dict, key: &str) -> Option<&'a Obj> {
+        dict.get(key).map(|v| self.resolve(v))
+    }
+
+    fn filters<'a>(&'a self, dict: &'a Dict) -> Vec<(&'a str, Option<&'a Dict>)> {
+        let names: Vec<&str> = match self.get(dict, "Filter") {
+            Some(Obj::Name(n)) => vec![n.as_str()],
+            Some(Obj::Array(items)) => items
+                .iter()
+                .filter_map(|i| self.resolve(i).as_name())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let parms: Vec<Option<&Dict>> = match self.get(dict, "DecodeParms") {
+            Some(Obj::Array(items)) => items.iter().map(|i| self.resolve(i).as_dict()).collect(),
+            Some(other) => vec![other.as_dict()],
+            None => Vec::new(),
+        };
+
+        names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| (name, parms.get(i).copied().flatten()))
+            .collect()
+    }
+
+    /// Applies the generic (non-image) filters of a stream, failing once any
+    /// stage decodes to more than `limit` bytes.
+    fn decode_stream(&self, dict: &Dict, raw: &[u8], limit: usize) -> Option<Vec<u8>> {
+        let mut data = raw.to_vec();
+        for (name, parms) in self.filters(dict) {
+            data = match name {
+                "FlateDecode" | "Fl" => {
+                    let inflated = inflate(&data, limit)?;
+                    self.apply_predictor(inflated, parms)?
+                }
+                "ASCIIHexDecode" | "AHx" => Parser::new(&data, 0).parse_hex_string(),
+                "ASCII85Decode" | "A85" => ascii85_decode(&data)?,
+                _ => return None,
+            };
+            if data.len() > limit {
+                return None;
+            }
+        }
+        Some(data)
+    }
+
+    fn apply_predictor(&self, data: Vec<u8>, parms: Option<&Dict>) -> Option<Vec<u8>> {
+        let Some(parms) = parms else {
+            return Some(data);
+        };
+        let int = |key: &str, default: f64| {
+            self.get(parms, key)
+                .and_then(Obj::as_num)
+                .unwrap_or(default) as usize
+        };
+
+        let predictor = int("Predictor", 1.0);
+        if predictor < 10 {
+            // TIFF predictor 2 is vanishingly rare in scanned documents.
+            return (predictor == 1).then_some(data);
+        }
+
+        let colors = int("Colors", 1.0);
+        let bpc = int("BitsPerComponent", 8.0);
+        let columns = int("Columns", 1.0);
+        let bits_per_pixel = colors.checked_mul(bpc)?;
+        let bpp = bits_per_pixel.div_ceil(8).max(1);
+        let row_len = bits_per_pixel.checked_mul(columns)?.div_ceil(8);
+        if row_len == 0 || row_len > data.len() {
+            return None;
+        }
+
+        png_unpredict(&data, row_len, bpp)
+    }
+}
+
+/// Inflates a zlib stream, failing if it holds more than `limit` bytes so a
+/// deflate bomb cannot exhaust the heap.
+fn inflate(data: &[u8], limit: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let result = ZlibDecoder::new(data)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut out);
+    if out.len() > limit {
+        return None;
+    }
+    match result {
+        Ok(_) => Some(out),
+        // Truncated streams are common; keep whatever was recovered.
+        Err(_) if !out.is_empty() => Some(out),
+        Err(_) => None,
+    }
+}
+
+fn png_unpredict(data: &[u8], row_len: usize, bpp: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut prev = vec![0u8; row_len];
+    for chunk in data.chunks(row_len + 1) {
+        let (&filter, row) = chunk.split_first()?;
+        let mut cur = row.to_vec();
+        cur.resize(row_len, 0);
+        for i in 0..row_len {
+            let left = if i >= bpp { cur[i - bpp] } else { 0 };
+            let up = prev[i];
+            let up_left = if i >= bpp { prev[i - bpp] } else { 0 };
+            let add = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return None,
+            };
+            cur[i] = cur[i].wrapping_add(add);
+        }
+        out.extend_from_slice(&cur);
+        prev = cur;
+    }
+    Some(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn ascii85_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut group = [0u8; 5];
+    let mut len = 0;
+    for &b in data {
+        match b {
+            b'~' => break,
+            b'z' if len == 0 => out.extend_from_slice(&[0; 4]),
+            b'!'..=b'u' => {
+                group[len] = b - b'!';
+                len += 1;
+                if len == 5 {
+                    let value = group
+                        .iter()
+                        .fold(0u32, |acc, &d| acc.wrapping_mul(85) + d as u32);
+                    out.extend_from_slice(&value.to_be_bytes());
+                    len = 0;
+                }
+            }
+            b if is_whitespace(b) => {}
+            _ => return None,
+        }
+    }
+    if len > 1 {
+        for slot in group.iter_mut().skip(len) {
+            *slot = 84;
+        }
+        let value = group
+            .iter()
+            .fold(0u32, |acc, &d| acc.wrapping_mul(85) + d as u32);
+        out.extend_from_slice(&value.to_be_bytes()[..len - 1]);
+    }
+    Some(out)
+}
+
+struct Page<'a> {
+    resources: Option<&'a Dict>,
+}
+
+impl Document {
+    /// Returns the pages in document order, with inherited resources resolved.
+    fn pages(&self) -> Vec<Page<'_>> {
+        let root = self
+            .objects
+            .values()
+            .filter_map(Obj::as_dict)
+            .find(|d| d.get("Type").and_then(Obj::as_name) == Some("Catalog"))
+            .and_then(|catalog| self.get(catalog, "Pages"))
+            .and_then(Obj::as_dict);
+
+        let mut pages = Vec::new();
+        if let Some(root) = root {
+            let mut visited = HashSet::new();
+            self.collect_pages(root, None, 0, &mut visited, &mut pages);
+        }
+
+        if pages.is_empty() {
+            // No usable page tree: fall back to page objects in object order.
+            let mut nums: Vec<&u32> = self
+                .objects
+                .iter()
+                .filter(|(_, obj)| {
+                    obj.as_dict()
+                        .and_then(|d| d.get("Type"))
+                        .and_then(Obj::as_name)
+                        == Some("Page")
+                })
+                .map(|(num, _)| num)
+                .collect();
+            nums.sort();
+            pages = nums
+                .into_iter()
+                .filter_map(|num| self.objects.get(num).and_then(Obj::as_dict))
+                .map(|page| Page {
+                    resources: self.get(page, "Resources").and_then(Obj::as_dict),
+                })
+                .collect();
+        }
+
+        pages
+    }
+
+    fn collect_pages<'a>(
+        &'a self,
+        node: &'a Dict,
+        inherited: Option<&'a Dict>,
+        depth: u32,
+        visited: &mut HashSet<*const Dict>,
+        pages: &mut Vec<Page<'a>>,
+    ) {
+        if depth > MAX_PAGE_TREE_DEPTH || !visited.insert(node as *const Dict) {
+            return;
+        }
+
+        let resources = self
+            .get(node, "Resources")
+            .and_then(Obj::as_dict)
+            .or(inherited);
+
+        match self.get(node, "Kids").and_then(Obj::as_array) {
+            Some(kids) => {
+                for kid in kids {
+                    if let Some(kid) = self.resolve(kid).as_dict() {
+                        self.collect_pages(kid, resources, depth + 1, visited, pages);
+                    }
+                }
+            }
+            None => pages.push(Page { resources }),
+        }
+    }
+
+    /// Collects the image XObjects reachable from `resources`, descending into
+    /// form XObjects.
+    fn images<'a>(&'a self, resources: &'a Dict, depth: u32, out: &mut Vec<(&'a Dict, &'a [u8])>) {
+        if depth > MAX_FORM_DEPTH {
+            return;
+        }
+        let Some(xobjects) = self.get(resources, "XObject").and_then(Obj::as_dict) else {
+            return;
+        };
+
+        for xobject in xobjects.values() {
+            let Obj::Stream(dict, data) = self.resolve(xobject) else {
+                continue;
+            };
+            match self.get(dict, "Subtype").and_then(Obj::as_name) {
+                Some("Image") => out.push((dict, data)),
+                Some("Form") => {
+                    if let Some(inner) = self.get(dict, "Resources").and_then(Obj::as_dict) {
+                        self.images(inner, depth + 1, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn decode_image(&self, dict: &Dict, raw: &[u8]) -> Option<GrayImage> {
+        let filters = self.filters(dict);
+        let (image_filter, image_parms) = match filters.last() {
+            Some((
+                name @ ("DCTDecode" | "DCT" | "CCITTFaxDecode" | "CCF" | "JPXDecode"),
+                parms,
+            )) => (Some(*name), *parms),
+            _ => (None, None),
+        };
+
+        // Check the dimensions before decoding anything they would bound.
+        let dimension = |key: &str| {
+            let value = self.get(dict, key).and_then(Obj::as_num)?;
+            (1.0..=MAX_IMAGE_DIMENSION as f64)
+                .contains(&value)
+                .then_some(value as u32)
+        };
+        let width = dimension("Width")?;
+        let height = dimension("Height")?;
+        if width as usize * height as usize > MAX_IMAGE_PIXELS {
+            return None;
+        }
+
+        // Decode any generic filters that precede the image codec.
+        let data = if image_filter.is_some() && filters.len() > 1 {
+            let mut generic = dict.clone();
+            let names: Vec<Obj> = filters[..filters.len() - 1]
+                .iter()
+                .map(|(n, _)| Obj::Name(n.to_string()))
+                .collect();
+            generic.insert("Filter".to_string(), Obj::Array(names));
+            generic.remove("DecodeParms");
+            self.decode_stream(&generic, raw, MAX_STREAM_BYTES)?
+        } else {
+            raw.to_vec()
+        };
+        let invert = self
+            .get(dict, "Decode")
+            .and_then(Obj::as_array)
+            .and_then(|d| d.first())
+            .and_then(Obj::as_num)
+            .is_some_and(|first| first >= 1.0);
+
+        let gray = match image_filter {
+            Some("DCTDecode" | "DCT") => {
+                image::load_from_memory_with_format(&data, ImageFormat::Jpeg)
+                    .ok()?
+                    .to_luma8()
+            }
+            Some("CCITTFaxDecode" | "CCF") => {
+                self.decode_ccitt(&data, width, height, image_parms)?
+            }
+            Some(_) => return None,
+            None => self.decode_raw_samples(dict, raw, width, height)?,
+        };
+
+        Some(if invert {
+            let mut gray = gray;
+            image::imageops::invert(&mut gray);
+            gray
+        } else {
+            gray
+        })
+    }
+
+    fn decode_ccitt(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        parms: Option<&Dict>,
+    ) -> Option<GrayImage> {
+        let num = |key: &str, default: f64| {
+            parms
+                .and_then(|p| self.get(p, key))
+                .and_then(Obj::as_num)
+                .unwrap_or(default)
+        };
+        let black_is_1 = parms
+            .and_then(|p| self.get(p, "BlackIs1"))
+            .is_some_and(|v| matches!(v, Obj::Bool(true)));
+
+        // Only Group 4 (K < 0), the encoding used by document scanners.
+        if num("K", 0.0) >= 0.0 {
+            return None;
+        }
+        let columns = num("Columns", width as f64);
+        let rows = num("Rows", height as f64);
+        let range = 1.0..=MAX_IMAGE_DIMENSION as f64;
+        if !range.contains(&columns) || !range.contains(&rows) {
+            return None;
+        }
+        let (columns, rows) = (columns as u16, rows as u16);
+        let capacity = (columns as usize).checked_mul(rows as usize)?;
+        if capacity > MAX_IMAGE_PIXELS {
+            return None;
+        }
+
+        let (black, white) = if black_is_1 { (255, 0) } else { (0, 255) };
+        let mut pixels = Vec::with_capacity(capacity);
+        let mut lines = 0u32;
+        fax::decoder::decode_g4(data.iter().copied(), columns, Some(rows), |transitions| {
+            pixels.extend(fax::decoder::pels(transitions, columns).map(|c| match c {
+                fax::Color::Black => black,
+                fax::Color::White => white,
+            }));
+            lines += 1;
+        })?;
+
+        GrayImage::from_raw(columns as u32, lines, pixels)
+    }
+
+    fn components(&self, color_space: Option<&Obj>) -> Option<ColorSpace> {
+        let Some(color_space) = color_space.map(|cs| self.resolve(cs)) else {
+            return Some(ColorSpace::Device(1));
+        };
+
+        match color_space {
+            Obj::Name(name) => match name.as_str() {
+                "DeviceGray" | "G" | "CalGray" => Some(ColorSpace::Device(1)),
+                "DeviceRGB" | "RGB" | "CalRGB" => Some(ColorSpace::Device(3)),
+                "DeviceCMYK" | "CMYK" => Some(ColorSpace::Device(4)),
+                _ => None,
+            },
+            Obj::Array(items) => match items.first().and_then(Obj::as_name)? {
+                "ICCBased" => {
+                    let profile = self.resolve(items.get(1)?).as_dict()?;
+                    // Gray, RGB and CMYK profiles; anything else is malformed.
+                    match self.get(profile, "N").and_then(Obj::as_num)? {
+                        1.0 => Some(ColorSpace::Device(1)),
+                        3.0 => Some(ColorSpace::Device(3)),
+                        4.0 => Some(ColorSpace::Device(4)),
+                        _ => None,
+                    }
+                }
+                "Indexed" | "I" => {
+                    let ColorSpace::Device(base) = self.components(items.get(1))? else {
+                        return None;
+                    };
+                    let lookup = match self.resolve(items.get(3)?) {
+                        Obj::Str(bytes) => bytes.clone(),
+                        Obj::Stream(dict, raw) => {
+                            self.decode_stream(dict, raw, MAX_LOOKUP_BYTES)?
+                        }
+                        _ => return None,
+                    };
+                    Some(ColorSpace::Indexed { base, lookup })
+                }
+                "CalGray" => Some(ColorSpace::Device(1)),
+                "CalRGB" | "Lab" => Some(ColorSpace::Device(3)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn decode_raw_samples(
+        &self,
+        dict: &Dict,
+        raw: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Option<GrayImage> {
+        let image_mask = matches!(self.get(dict, "ImageMask"), Some(Obj::Bool(true)));
+        let bpc = if image_mask {
+            1
+        } else {
+            self.get(dict, "BitsPerComponent")
+                .and_then(Obj::as_num)
+                .unwrap_or(8.0) as usize
+        };
+        if !matches!(bpc, 1 | 2 | 4 | 8 | 16) {
+            return None;
+        }
+
+        let color_space = if image_mask {
+            ColorSpace::Device(1)
+        } else {
+            self.components(self.get(dict, "ColorSpace"))?
+        };
+        let channels = match &color_space {
+            ColorSpace::Device(n) => *n,
+            ColorSpace::Indexed { .. } => 1,
+        };
+
+        let row_len = (width as usize)
+            .checked_mul(channels)?
+            .checked_mul(bpc)?
+            .div_ceil(8);
+        let pixel_count = (width as usize).checked_mul(height as usize)?;
+        if row_len == 0 || pixel_count > MAX_IMAGE_PIXELS {
+            return None;
+        }
+        // PNG predictors add a filter-type byte to every row.
+        let data = self.decode_stream(dict, raw, (row_len + 1).checked_mul(height as usize)?)?;
+        if data.len() < row_len.checked_mul(height as usize)? {
+            return None;
+        }
+
+        let max = ((1u32 << bpc) - 1) as f32;
+        let mut pixels = Vec::with_capacity(pixel_count);
+        let mut samples = vec![0u8; channels];
+        for row in data.chunks(row_len).take(height as usize) {
+            for x in 0..width as usize {
+                for (c, sample) in samples.iter_mut().enumerate() {
+                    let raw = read_sample(row, (x * channels + c) * bpc, bpc);
+                    *sample = match color_space {
+                        ColorSpace::Indexed { .. } => raw as u8,
+                        ColorSpace::Device(_) => (raw as f32 * 255.0 / max) as u8,
+                    };
+                }
+                pixels.push(color_space.to_gray(&samples));
+            }
+        }
+
+        GrayImage::from_raw(width, height, pixels)
+    }
+}
+
+enum ColorSpace {
+    Device(usize),
+    Indexed { base: usize, lookup: Vec<u8> },
+}
+
+impl ColorSpace {
+    fn to_gray(&self, samples: &[u8]) -> u8 {
+        match self {
+            ColorSpace::Device(n) => device_to_gray(&samples[..*n]),
+            ColorSpace::Indexed { base, lookup } => {
+                let start = samples[0] as usize * base;
+                match lookup.get(start..start + base) {
+                    Some(entry) => device_to_gray(entry),
+                    None => 0,
+                }
+            }
+        }
+    }
+}
+
+fn device_to_gray(samples: &[u8]) -> u8 {
+    match samples {
+        [g] => *g,
+        [r, g, b] => (0.299 * *r as f32 + 0.587 * *g as f32 + 0.114 * *b as f32) as u8,
+        [c, m, y, k] => {
+            let k = *k as f32 / 255.0;
+            let channel = |v: u8| 255.0 * (1.0 - v as f32 / 255.0) * (1.0 - k);
+            (0.299 * channel(*c) + 0.587 * channel(*m) + 0.114 * channel(*y)) as u8
+        }
+        other => other.first().copied().unwrap_or(0),
+    }
+}
+
+fn read_sample(row: &[u8], bit_offset: usize, bpc: usize) -> u16 {
+    match bpc {
+        8 => row.get(bit_offset / 8).copied().unwrap_or(0) as u16,
+        16 => {
+            // Keep the high byte; 8 bits of precision are plenty for detection.
+            (row.get(bit_offset / 8).copied().unwrap_or(0) as u16) << 8
+        }
+        _ => {
+            let byte = row.get(bit_offset / 8).copied().unwrap_or(0);
+            let shift = 8 - bpc - (bit_offset % 8);
+            ((byte >> shift) & ((1u8 << bpc) - 1)) as u16
+        }
+    }
+}
+
+/// Scans the images embedded in one page, returning the first detection.
+fn scan_page(doc: &Document, page: &Page) -> Result<Detection, Error> {
+    let mut images = Vec::new();
+    if let Some(resources) = page.resources {
+        doc.images(resources, 0, &mut images);
+    }
+
+    // Pages whose images all use unsupported codecs (e.g. JPEG 2000) are
+    // reported as such rather than as having no barcode.
+    let mut result = Err(if images.is_empty() {
+        Error::NotDetected
+    } else {
+        Error::UnsupportedFormat
+    });
+    for (dict, data) in images {
+        let Some(gray) = doc.decode_image(dict, data) else {
+            continue;
+        };
+        result = filter_result(detect_from_gray_image(gray));
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+/// A page's 1-based number together with its scan result.
+pub type PageResult = (u32, Result<Detection, Error>);
+
+/// Scans every page in the configured page range.
+pub fn scan_pages(bytes: &[u8]) -> Result<Vec<PageResult>, Error> {
+    let doc = Document::load(bytes)?;
+
+    let opts = PDF_OPTIONS.with(|o| o.get());
+    let pages = doc.pages();
+    if pages.is_empty() {
+        return Err(Error::NotDetected);
+    }
+
+    let first = opts.first_page as usize;
+    let last = opts
+        .last_page
+        .map_or(pages.len(), |l| (l as usize).min(pages.len()));
+    Ok(pages
+        .iter()
+        .enumerate()
+        .take(last)
+        .skip(first - 1)
+        .map(|(i, page)| ((i + 1) as u32, scan_page(&doc, page)))
+        .collect())
+}
+
+/// Scans every page in the configured page range, reporting one `on_detect`
+/// per page tagged with its 1-based `page` number, followed by `on_stop`.
+pub fn scan_pdf_bytes(bytes: &[u8]) {
+    match scan_pages(bytes) {
+        Ok(results) => {
+            for (page, result) in results {
+                invoke_on_detect_tagged(
+                    result.as_ref(),
+                    &[("page", JsValue::from_f64(page as f64))],
+                );
+            }
+        }
+        Err(e) => invoke_on_detect(Err(&e)),
+    }
+
+    invoke_on_stop();
+}
+
+pub fn scan_pdf_blob(blob: &Blob) {
+    let promise = blob.array_buffer();
+    spawn_local(async move {
+        match JsFuture::from(promise).await {
+            Ok(buffer) => scan_pdf_bytes(&Uint8Array::new(&buffer).to_vec()),
+            Err(_) => {
+                invoke_on_detect(Err(&Error::FileReadFailed));
+                invoke_on_stop();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use fax::{Color, VecWriter, encoder::Encoder};
+    use flate2::{Compression, write::ZlibEncoder};
+
+    use super::*;
+    use crate::detector::tests::qr_image;
+
+    /// Builds a PDF from numbered object bodies, with an xref table.
+    fn pdf(objects: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut out = b"%PDF-1.7\n".to_vec();
+        let mut offsets = Vec::new();
+        for (num, body) in objects {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{num} 0 obj\n").as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        out.extend_from_slice(format!("xref\n0 {}\n", offsets.len() + 1).as_bytes());
+        out.extend_from_slice(b"0000000000 65535 f \n");
+        for offset in offsets {
+            out.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+        }
+        out.extend_from_slice(
+            format!("trailer\n<< /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n").as_bytes(),
+        );
+        out
+    }
+
+    fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut out = format!("<< {dict} /Length {} >>\nstream\n", data.len()).into_bytes();
+        out.extend_from_slice(data);
+        out.extend_from_slice(b"\nendstream");
+        out
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// A catalog and page tree with one page per image XObject body.
+    fn image_pages(images: &[Vec<u8>]) -> Vec<u8> {
+        let first_page = 3;
+        let first_image = first_page + images.len() as u32;
+        let kids: Vec<String> = (0..images.len() as u32)
+            .map(|i| format!("{} 0 R", first_page + i))
+            .collect();
+
+        let mut objects = vec![
+            (1, b"<< /Type /Catalog /Pages 2 0 R >>".to_vec()),
+            (
+                2,
+                format!(
+                    "<< /Type /Pages /Kids [{}] /Count {} >>",
+                    kids.join(" "),
+                    images.len()
+                )
+                .into_bytes(),
+            ),
+        ];
+        for i in 0..images.len() as u32 {
+            objects.push((
+                first_page + i,
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /Resources << /XObject << /Im0 {} 0 R >> >> >>",
+                    first_image + i
+                )
+                .into_bytes(),
+            ));
+        }
+        for (i, image) in images.iter().enumerate() {
+            objects.push((first_image + i as u32, image.clone()));
+        }
+        pdf(&objects)
+    }
+
+    /// Decodes the image XObject on the first page of `bytes`.
+    fn first_image(bytes: &[u8]) -> Option<GrayImage> {
+        let doc = Document::load(bytes).ok()?;
+        let pages = doc.pages();
+        let mut images = Vec::new();
+        doc.images(pages.first()?.resources?, 0, &mut images);
+        let (dict, data) = images.first()?;
+        doc.decode_image(dict, data)
+    }
+
+    fn image(dict: &str, data: &[u8]) -> Vec<u8> {
+        stream(&format!("/Type /XObject /Subtype /Image {dict}"), data)
+    }
+
+    #[test]
+    fn parses_direct_objects() {
+        let source =
+            b"<< /A#20B (x\\(y\\)\\101\\n) /H <48 69 7> /Arr [1 2 0 R -3.5 true null] /R 12 0 R >>";
+        let Some(Obj::Dict(dict)) = Parser::new(source, 0).parse() else {
+            panic!("not a dictionary");
+        };
+
+        assert!(matches!(&dict["A B"], Obj::Str(s) if s == b"x(y)A\n"));
+        assert!(matches!(&dict["H"], Obj::Str(s) if s == b"Hip"));
+        assert!(matches!(&dict["R"], Obj::Ref(12)));
+        let Obj::Array(items) = &dict["Arr"] else {
+            panic!("not an array");
+        };
+        assert!(matches!(
+            items.as_slice(),
+            [Obj::Num(a), Obj::Ref(2), Obj::Num(b), Obj::Bool(true), Obj::Null]
+                if *a == 1.0 && *b == -3.5
+        ));
+    }
+
+    #[test]
+    fn loads_objects_without_a_usable_xref() {
+        let mut bytes = pdf(&[
+            (1, b"<< /Type /Catalog /Pages 2 0 R >>".to_vec()),
+            (2, b"<< /Type /Pages /Kids [] /Count 0 >>".to_vec()),
+        ]);
+        // An update appended without its own xref section redefines object
+        // 2, so the table only lists the stale definition.
+        bytes.extend_from_slice(b"2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 1 >>\nendobj\n");
+        bytes.extend_from_slice(b"3 0 obj\n<< /Type /Page /Parent 2 0 R >>\nendobj\n");
+
+        let doc = Document::load(&bytes).unwrap();
+        assert_eq!(doc.objects.len(), 3);
+        assert_eq!(doc.pages().len(), 1);
+    }
+
+    #[test]
+    fn expands_compressed_object_streams() {
+        let catalog = "<< /Type /Catalog /Pages 2 0 R >> ";
+        let header = format!("1 0 2 {} ", catalog.len());
+        let body = format!("{header}{catalog}<< /Type /Pages /Kids [3 0 R] >>");
+        let first = header.len();
+        let bytes = pdf(&[
+            (
+                5,
+                stream(
+                    &format!("/Type /ObjStm /N 2 /First {first} /Filter /FlateDecode"),
+                    &deflate(body.as_bytes()),
+                ),
+            ),
+            (3, b"<< /Type /Page /Parent 2 0 R >>".to_vec()),
+        ]);
+
+        let doc = Document::load(&bytes).unwrap();
+        assert!(doc.objects.contains_key(&1));
+        assert!(doc.objects.contains_key(&2));
+        assert_eq!(doc.pages().len(), 1);
+    }
+
+    #[test]
+    fn decodes_flate_with_png_predictors() {
+        // Row 1 with the Sub filter, row 2 with the Up filter.
+        let encoded = [1, 10, 5, 5, 2, 1, 1, 1];
+        let bytes = image_pages(&[image(
+            "/Width 3 /Height 2 /BitsPerComponent 8 /ColorSpace /DeviceGray \
+             /Filter /FlateDecode /DecodeParms << /Predictor 15 /Columns 3 >>",
+            &deflate(&encoded),
+        )]);
+
+        let gray = first_image(&bytes).unwrap();
+        assert_eq!(gray.as_raw(), &[10, 15, 20, 11, 16, 21]);
+    }
+
+    #[test]
+    fn inflate_stops_at_the_limit() {
+        let zeros = deflate(&[0; 4096]);
+        assert_eq!(inflate(&zeros, 4096).unwrap().len(), 4096);
+        assert!(inflate(&zeros, 4095).is_none());
+    }
+
+    #[test]
+    fn rejects_streams_that_inflate_past_their_dimensions() {
+        let bomb = deflate(&vec![0; 1 << 20]);
+        let bytes = image_pages(&[image(
+            "/Width 4 /Height 4 /BitsPerComponent 8 /ColorSpace /DeviceGray /Filter /FlateDecode",
+            &bomb,
+        )]);
+        assert!(first_image(&bytes).is_none());
+
+        // The same data is fine once the dimensions account for it.
+        let bytes = image_pages(&[image(
+            "/Width 1024 /Height 1024 /BitsPerComponent 8 /ColorSpace /DeviceGray \
+             /Filter /FlateDecode",
+            &bomb,
+        )]);
+        assert!(first_image(&bytes).is_some());
+
+        let lookup = stream("/Filter /FlateDecode", &bomb);
+        let bytes = pdf(&[
+            (1, b"<< /Type /Catalog /Pages 2 0 R >>".to_vec()),
+            (
+                2,
+                b"<< /Type /Pages /Kids [3 0 R] /Resources << /XObject << /Im0 4 0 R >> >> >>"
+                    .to_vec(),
+            ),
+            (3, b"<< /Type /Page /Parent 2 0 R >>".to_vec()),
+            (
+                4,
+                image(
+                    "/Width 1 /Height 1 /BitsPerComponent 8 /ColorSpace [/Indexed /DeviceGray 255 5 0 R]",
+                    &[0],
+                ),
+            ),
+            (5, lookup),
+        ]);
+        assert!(first_image(&bytes).is_none());
+    }
+
+    #[test]
+    fn decodes_raw_samples_in_each_color_space() {
+        let rgb = image(
+            "/Width 2 /Height 1 /BitsPerComponent 8 /ColorSpace /DeviceRGB",
+            &[255, 255, 255, 0, 0, 0],
+        );
+        assert_eq!(
+            first_image(&image_pages(&[rgb])).unwrap().as_raw(),
+            &[255, 0]
+        );
+
+        let mask = image(
+            "/Width 8 /Height 1 /ImageMask true /Decode [1 0]",
+            &[0b1010_0000],
+        );
+        assert_eq!(
+            first_image(&image_pages(&[mask])).unwrap().as_raw(),
+            &[0, 255, 0, 255, 255, 255, 255, 255]
+        );
+
+        let indexed = image(
+            "/Width 3 /Height 1 /BitsPerComponent 8 /ColorSpace [/Indexed /DeviceGray 1 <00FF>]",
+            &[1, 0, 7],
+        );
+        assert_eq!(
+            first_image(&image_pages(&[indexed])).unwrap().as_raw(),
+            &[255, 0, 0]
+        );
+
+        let bytes = pdf(&[
+            (1, b"<< /Type /Catalog /Pages 2 0 R >>".to_vec()),
+            (
+                2,
+                b"<< /Type /Pages /Kids [3 0 R] /Resources << /XObject << /Im0 4 0 R >> >> >>"
+                    .to_vec(),
+            ),
+            (3, b"<< /Type /Page /Parent 2 0 R >>".to_vec()),
+            (
+                4,
+                image(
+                    "/Width 2 /Height 1 /BitsPerComponent 4 /ColorSpace [/ICCBased 5 0 R]",
+                    &[0xf0],
+                ),
+            ),
+            (5, stream("/N 1", b"")),
+        ]);
+        assert_eq!(first_image(&bytes).unwrap().as_raw(), &[255, 0]);
+    }
+
+    #[test]
+    fn decodes_ccitt_group_4() {
+        let (width, height) = (16u16, 4u16);
+        let dark = |x: u16, y: u16| (x / 4 + y).is_multiple_of(2);
+        let mut encoder = Encoder::new(VecWriter::new());
+        for y in 0..height {
+            let line = (0..width).map(|x| {
+                if dark(x, y) {
+                    Color::Black
+                } else {
+                    Color::White
+                }
+            });
+            encoder.encode_line(line, width).unwrap();
+        }
+        let data = encoder.finish().unwrap().finish();
+
+        let bytes = image_pages(&[image(
+            &format!(
+                "/Width {width} /Height {height} /BitsPerComponent 1 /Filter /CCITTFaxDecode \
+                 /DecodeParms << /K -1 /Columns {width} /Rows {height} >>"
+            ),
+            &data,
+        )]);
+
+        let gray = first_image(&bytes).unwrap();
+        assert_eq!((gray.width(), gray.height()), (width as u32, height as u32));
+        for (x, y, pixel) in gray.enumerate_pixels() {
+            assert_eq!(pixel.0[0] == 0, dark(x as u16, y as u16), "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_images_without_panicking() {
+        let cases = [
+            image("/Width 0 /Height 4 /BitsPerComponent 8", &[0; 16]),
+            image("/Width 4 /Height -1 /BitsPerComponent 8", &[0; 16]),
+            image("/Width 4 /Height 4 /BitsPerComponent 8", &[0; 15]),
+            image(
+                "/Width 4294967296 /Height 4294967296 /BitsPerComponent 8",
+                &[0; 16],
+            ),
+            image("/Width 16384 /Height 16384 /BitsPerComponent 8", &[0; 16]),
+            image("/Width 4 /Height 4 /BitsPerComponent 3", &[0; 16]),
+            image("/Width 4 /Height 4 /ColorSpace /Pattern", &[0; 16]),
+            image(
+                "/Width 4 /Height 4 /ColorSpace [/ICCBased << /N 0 >>]",
+                &[0; 16],
+            ),
+            image(
+                "/Width 4 /Height 4 /Filter /FlateDecode \
+                 /DecodeParms << /Predictor 12 /Columns 1e30 >>",
+                &deflate(&[0; 20]),
+            ),
+            image(
+                "/Width 4 /Height 4 /Filter /CCITTFaxDecode /DecodeParms << /K -1 /Columns 0 >>",
+                &[0; 16],
+            ),
+            image("/Width 4 /Height 4 /Filter /FlateDecode", b"not zlib"),
+            image("/Width 4 /Height 4 /Filter /JPXDecode", &[0; 16]),
+        ];
+        for (i, case) in cases.into_iter().enumerate() {
+            assert!(first_image(&image_pages(&[case])).is_none(), "case {i}");
+        }
+    }
+
+    #[test]
+    fn survives_truncated_and_corrupt_documents() {
+        assert!(matches!(
+            Document::load(b"not a pdf"),
+            Err(Error::UnsupportedFormat)
+        ));
+        assert!(Document::load(b"").is_err());
+
+        let bytes = image_pages(&[image(
+            "/Width 2 /Height 1 /Length 18446744073709551615",
+            &[0, 255],
+        )]);
+        for len in 0..bytes.len() {
+            if let Ok(doc) = Document::load(&bytes[..len]) {
+                let _ = doc.pages();
+            }
+        }
+
+        let looped = pdf(&[
+            (1, b"<< /Type /Catalog /Pages 2 0 R >>".to_vec()),
+            (2, b"<< /Type /Pages /Kids [2 0 R] >>".to_vec()),
+            (3, b"4 0 R".to_vec()),
+            (4, b"3 0 R".to_vec()),
+        ]);
+        let doc = Document::load(&looped).unwrap();
+        assert!(doc.pages().is_empty());
+        assert!(matches!(doc.resolve(&Obj::Ref(3)), Obj::Null));
+    }
+
+    #[test]
+    fn scans_each_page_in_range() {
+        let qr = qr_image("wascan", 4);
+        let barcode = image(
+            &format!(
+                "/Width {} /Height {} /BitsPerComponent 8 /ColorSpace /DeviceGray /Filter /FlateDecode",
+                qr.width(),
+                qr.height()
+            ),
+            &deflate(qr.as_raw()),
+        );
+        let blank = image(
+            "/Width 100 /Height 100 /BitsPerComponent 8 /ColorSpace /DeviceGray",
+            &[255; 100 * 100],
+        );
+        let bytes = image_pages(&[barcode, blank]);
+
+        let results = scan_pages(&bytes).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(&results[0], (1, Ok(detection)) if detection.value == "wascan"));
+        assert!(matches!(&results[1], (2, Err(Error::NotDetected))));
+    }
+}
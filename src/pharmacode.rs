@@ -0,0 +1,409 @@
+use std::{cell::Cell, collections::HashMap};
+
+use rxing::{BinaryBitmap, Luma8LuminanceSource, common::HybridBinarizer};
+use wasm_bindgen::JsValue;
+
+use crate::{
+    detector::{Detection, Format},
+    error::Error,
+    options, validator,
+};
+
+/// Bars in the shortest and longest one-track Pharmacodes.
+const MIN_BARS: u32 = 2;
+const MAX_BARS: u32 = 16;
+const MIN_VALUE: u32 = 3;
+const MAX_VALUE: u32 = 131_070;
+
+/// Rows scanned across the middle of the image, and how many of them must
+/// read the same value. Pharmacode has no check digit, so agreement between
+/// rows is what keeps misreads out.
+const SCAN_ROWS: usize = 15;
+const MIN_AGREEING_ROWS: usize = 3;
+
+/// The quiet zone on either side must be at least this many times the mean
+/// space between bars (the specification asks for 6 mm against 1 mm spaces).
+const QUIET_ZONE_SPACES: f32 = 3.0;
+/// Spaces between bars are nominally equal; allowed deviation from the
+/// first space of a symbol.
+const MIN_SPACE_RATIO: f32 = 0.5;
+const MAX_SPACE_RATIO: f32 = 1.75;
+/// Wide bars are nominally three times as wide as narrow bars.
+const MIN_WIDE_NARROW_RATIO: f32 = 1.8;
+/// Allowed deviation of a bar from the mean width of its class.
+const BAR_WIDTH_TOLERANCE: f32 = 0.5;
+
+#[derive(Clone, Copy)]
+struct PharmacodeOptions {
+    /// Read the leftmost bar as the least significant, for codes printed
+    /// upside down relative to the camera.
+    reverse: bool,
+    min_bars: u32,
+}
+
+impl Default for PharmacodeOptions {
+    fn default() -> Self {
+        PharmacodeOptions {
+            reverse: false,
+            min_bars: MIN_BARS,
+        }
+    }
+}
+
+thread_local! {
+    static PHARMACODE_OPTIONS: Cell<PharmacodeOptions> =
+        Cell::new(PharmacodeOptions::default());
+}
+
+pub fn set_pharmacode_options(options: &JsValue) -> Result<(), Error> {
+    let obj = options::as_object(options)?;
+    let defaults = PharmacodeOptions::default();
+
+    let reverse = match options::get_string(&obj, "direction")?.as_deref() {
+        None | Some("forward") => false,
+        Some("reverse") => true,
+        Some(_) => return Err(Error::InvalidOptions),
+    };
+    let min_bars = options::get_u32(&obj, "minBars")?.unwrap_or(defaults.min_bars);
+    if !(MIN_BARS..=MAX_BARS).contains(&min_bars) {
+        return Err(Error::InvalidOptions);
+    }
+
+    PHARMACODE_OPTIONS.with(|opts| opts.set(PharmacodeOptions { reverse, min_bars }));
+    Ok(())
+}
+
+/// A dark or light run of pixels along a row.
+#[derive(Clone, Copy)]
+pub struct Run {
+    pub dark: bool,
+    pub start: usize,
+    pub len: usize,
+}
+
+pub fn runs(bits: impl Iterator<Item = bool>) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for (x, dark) in bits.enumerate() {
+        match runs.last_mut() {
+            Some(run) if run.dark == dark => run.len += 1,
+            _ => runs.push(Run {
+                dark,
+                start: x,
+                len: 1,
+            }),
+        }
+    }
+    runs
+}
+
+/// Classifies bar widths as narrow (`false`) or wide (`true`).
+fn classify(bars: &[usize], mean_space: f32) -> Option<Vec<bool>> {
+    let min = *bars.iter().min()? as f32;
+    let max = *bars.iter().max()? as f32;
+    // With both widths present they set the threshold; otherwise narrow bars
+    // are about half a space wide and wide bars one and a half.
+    let threshold = if max / min >= MIN_WIDE_NARROW_RATIO {
+        (min + max) / 2.0
+    } else {
+        mean_space
+    };
+    let wide: Vec<bool> = bars.iter().map(|w| *w as f32 > threshold).collect();
+
+    let class_mean = |class: bool| {
+        let widths: Vec<f32> = bars
+            .iter()
+            .zip(&wide)
+            .filter(|(_, w)| **w == class)
+            .map(|(b, _)| *b as f32)
+            .collect();
+        (!widths.is_empty()).then(|| widths.iter().sum::<f32>() / widths.len() as f32)
+    };
+    let (narrow_mean, wide_mean) = (class_mean(false), class_mean(true));
+    if let (Some(narrow), Some(wide)) = (narrow_mean, wide_mean)
+        && wide / narrow < MIN_WIDE_NARROW_RATIO
+    {
+        return None;
+    }
+
+    let consistent = bars.iter().zip(&wide).all(|(b, w)| {
+        let mean = if *w { wide_mean } else { narrow_mean };
+        mean.is_some_and(|mean| (*b as f32 - mean).abs() <= mean * BAR_WIDTH_TOLERANCE)
+    });
+    consistent.then_some(wide)
+}
+
+/// Computes the value of a symbol from its bar classes, left to right.
+/// Counting from the least significant bar, a narrow bar at position `i`
+/// adds `2^i` and a wide bar `2^(i+1)`.
+fn value(wide: &[bool], reverse: bool) -> u32 {
+    let weight = |(i, wide): (usize, &bool)| if *wide { 2 << i } else { 1 << i };
+    if reverse {
+        wide.iter().enumerate().map(weight).sum()
+    } else {
+        wide.iter().rev().enumerate().map(weight).sum()
+    }
+}
+
+/// Decodes the Pharmacodes along one row, returning each value with the
+/// horizontal extent of its bars.
+fn decode_row(runs: &[Run], width: usize, opts: PharmacodeOptions) -> Vec<(u32, usize, usize)> {
+    let ends_inside = |run: &Run| run.start + run.len < width;
+    let mut found = Vec::new();
+    let mut first = 1;
+    while first < runs.len() {
+        // A symbol starts at a bar after a light run; a bar cut off by the
+        // edge of the image is not a symbol.
+        if !runs[first].dark || !ends_inside(&runs[first]) {
+            first += 1;
+            continue;
+        }
+
+        // Extend the symbol while the spaces stay close to the first one.
+        let mut last = first;
+        if let Some(reference) = runs.get(first + 1).filter(|run| ends_inside(run)) {
+            while let (Some(space), Some(bar)) = (runs.get(last + 1), runs.get(last + 2)) {
+                let ratio = space.len as f32 / reference.len as f32;
+                if !(MIN_SPACE_RATIO..=MAX_SPACE_RATIO).contains(&ratio) || !ends_inside(bar) {
+                    break;
+                }
+                last += 2;
+            }
+        }
+        let symbol = &runs[first..=last];
+        let (before, after) = (runs[first - 1], runs.get(last + 1));
+        first = last + 1;
+
+        let bars: Vec<usize> = symbol.iter().step_by(2).map(|run| run.len).collect();
+        let count = bars.len() as u32;
+        if count < opts.min_bars || count > MAX_BARS {
+            continue;
+        }
+
+        let spaces = symbol.iter().skip(1).step_by(2).map(|run| run.len);
+        let mean_space = spaces.sum::<usize>() as f32 / (bars.len() - 1) as f32;
+        let quiet = mean_space * QUIET_ZONE_SPACES;
+        if (before.len as f32) < quiet || after.is_none_or(|run| (run.len as f32) < quiet) {
+            continue;
+        }
+
+        let Some(wide) = classify(&bars, mean_space) else {
+            continue;
+        };
+        let value = value(&wide, opts.reverse);
+        if (MIN_VALUE..=MAX_VALUE).contains(&value) {
+            let end = symbol[symbol.len() - 1];
+            found.push((value, symbol[0].start, end.start + end.len));
+        }
+    }
+    found
+}
+
+/// Decodes a one-track Pharmacode with vertical bars.
+pub fn decode(gray_data: &[u8], width: u32, height: u32) -> Option<Detection> {
+    let opts = PHARMACODE_OPTIONS.with(|opts| opts.get());
+    let src = Luma8LuminanceSource::new(gray_data.to_vec(), width, height);
+    let bitmap = BinaryBitmap::new(HybridBinarizer::new(src));
+    let (width, height) = (width as usize, height as usize);
+
+    let mut votes: HashMap<u32, (usize, Vec<(f32, f32)>)> = HashMap::new();
+    for row in 1..=SCAN_ROWS {
+        let y = height * row / (SCAN_ROWS + 1);
+        let Ok(bits) = bitmap.get_black_row(y) else {
+            continue;
+        };
+        let runs = runs((0..width).map(|x| bits.get(x)));
+        for (value, start, end) in decode_row(&runs, width, opts) {
+            let vote = votes
+                .entry(value)
+                .or_insert_with(|| (0, vec![(start as f32, y as f32), (end as f32, y as f32)]));
+            vote.0 += 1;
+        }
+    }
+
+    let (value, (_, points)) = votes
+        .into_iter()
+        .filter(|(_, (count, _))| *count >= MIN_AGREEING_ROWS)
+        .max_by_key(|(value, (count, _))| (*count, *value))?;
+
+    validator::validate(&Format::Pharmacode, &value.to_string()).map(|value| Detection {
+        value,
+        format: Format::Pharmacode,
+        points,
+        structured_append: None,
+        linked_composite: false,
+        add_on: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    /// The bar classes of `value`, left to right.
+    fn encode(mut value: u32) -> Vec<bool> {
+        let mut wide = Vec::new();
+        while value > 0 {
+            let is_wide = value.is_multiple_of(2);
+            wide.push(is_wide);
+            value = (value - if is_wide { 2 } else { 1 }) / 2;
+        }
+        wide.reverse();
+        wide
+    }
+
+    /// Element widths in modules of half the narrow bar: narrow bars 1, wide
+    /// bars 3 and spaces 2, as in the 0.5/1.5/1 mm specification.
+    fn widths(wide: &[bool]) -> Vec<usize> {
+        let mut widths = Vec::new();
+        for (i, is_wide) in wide.iter().enumerate() {
+            if i > 0 {
+                widths.push(2);
+            }
+            widths.push(if *is_wide { 3 } else { 1 });
+        }
+        widths
+    }
+
+    /// Renders a symbol with `quiet` modules of quiet zone on either side.
+    fn render(widths: &[usize], module: u32, quiet: u32) -> GrayImage {
+        let modules = widths.iter().sum::<usize>() as u32;
+        let dark: Vec<bool> = widths
+            .iter()
+            .enumerate()
+            .flat_map(|(i, w)| std::iter::repeat_n(i % 2 == 0, *w))
+            .collect();
+        GrayImage::from_fn((modules + 2 * quiet) * module, 40, |x, _| {
+            let dark = (x / module)
+                .checked_sub(quiet)
+                .is_some_and(|m| dark.get(m as usize) == Some(&true));
+            Luma([if dark { 0 } else { 255 }])
+        })
+    }
+
+    fn read(gray: &GrayImage) -> Option<String> {
+        decode(gray.as_raw(), gray.width(), gray.height()).map(|d| d.value)
+    }
+
+    fn top_row(gray: &GrayImage) -> Vec<bool> {
+        (0..gray.width())
+            .map(|x| gray.get_pixel(x, 0).0[0] == 0)
+            .collect()
+    }
+
+    fn set_options(opts: PharmacodeOptions) {
+        PHARMACODE_OPTIONS.with(|slot| slot.set(opts));
+    }
+
+    #[test]
+    fn values_weigh_bars_from_the_right() {
+        assert_eq!(value(&[false, false], false), 3);
+        assert_eq!(value(&[true, true], false), 6);
+        assert_eq!(value(&[true, false], false), 5);
+        assert_eq!(value(&[true, false], true), 4);
+        assert_eq!(value(&[true; 16], false), MAX_VALUE);
+        for n in [3, 4, 14, 15, 91, 1234, 65535, MAX_VALUE] {
+            let wide = encode(n);
+            assert_eq!(value(&wide, false), n);
+            let reversed: Vec<bool> = wide.iter().rev().copied().collect();
+            assert_eq!(value(&reversed, true), n);
+        }
+    }
+
+    #[test]
+    fn bars_are_classified_by_width() {
+        assert_eq!(
+            classify(&[2, 6, 2, 6], 4.0),
+            Some(vec![false, true, false, true])
+        );
+        // A single class is placed against the spaces.
+        assert_eq!(classify(&[2, 2, 3], 4.0), Some(vec![false; 3]));
+        assert_eq!(classify(&[6, 6, 5], 4.0), Some(vec![true; 3]));
+        // Wide bars too close to narrow ones, or widths all over the place.
+        assert_eq!(classify(&[4, 6], 5.0), None);
+        assert_eq!(classify(&[2, 7, 12], 4.0), None);
+    }
+
+    #[test]
+    fn decodes_rendered_symbols() {
+        for module in [2, 3, 5] {
+            for n in [3, 4, 14, 15, 91, 1234, 65535, MAX_VALUE] {
+                let gray = render(&widths(&encode(n)), module, 12);
+                assert_eq!(read(&gray), Some(n.to_string()), "{n} at {module}px");
+            }
+        }
+    }
+
+    #[test]
+    fn reverse_reads_symbols_seen_upside_down() {
+        let mut wide = encode(1234);
+        wide.reverse();
+        let gray = render(&widths(&wide), 3, 12);
+        assert_ne!(read(&gray), Some("1234".into()));
+
+        set_options(PharmacodeOptions {
+            reverse: true,
+            ..PharmacodeOptions::default()
+        });
+        assert_eq!(read(&gray), Some("1234".into()));
+    }
+
+    #[test]
+    fn min_bars_rejects_shorter_symbols() {
+        // 14 has three bars, 15 four.
+        let (short, long) = (
+            render(&widths(&encode(14)), 3, 12),
+            render(&widths(&encode(15)), 3, 12),
+        );
+        assert_eq!(read(&short), Some("14".into()));
+
+        set_options(PharmacodeOptions {
+            min_bars: 4,
+            ..PharmacodeOptions::default()
+        });
+        assert_eq!(read(&short), None);
+        assert_eq!(read(&long), Some("15".into()));
+    }
+
+    #[test]
+    fn requires_a_quiet_zone() {
+        let symbol = widths(&encode(1234));
+        // Three spaces of two modules each are the minimum.
+        assert_eq!(read(&render(&symbol, 3, 6)), Some("1234".into()));
+        assert_eq!(read(&render(&symbol, 3, 4)), None);
+
+        // A bar next to the symbol breaks the quiet zone on that side.
+        let mut crowded = vec![1, 5];
+        crowded.extend(&symbol);
+        let row = top_row(&render(&crowded, 1, 12));
+        assert!(
+            decode_row(
+                &runs(row.iter().copied()),
+                row.len(),
+                PharmacodeOptions::default()
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn bars_cut_off_by_the_edge_are_not_symbols() {
+        let symbol = widths(&encode(1234));
+        let row = top_row(&render(&symbol, 1, 12));
+        let width = 12 + symbol.iter().sum::<usize>() - 1;
+        let cut = runs(row[..width].iter().copied());
+        assert!(decode_row(&cut, width, PharmacodeOptions::default()).is_empty());
+        assert_eq!(
+            decode_row(
+                &runs(row.iter().copied()),
+                row.len(),
+                PharmacodeOptions::default()
+            )
+            .iter()
+            .map(|(value, _, _)| *value)
+            .collect::<Vec<_>>(),
+            [1234]
+        );
+    }
+}
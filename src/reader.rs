@@ -1,15 +1,25 @@
-use std::cell::RefCell;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
 
-use wasm_bindgen::{JsCast, JsValue};
-use web_sys::{Document, Event, HtmlInputElement};
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue, convert::FromWasmAbi, prelude::Closure};
+use web_sys::{
+    Blob, ClipboardEvent, Document, DragEvent, Element, Event, EventTarget, File, FileList,
+    HtmlCanvasElement, HtmlImageElement, HtmlInputElement, ImageData,
+};
 
 use crate::{
-    detector::detect_from_image,
+    batch,
+    detector::{Detection, canvas_context, detect_from_image, detect_from_rgba, emit_image_result},
     error::Error,
-    event::{invoke_on_detect, invoke_on_start, invoke_on_stop},
+    event::{invoke_on_detect, invoke_on_drop_hover, invoke_on_start, invoke_on_stop},
+    pdf,
 };
 
 const HIDDEN_FILE_INPUT_ID: &str = "wascan-file-input";
+const UNTYPED_IMAGE_EXTENSIONS: [&str; 3] = [".heic", ".heif", ".avif"];
 
 thread_local! {
     static DOCUMENT_REF: RefCell<Option<Document>> = const { RefCell::new(None) };
@@ -21,6 +31,53 @@ fn handle_detection_error(error: Error) {
     invoke_on_stop();
 }
 
+fn handle_files(files: Option<FileList>) {
+    if batch::batch_enabled()
+        && let Some(files) = files.as_ref().filter(|files| files.length() > 0)
+    {
+        batch::scan_files(files);
+        return;
+    }
+
+    let Some(file) = files.and_then(|files| files.get(0)) else {
+        handle_detection_error(Error::NoFileSelected);
+        return;
+    };
+
+    if file.type_() == pdf::PDF_MIME {
+        pdf::scan_pdf_blob(&file);
+        return;
+    }
+
+    if !is_image_file(&file) {
+        handle_detection_error(Error::InvalidMime);
+        return;
+    }
+
+    detect_from_image(&file);
+}
+
+/// Returns true for image files, including HEIC/HEIF/AVIF photos for which
+/// some browsers report an empty MIME type.
+pub fn is_image_file(file: &File) -> bool {
+    let mime = file.type_();
+    if mime.starts_with("image/") {
+        return true;
+    }
+
+    let name = file.name().to_ascii_lowercase();
+    mime.is_empty()
+        && UNTYPED_IMAGE_EXTENSIONS
+            .iter()
+            .any(|ext| name.ends_with(ext))
+}
+
+fn first_image_file(files: &FileList) -> Option<File> {
+    (0..files.length())
+        .filter_map(|i| files.get(i))
+        .find(is_image_file)
+}
+
 pub fn init_reader() -> Result<(), JsValue> {
     let window = web_sys::window().ok_or_else(|| JsValue::from(Error::WindowNotFound))?;
     let document = window
@@ -49,15 +106,23 @@ fn ensure_file_input(doc: &Document) -> Result<(), JsValue> {
     let input = doc.create_element("input")?;
     input.set_attribute("type", "file")?;
     input.set_attribute("id", HIDDEN_FILE_INPUT_ID)?;
-    input.set_attribute("accept", "image/*")?;
+    let accept = format!(
+        "image/*,{},{}",
+        UNTYPED_IMAGE_EXTENSIONS.join(","),
+        pdf::PDF_MIME
+    );
+    input.set_attribute("accept", &accept)?;
     input.set_attribute("style", "display: none;")?;
+    if batch::batch_enabled() {
+        input.set_attribute("multiple", "")?;
+    }
 
     if let Some(body) = doc.body() {
         body.append_child(&input)?;
     }
 
     let document_for_change = doc.clone();
-    let change_closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |_evt: Event| {
+    let change_closure = Closure::wrap(Box::new(move |_evt: Event| {
         invoke_on_start();
 
         let Some(input_el) = document_for_change.get_element_by_id(HIDDEN_FILE_INPUT_ID) else {
@@ -70,22 +135,7 @@ fn ensure_file_input(doc: &Document) -> Result<(), JsValue> {
             return;
         };
 
-        let Some(files) = input_html.files() else {
-            handle_detection_error(Error::NoFileSelected);
-            return;
-        };
-
-        let Some(file) = files.get(0) else {
-            handle_detection_error(Error::NoFileSelected);
-            return;
-        };
-
-        if !file.type_().starts_with("image/") {
-            handle_detection_error(Error::InvalidMime);
-            return;
-        }
-
-        detect_from_image(file);
+        handle_files(input_html.files());
     }) as Box<dyn FnMut(_)>);
 
     if let Some(file_input) = doc.get_element_by_id(HIDDEN_FILE_INPUT_ID) {
@@ -97,6 +147,25 @@ fn ensure_file_input(doc: &Document) -> Result<(), JsValue> {
     Ok(())
 }
 
+/// Toggles the `multiple` attribute of the hidden file input, if it exists.
+pub fn set_multiple(enabled: bool) {
+    DOCUMENT_REF.with(|doc_ref| {
+        let Some(input) = doc_ref
+            .borrow()
+            .as_ref()
+            .and_then(|doc| doc.get_element_by_id(HIDDEN_FILE_INPUT_ID))
+        else {
+            return;
+        };
+
+        if enabled {
+            input.set_attribute("multiple", "").ok();
+        } else {
+            input.remove_attribute("multiple").ok();
+        }
+    });
+}
+
 pub fn read_from_image() -> Result<(), JsValue> {
     if !FILE_INPUT_READY.with(|ready| *ready.borrow()) {
         init_reader()?;
@@ -118,3 +187,149 @@ pub fn read_from_image() -> Result<(), JsValue> {
         }
     })
 }
+
+fn detect_from_image_data(image_data: &ImageData) -> Result<Detection, Error> {
+    detect_from_rgba(&image_data.data(), image_data.width(), image_data.height())
+}
+
+fn detect_from_canvas(canvas: &HtmlCanvasElement) -> Result<Detection, Error> {
+    let ctx = canvas_context(canvas)?;
+    let image_data = ctx
+        .get_image_data(0.0, 0.0, canvas.width() as f64, canvas.height() as f64)
+        .map_err(|_| Error::Internal)?;
+
+    detect_from_image_data(&image_data)
+}
+
+fn detect_from_image_element(img: &HtmlImageElement) -> Result<Detection, Error> {
+    if !img.complete() || img.natural_width() == 0 || img.natural_height() == 0 {
+        return Err(Error::ImageDecodeFailed);
+    }
+
+    let document = img.owner_document().ok_or(Error::DocumentNotFound)?;
+    let canvas: HtmlCanvasElement = document
+        .create_element("canvas")
+        .ok()
+        .and_then(|el| el.dyn_into().ok())
+        .ok_or(Error::Internal)?;
+    canvas.set_width(img.natural_width());
+    canvas.set_height(img.natural_height());
+
+    canvas_context(&canvas)?
+        .draw_image_with_html_image_element(img, 0.0, 0.0)
+        .map_err(|_| Error::Internal)?;
+
+    detect_from_canvas(&canvas)
+}
+
+pub fn scan_bytes(bytes: &Uint8Array) {
+    invoke_on_start();
+
+    let parts = Array::of1(bytes);
+    match Blob::new_with_u8_array_sequence(&parts) {
+        Ok(blob) => detect_from_image(&blob),
+        Err(_) => handle_detection_error(Error::Internal),
+    }
+}
+
+pub fn scan_blob(blob: &Blob) {
+    invoke_on_start();
+    detect_from_image(blob);
+}
+
+pub fn scan_image_data(image_data: &ImageData) {
+    invoke_on_start();
+    emit_image_result(detect_from_image_data(image_data));
+}
+
+pub fn scan_canvas(canvas: &HtmlCanvasElement) {
+    invoke_on_start();
+    emit_image_result(detect_from_canvas(canvas));
+}
+
+pub fn scan_image_element(img: &HtmlImageElement) {
+    invoke_on_start();
+    emit_image_result(detect_from_image_element(img));
+}
+
+fn add_listener<E: FromWasmAbi + 'static>(
+    target: &EventTarget,
+    event: &str,
+    handler: impl FnMut(E) + 'static,
+) -> Result<(), JsValue> {
+    let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(E)>);
+    target.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())?;
+    closure.forget();
+
+    Ok(())
+}
+
+pub fn bind_file_input(input: &HtmlInputElement) -> Result<(), JsValue> {
+    let input_for_change = input.clone();
+    add_listener(input, "change", move |_evt: Event| {
+        invoke_on_start();
+        handle_files(input_for_change.files());
+    })
+}
+
+pub fn bind_drop_zone(element: &Element) -> Result<(), JsValue> {
+    // `dragenter`/`dragleave` also fire when moving over child elements, so
+    // hover state is tracked with a nesting depth rather than a flag.
+    let depth = Rc::new(Cell::new(0u32));
+
+    let depth_enter = depth.clone();
+    add_listener(element, "dragenter", move |evt: DragEvent| {
+        evt.prevent_default();
+        depth_enter.set(depth_enter.get() + 1);
+        if depth_enter.get() == 1 {
+            invoke_on_drop_hover(true);
+        }
+    })?;
+
+    add_listener(element, "dragover", move |evt: DragEvent| {
+        evt.prevent_default();
+        if let Some(transfer) = evt.data_transfer() {
+            transfer.set_drop_effect("copy");
+        }
+    })?;
+
+    let depth_leave = depth.clone();
+    add_listener(element, "dragleave", move |_evt: DragEvent| {
+        depth_leave.set(depth_leave.get().saturating_sub(1));
+        if depth_leave.get() == 0 {
+            invoke_on_drop_hover(false);
+        }
+    })?;
+
+    add_listener(element, "drop", move |evt: DragEvent| {
+        evt.prevent_default();
+        if depth.replace(0) > 0 {
+            invoke_on_drop_hover(false);
+        }
+
+        invoke_on_start();
+        handle_files(evt.data_transfer().and_then(|t| t.files()));
+    })
+}
+
+pub fn enable_paste_scan(target: &EventTarget) -> Result<(), JsValue> {
+    add_listener(target, "paste", move |evt: ClipboardEvent| {
+        // Pasting text is left to the page; only clipboard images are scanned.
+        let Some(file) = evt
+            .clipboard_data()
+            .and_then(|data| data.files())
+            .and_then(|files| first_image_file(&files))
+        else {
+            return;
+        };
+
+        evt.prevent_default();
+        invoke_on_start();
+        detect_from_image(&file);
+    })
+}
+
+pub fn scan_pdf(bytes: &Uint8Array) {
+    invoke_on_start();
+    pdf::scan_pdf_bytes(&bytes.to_vec());
+}
@@ -10,9 +10,16 @@ use web_sys::{
 };
 
 use crate::{
-    detector::detect_from_stream,
+    backend::{self, StreamMode},
+    detector::{Detection, Format, detect_from_stream, rgba_to_gray, scan_formats},
     error::Error,
-    event::{invoke_on_detect, invoke_on_start, invoke_on_stop},
+    event::{
+        invoke_on_detect, invoke_on_detect_error, invoke_on_detect_tagged, invoke_on_start,
+        invoke_on_stop,
+    },
+    feedback, overlay, stats,
+    structured_append::{self, Assembly},
+    validator,
 };
 
 thread_local! {
@@ -21,6 +28,7 @@ thread_local! {
     static RUNNING_FLAG: RefCell<Option<Rc<Cell<bool>>>> = const { RefCell::new(None) };
     static VIDEO_ELEMENT_ID: RefCell<Option<String>> = const { RefCell::new(None) };
     static LAST_DETECTED_CODE: RefCell<Option<String>> = const { RefCell::new(None) };
+    static LAST_REJECTED_CODE: RefCell<Option<String>> = const { RefCell::new(None) };
     static DETECTION_COUNT: Cell<u32> = const { Cell::new(0) };
 }
 
@@ -31,6 +39,107 @@ fn handle_detection_error(error: Error) {
     invoke_on_stop();
 }
 
+fn handle_detection_failure(error: Error, cause: &JsValue) {
+    invoke_on_detect_error(&error, cause);
+    invoke_on_stop();
+}
+
+/// Reads the canvas pixels as grayscale.
+fn capture_gray(ctx: &CanvasRenderingContext2d, width: u32, height: u32) -> Option<Vec<u8>> {
+    let image_data = ctx
+        .get_image_data(0.0, 0.0, width as f64, height as f64)
+        .ok()?;
+
+    Some(rgba_to_gray(&image_data.data()))
+}
+
+fn rxing_frame(
+    ctx: &CanvasRenderingContext2d,
+    width: u32,
+    height: u32,
+    formats: &[Format],
+) -> Result<Detection, Error> {
+    let gray = capture_gray(ctx, width, height).ok_or(Error::Internal)?;
+
+    detect_from_stream(gray, width, height, formats)
+}
+
+/// Buffers structured append symbols read from the stream, reporting the
+/// concatenated message once the sequence is complete. Each symbol has
+/// already passed its own error correction and is only buffered once, so
+/// the consecutive-detection check is not applied.
+fn handle_structured_append(detection: Detection, tags: &[(&str, JsValue)]) {
+    overlay::show_detection(&detection);
+
+    match structured_append::collect(detection, Date::now()) {
+        Ok(Assembly::Complete(message)) if validator::passes_filter(&message) => {
+            stats::record_detection();
+            feedback::success();
+            invoke_on_detect_tagged(Ok(&message), tags);
+        }
+        Ok(Assembly::Complete(_)) | Err(Error::Rejected) => feedback::rejected(),
+        Err(error) => invoke_on_detect_tagged(Err(&error), tags),
+        Ok(_) => {}
+    }
+}
+
+/// Applies the filter and the consecutive-detection check to a frame result,
+/// reporting confirmed detections through `on_detect`.
+fn handle_stream_result(result: Result<Detection, Error>, tags: &[(&str, JsValue)]) {
+    let result = match result {
+        Ok(detection) if detection.structured_append.is_some() && structured_append::enabled() => {
+            handle_structured_append(detection, tags);
+            return;
+        }
+        other => other,
+    };
+
+    let detection = match result {
+        Ok(detection) if !validator::passes_filter(&detection) => {
+            // Signal each rejected value once rather than on every frame.
+            let is_new = LAST_REJECTED_CODE.with(|code| {
+                code.replace(Some(detection.value.clone())).as_ref() != Some(&detection.value)
+            });
+            if is_new {
+                feedback::rejected();
+            }
+            None
+        }
+        other => other.ok(),
+    };
+
+    let Some(detection) = detection else {
+        return;
+    };
+
+    overlay::show_detection(&detection);
+    let text = detection.value.clone();
+    let last_code = LAST_DETECTED_CODE.with(|code| code.borrow().clone());
+
+    if let Some(ref last) = last_code {
+        if last == &text {
+            let count = DETECTION_COUNT.with(|c| c.get()) + 1;
+            DETECTION_COUNT.with(|c| c.set(count));
+            if count >= REQUIRED_CONSECUTIVE_DETECTIONS {
+                stats::record_detection();
+                feedback::success();
+                invoke_on_detect_tagged(Ok(&detection), tags);
+                DETECTION_COUNT.with(|c| c.set(0));
+            }
+        } else {
+            LAST_DETECTED_CODE.with(|code| {
+                *code.borrow_mut() = Some(text.clone());
+            });
+            DETECTION_COUNT.with(|c| c.set(1));
+        }
+    } else {
+        LAST_DETECTED_CODE.with(|code| {
+            *code.borrow_mut() = Some(text.clone());
+        });
+        DETECTION_COUNT.with(|c| c.set(1));
+    }
+}
+
 fn now_millis() -> u64 {
     Date::now() as u64
 }
@@ -81,13 +190,22 @@ pub fn start_stream_scan(video_element_id: &str) -> Result<(), JsValue> {
             return;
         };
 
+        // Browsers only expose the camera to secure (HTTPS or localhost) pages.
+        if !window.is_secure_context() {
+            STREAMING.with(|s| s.set(false));
+
+            handle_detection_error(Error::InsecureContext);
+
+            return;
+        }
+
         let navigator = window.navigator();
         let media_devices = match navigator.media_devices() {
             Ok(md) => md,
-            Err(_) => {
+            Err(err) => {
                 STREAMING.with(|s| s.set(false));
 
-                handle_detection_error(Error::NoMedia);
+                handle_detection_failure(Error::NoMedia, &err);
 
                 return;
             }
@@ -162,10 +280,10 @@ pub fn start_stream_scan(video_element_id: &str) -> Result<(), JsValue> {
 
         let g_um = match media_devices.get_user_media_with_constraints(&constraints) {
             Ok(s) => s,
-            Err(_) => {
+            Err(err) => {
                 STREAMING.with(|s| s.set(false));
 
-                handle_detection_error(Error::NoMedia);
+                handle_detection_failure(Error::NoMedia, &err);
 
                 return;
             }
@@ -181,10 +299,15 @@ pub fn start_stream_scan(video_element_id: &str) -> Result<(), JsValue> {
                     .and_then(|name_val| name_val.as_string());
                 let error_type = match err_name.as_deref() {
                     Some("NotAllowedError") | Some("PermissionDeniedError") => Error::NoPermission,
+                    Some("NotReadableError") | Some("TrackStartError") => Error::CameraInUse,
+                    Some("OverconstrainedError") | Some("ConstraintNotSatisfiedError") => {
+                        Error::Overconstrained
+                    }
+                    Some("SecurityError") => Error::InsecureContext,
                     _ => Error::NoMedia,
                 };
 
-                handle_detection_error(error_type);
+                handle_detection_failure(error_type, &err);
 
                 return;
             }
@@ -286,6 +409,7 @@ pub fn start_stream_scan(video_element_id: &str) -> Result<(), JsValue> {
         let raf_cb2 = raf_cb.clone();
         let running_clone = running.clone();
         let last_scan_ms_clone = last_scan_ms.clone();
+        let native_busy = Rc::new(Cell::new(false));
 
         *raf_cb.borrow_mut() = Some(Closure::new(move |_ts: f64| {
             if !running_clone.get() {
@@ -296,6 +420,8 @@ pub fn start_stream_scan(video_element_id: &str) -> Result<(), JsValue> {
                 return;
             };
 
+            overlay::render(&video_for_raf);
+
             let now_ms = now_millis();
             if now_ms.saturating_sub(last_scan_ms_clone.get()) < 100 {
                 if let Some(cb) = raf_cb2.borrow().as_ref() {
@@ -321,60 +447,92 @@ pub fn start_stream_scan(video_element_id: &str) -> Result<(), JsValue> {
                 return;
             }
 
+            let mode = backend::stream_mode();
+            if mode != StreamMode::Rxing && native_busy.get() {
+                // The previous frame is still being decoded by the browser.
+                if let Some(cb) = raf_cb2.borrow().as_ref() {
+                    window
+                        .request_animation_frame(cb.as_ref().unchecked_ref())
+                        .ok();
+                }
+
+                return;
+            }
+
             canvas.set_width(vw);
             canvas.set_height(vh);
 
             ctx.draw_image_with_html_video_element(&video_for_raf, 0.0, 0.0)
                 .ok();
-            let image_data = match ctx.get_image_data(0.0, 0.0, vw as f64, vh as f64) {
-                Ok(d) => d,
-                Err(_) => {
-                    if let Some(cb) = raf_cb2.borrow().as_ref() {
-                        window
-                            .request_animation_frame(cb.as_ref().unchecked_ref())
-                            .ok();
-                    }
 
-                    return;
+            if mode == StreamMode::Rxing {
+                if let Some(gray) = capture_gray(&ctx, vw, vh) {
+                    let decode_started = stats::now_precise();
+                    let result = detect_from_stream(gray, vw, vh, &scan_formats());
+                    stats::record_frame(
+                        vw,
+                        vh,
+                        stats::now_precise() - decode_started,
+                        result.is_ok(),
+                    );
+
+                    let tags = if backend::tags_results() {
+                        vec![("backend", JsValue::from_str("rxing"))]
+                    } else {
+                        Vec::new()
+                    };
+                    handle_stream_result(result, &tags);
                 }
-            };
-
-            let data = image_data.data();
-            let bytes: Vec<u8> = data.0;
-            let mut gray = vec![0u8; (vw * vh) as usize];
-            for (dst, px) in gray.iter_mut().zip(bytes.chunks_exact(4)) {
-                let r = px[0] as f32;
-                let g = px[1] as f32;
-                let b = px[2] as f32;
-                let y = 0.299 * r + 0.587 * g + 0.114 * b;
-                *dst = y as u8;
-            }
-
-            if let Ok(text) = detect_from_stream(gray, vw, vh) {
-                let last_code = LAST_DETECTED_CODE.with(|code| code.borrow().clone());
-
-                if let Some(ref last) = last_code {
-                    if last == &text {
-                        let count = DETECTION_COUNT.with(|c| c.get()) + 1;
-                        DETECTION_COUNT.with(|c| c.set(count));
-                        if count >= REQUIRED_CONSECUTIVE_DETECTIONS {
-                            invoke_on_detect(Ok(&text));
-                            DETECTION_COUNT.with(|c| c.set(0));
-                        }
+            } else {
+                native_busy.set(true);
+                let busy = native_busy.clone();
+                let running = running_clone.clone();
+                let source: JsValue = canvas.clone().into();
+                let ctx = ctx.clone();
+
+                spawn_local(async move {
+                    let decode_started = stats::now_precise();
+                    let native = backend::detect_native(&source, vw, vh).await;
+                    let (result, tags) = if mode == StreamMode::Compare {
+                        backend::compare(native, rxing_frame(&ctx, vw, vh, &scan_formats()))
                     } else {
-                        LAST_DETECTED_CODE.with(|code| {
-                            *code.borrow_mut() = Some(text.clone());
-                        });
-                        DETECTION_COUNT.with(|c| c.set(1));
+                        // Formats and features the native detector lacks are
+                        // left to rxing, on the same frame.
+                        let fallback = backend::rxing_fallback_formats();
+                        match native {
+                            Err(_) if !fallback.is_empty() => (
+                                rxing_frame(&ctx, vw, vh, &fallback),
+                                vec![("backend", JsValue::from_str("rxing"))],
+                            ),
+                            Ok(native) if backend::needs_rxing_confirmation(&native) => {
+                                match rxing_frame(&ctx, vw, vh, &[native.format]) {
+                                    Ok(rxing) if rxing.structured_append.is_some() => {
+                                        (Ok(rxing), vec![("backend", JsValue::from_str("rxing"))])
+                                    }
+                                    _ => {
+                                        (Ok(native), vec![("backend", JsValue::from_str("native"))])
+                                    }
+                                }
+                            }
+                            native => (native, vec![("backend", JsValue::from_str("native"))]),
+                        }
+                    };
+                    stats::record_frame(
+                        vw,
+                        vh,
+                        stats::now_precise() - decode_started,
+                        result.is_ok(),
+                    );
+
+                    if running.get() {
+                        handle_stream_result(result, &tags);
                     }
-                } else {
-                    LAST_DETECTED_CODE.with(|code| {
-                        *code.borrow_mut() = Some(text.clone());
-                    });
-                    DETECTION_COUNT.with(|c| c.set(1));
-                }
+                    busy.set(false);
+                });
             }
 
+            stats::emit_if_due();
+
             if let Some(cb) = raf_cb2.borrow().as_ref() {
                 window
                     .request_animation_frame(cb.as_ref().unchecked_ref())
@@ -382,6 +540,10 @@ pub fn start_stream_scan(video_element_id: &str) -> Result<(), JsValue> {
             }
         }));
 
+        backend::prepare().await;
+        stats::begin_session();
+        overlay::attach(&video_el);
+
         if let Some(cb) = raf_cb.borrow().as_ref() {
             window
                 .request_animation_frame(cb.as_ref().unchecked_ref())
@@ -427,6 +589,9 @@ fn stop_stream_scan_internal(video: &HtmlVideoElement, stream: &MediaStream) {
     LAST_DETECTED_CODE.with(|code| {
         *code.borrow_mut() = None;
     });
+    LAST_REJECTED_CODE.with(|code| {
+        *code.borrow_mut() = None;
+    });
     DETECTION_COUNT.with(|c| c.set(0));
 
     RUNNING_FLAG.with(|flag| {
@@ -446,5 +611,7 @@ fn stop_stream_scan_internal(video: &HtmlVideoElement, stream: &MediaStream) {
 
     video.set_src_object(None);
 
+    overlay::detach();
+    stats::end_session();
     invoke_on_stop();
 }
@@ -0,0 +1,445 @@
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+use js_sys::{Array, Date, JSON, Object, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::{
+    content::{decode_shc_numeric, split_shc},
+    crypto::{Algorithm, PublicKey, base64url_decode},
+    error::Error,
+    options,
+};
+
+/// Largest inflated payload accepted, to bound the memory a crafted card can
+/// claim.
+const MAX_PAYLOAD_LEN: u64 = 1 << 20;
+
+/// A signing key from the application's JWKS.
+struct Jwk {
+    kid: Option<String>,
+    key: PublicKey,
+}
+
+/// Reads the keys of a JWKS, given either as `{ keys: [...] }` or as the key
+/// array itself. Keys other than EC P-256 cannot sign health cards and are
+/// skipped.
+fn parse_jwks(options: &JsValue) -> Result<Vec<Jwk>, Error> {
+    let obj = options::as_object(options)?;
+    let Some(jwks) = options::get(&obj, "jwks") else {
+        return Ok(Vec::new());
+    };
+    let keys = match jwks.dyn_ref::<Array>() {
+        Some(keys) => keys.clone(),
+        None => options::get(&options::as_object(&jwks)?, "keys")
+            .and_then(|keys| keys.dyn_into::<Array>().ok())
+            .ok_or(Error::InvalidOptions)?,
+    };
+
+    let mut parsed = Vec::new();
+    for key in keys.iter() {
+        let key = options::as_object(&key)?;
+        if options::get_string(&key, "kty")?.as_deref() != Some("EC")
+            || options::get_string(&key, "crv")?.as_deref() != Some("P-256")
+        {
+            continue;
+        }
+
+        let coordinate = |name| -> Result<Vec<u8>, Error> {
+            options::get_string(&key, name)?
+                .and_then(|value| base64url_decode(&value))
+                .ok_or(Error::InvalidOptions)
+        };
+        parsed.push(Jwk {
+            kid: options::get_string(&key, "kid")?,
+            key: PublicKey::from_p256_coordinates(&coordinate("x")?, &coordinate("y")?)?,
+        });
+    }
+    Ok(parsed)
+}
+
+/// Joins the chunks of a multi-QR card, given in any order, into the JWS.
+/// Chunks read more than once are accepted; a card still missing chunks
+/// yields `SequenceIncomplete`.
+fn reassemble(values: &[String]) -> Result<String, Error> {
+    let mut chunks: Vec<Option<String>> = Vec::new();
+    for value in values {
+        let (index, count, data) = split_shc(value.trim()).ok_or(Error::UnsupportedFormat)?;
+        if chunks.is_empty() {
+            // Every chunk must be among the values, which also bounds the
+            // allocation by the input.
+            if count as usize > values.len() {
+                return Err(Error::SequenceIncomplete);
+            }
+            chunks = vec![None; count as usize];
+        } else if chunks.len() != count as usize {
+            return Err(Error::PayloadDecodeFailed);
+        }
+
+        let decoded = decode_shc_numeric(data).ok_or(Error::PayloadDecodeFailed)?;
+        match &chunks[index as usize - 1] {
+            Some(existing) if *existing != decoded => return Err(Error::PayloadDecodeFailed),
+            _ => chunks[index as usize - 1] = Some(decoded),
+        }
+    }
+
+    chunks
+        .into_iter()
+        .collect::<Option<String>>()
+        .filter(|jws| !jws.is_empty())
+        .ok_or(Error::SequenceIncomplete)
+}
+
+/// Inflates a raw DEFLATE stream, as used for `"zip": "DEF"` payloads.
+fn inflate(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    DeflateDecoder::new(data)
+        .take(MAX_PAYLOAD_LEN + 1)
+        .read_to_end(&mut out)
+        .map_err(|_| Error::PayloadDecodeFailed)?;
+    if out.len() as u64 > MAX_PAYLOAD_LEN {
+        return Err(Error::PayloadDecodeFailed);
+    }
+    Ok(out)
+}
+
+/// The parts of a compact JWS.
+struct Jws<'a> {
+    signing_input: &'a str,
+    header: Vec<u8>,
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl<'a> Jws<'a> {
+    fn parse(jws: &'a str) -> Result<Self, Error> {
+        let (signing_input, signature) = jws.rsplit_once('.').ok_or(Error::PayloadDecodeFailed)?;
+        let (header, payload) = signing_input
+            .split_once('.')
+            .ok_or(Error::PayloadDecodeFailed)?;
+        let decode = |part: &str| base64url_decode(part).ok_or(Error::PayloadDecodeFailed);
+
+        Ok(Jws {
+            signing_input,
+            header: decode(header)?,
+            payload: decode(payload)?,
+            signature: decode(signature)?,
+        })
+    }
+}
+
+fn parse_json(bytes: &[u8]) -> Result<JsValue, Error> {
+    let text = std::str::from_utf8(bytes).map_err(|_| Error::PayloadDecodeFailed)?;
+    JSON::parse(text).map_err(|_| Error::PayloadDecodeFailed)
+}
+
+fn field(value: &JsValue, key: &str) -> Option<JsValue> {
+    Reflect::get(value, &JsValue::from_str(key))
+        .ok()
+        .filter(|v| !v.is_undefined() && !v.is_null())
+}
+
+fn string_field(value: &JsValue, key: &str) -> Option<String> {
+    field(value, key).and_then(|v| v.as_string())
+}
+
+/// Checks the signature against the JWKS, returning the `verification`
+/// status reported to the caller.
+fn verify(jws: &Jws, alg: Option<&str>, kid: Option<&str>, keys: &[Jwk]) -> &'static str {
+    if keys.is_empty() {
+        return "unchecked";
+    }
+    if alg != Some("ES256") {
+        return "unsupported_algorithm";
+    }
+    let mut candidates = keys
+        .iter()
+        .filter(|jwk| kid.is_none() || jwk.kid.is_none() || jwk.kid.as_deref() == kid)
+        .peekable();
+    if candidates.peek().is_none() {
+        return "unknown_key";
+    }
+
+    let message = jws.signing_input.as_bytes();
+    if candidates.any(|jwk| jwk.key.verify(Algorithm::Es256, message, &jws.signature)) {
+        "valid"
+    } else {
+        "invalid"
+    }
+}
+
+/// Decodes a SMART Health Card from its `shc:/` value, or from every chunk
+/// of a multi-QR card, optionally verifying its signature against the JWKS
+/// in `options`.
+pub fn decode_smart_health_card(values: &JsValue, options: &JsValue) -> Result<JsValue, Error> {
+    let keys = parse_jwks(options)?;
+
+    let values: Vec<String> = match values.dyn_ref::<Array>() {
+        Some(array) => array
+            .iter()
+            .map(|value| value.as_string().ok_or(Error::UnsupportedFormat))
+            .collect::<Result<_, _>>()?,
+        None => vec![values.as_string().ok_or(Error::UnsupportedFormat)?],
+    };
+    let jws = reassemble(&values)?;
+    let jws = Jws::parse(&jws)?;
+
+    let header = parse_json(&jws.header)?;
+    let payload = match string_field(&header, "zip").as_deref() {
+        Some("DEF") => inflate(&jws.payload)?,
+        Some(_) => return Err(Error::PayloadDecodeFailed),
+        None => jws.payload.clone(),
+    };
+    let payload = parse_json(&payload)?;
+
+    let alg = string_field(&header, "alg");
+    let kid = string_field(&header, "kid");
+    let verification = verify(&jws, alg.as_deref(), kid.as_deref(), &keys);
+
+    let credential = field(&payload, "vc");
+    let subject = credential
+        .as_ref()
+        .and_then(|vc| field(vc, "credentialSubject"));
+    let bundle = subject
+        .as_ref()
+        .and_then(|subject| field(subject, "fhirBundle"))
+        .ok_or(Error::PayloadDecodeFailed)?;
+
+    let expires_at = field(&payload, "exp").and_then(|exp| exp.as_f64());
+    let expired = expires_at.is_some_and(|exp| exp * 1000.0 < Date::now());
+    let optional = |value: Option<JsValue>| value.unwrap_or(JsValue::NULL);
+
+    let obj = Object::new();
+    for (key, value) in [
+        ("issuer", optional(field(&payload, "iss"))),
+        ("kid", optional(kid.map(|kid| JsValue::from_str(&kid)))),
+        (
+            "algorithm",
+            optional(alg.map(|alg| JsValue::from_str(&alg))),
+        ),
+        ("issuedAt", optional(field(&payload, "nbf"))),
+        ("expiresAt", optional(expires_at.map(JsValue::from_f64))),
+        ("expired", JsValue::from_bool(expired)),
+        (
+            "types",
+            optional(credential.as_ref().and_then(|vc| field(vc, "type"))),
+        ),
+        ("bundle", bundle),
+        ("verification", JsValue::from_str(verification)),
+    ] {
+        let _ = Reflect::set(&obj, &JsValue::from_str(key), &value);
+    }
+    Ok(obj.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::DeflateEncoder};
+
+    use super::*;
+    use crate::crypto::{
+        base64_encode,
+        tests::{es256, sec1_point, signing_key},
+    };
+
+    const KID: &str = "3Kfdg-XwP-7gXyywtUfUADwBumDOPKMQx-iELL11W9s";
+
+    fn base64url(bytes: &[u8]) -> String {
+        base64_encode(bytes)
+            .trim_end_matches('=')
+            .replace('+', "-")
+            .replace('/', "_")
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    const PAYLOAD: &str = r#"{"iss":"https://spec.smarthealth.cards/examples/issuer","nbf":1767225600,"vc":{"type":["https://smarthealth.cards#health-card"],"credentialSubject":{"fhirVersion":"4.0.1","fhirBundle":{"resourceType":"Bundle","type":"collection","entry":[]}}}}"#;
+
+    /// A compact JWS with a deflated payload, signed like a health card.
+    fn jws(header: &str) -> String {
+        let signing_input = format!(
+            "{}.{}",
+            base64url(header.as_bytes()),
+            base64url(&deflate(PAYLOAD.as_bytes()))
+        );
+        let signature = es256(&signing_key(), signing_input.as_bytes());
+        format!("{signing_input}.{}", base64url(&signature))
+    }
+
+    fn card() -> String {
+        jws(&format!(r#"{{"zip":"DEF","alg":"ES256","kid":"{KID}"}}"#))
+    }
+
+    fn numeric(jws: &str) -> String {
+        jws.bytes().map(|c| format!("{:02}", c - 45)).collect()
+    }
+
+    /// Splits a JWS into `shc:/index/count/` chunks.
+    fn chunks(jws: &str, count: usize) -> Vec<String> {
+        let size = jws.len().div_ceil(count);
+        jws.as_bytes()
+            .chunks(size)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let chunk = std::str::from_utf8(chunk).unwrap();
+                format!("shc:/{}/{count}/{}", i + 1, numeric(chunk))
+            })
+            .collect()
+    }
+
+    fn keys(kid: Option<&str>) -> Vec<Jwk> {
+        let point = sec1_point(&signing_key());
+        vec![Jwk {
+            kid: kid.map(str::to_string),
+            key: PublicKey::from_p256_coordinates(&point[1..33], &point[33..]).unwrap(),
+        }]
+    }
+
+    #[test]
+    fn numeric_pairs_must_be_two_digits() {
+        assert_eq!(decode_shc_numeric("5677").as_deref(), Some("ez"));
+        assert_eq!(decode_shc_numeric("0077").as_deref(), Some("-z"));
+        for digits in ["", "5", "+5", "5+", " 5", "-1", "5a", "78", "99", "５６"] {
+            assert_eq!(decode_shc_numeric(digits), None, "{digits}");
+        }
+    }
+
+    #[test]
+    fn single_cards_reassemble_to_their_jws() {
+        let card = card();
+        let value = format!("shc:/{}", numeric(&card));
+        assert_eq!(reassemble(&[value]).unwrap(), card);
+    }
+
+    #[test]
+    fn chunks_reassemble_in_any_order() {
+        let card = card();
+        let mut values = chunks(&card, 3);
+        assert_eq!(reassemble(&values).unwrap(), card);
+
+        values.reverse();
+        assert_eq!(reassemble(&values).unwrap(), card);
+
+        // A chunk read twice is accepted.
+        values.insert(1, values[0].clone());
+        assert_eq!(reassemble(&values).unwrap(), card);
+    }
+
+    #[test]
+    fn missing_chunks_leave_the_card_incomplete() {
+        let values = chunks(&card(), 3);
+        assert!(matches!(
+            reassemble(&values[..2]),
+            Err(Error::SequenceIncomplete)
+        ));
+        // Enough values to hold every chunk, one of them read twice.
+        let repeated = [values[0].clone(), values[2].clone(), values[0].clone()];
+        assert!(matches!(
+            reassemble(&repeated),
+            Err(Error::SequenceIncomplete)
+        ));
+    }
+
+    #[test]
+    fn conflicting_chunks_are_rejected() {
+        let card = card();
+        let mut values = chunks(&card, 3);
+        // Another card's first chunk.
+        let other = chunks(&jws(r#"{"zip":"DEF","alg":"ES256"}"#), 3);
+        values.push(other[0].clone());
+        assert!(matches!(
+            reassemble(&values),
+            Err(Error::PayloadDecodeFailed)
+        ));
+
+        // Chunks disagreeing on the count.
+        let mut values = chunks(&card, 3);
+        values[1] = chunks(&card, 2)[1].clone();
+        assert!(matches!(
+            reassemble(&values),
+            Err(Error::PayloadDecodeFailed)
+        ));
+
+        assert!(matches!(
+            reassemble(&["https://example.com".to_string()]),
+            Err(Error::UnsupportedFormat)
+        ));
+        for value in ["shc:/4/3/5677", "shc:/+1/3/5677", "shc:/1/+3/5677"] {
+            assert!(matches!(
+                reassemble(&[value.to_string()]),
+                Err(Error::UnsupportedFormat)
+            ));
+        }
+    }
+
+    #[test]
+    fn payloads_inflate() {
+        let card = card();
+        let jws = Jws::parse(&card).unwrap();
+        assert_eq!(inflate(&jws.payload).unwrap(), PAYLOAD.as_bytes());
+        assert_eq!(
+            jws.header,
+            format!(r#"{{"zip":"DEF","alg":"ES256","kid":"{KID}"}}"#).as_bytes()
+        );
+
+        // A final block of the reserved type.
+        assert!(inflate(&[0x07]).is_err());
+        let bomb = deflate(&vec![0; MAX_PAYLOAD_LEN as usize + 1]);
+        assert!(bomb.len() < 4096);
+        assert!(matches!(inflate(&bomb), Err(Error::PayloadDecodeFailed)));
+    }
+
+    #[test]
+    fn malformed_jws_are_rejected() {
+        let card = card();
+        let (signing_input, signature) = card.rsplit_once('.').unwrap();
+        for jws in [
+            "",
+            signing_input,
+            &format!("{signing_input}.{signature}=="),
+            &format!("{signing_input}.{}", signature.replace('-', "+")),
+        ] {
+            assert!(Jws::parse(jws).is_err(), "{jws}");
+        }
+    }
+
+    #[test]
+    fn signatures_verify_against_the_jwks() {
+        let card = card();
+        let jws = Jws::parse(&card).unwrap();
+        let es256 = Some("ES256");
+
+        assert_eq!(verify(&jws, es256, Some(KID), &keys(Some(KID))), "valid");
+        // A key without a kid is tried for any card.
+        assert_eq!(verify(&jws, es256, Some(KID), &keys(None)), "valid");
+        assert_eq!(verify(&jws, es256, Some(KID), &[]), "unchecked");
+        assert_eq!(
+            verify(&jws, es256, Some(KID), &keys(Some("another"))),
+            "unknown_key"
+        );
+        assert_eq!(
+            verify(&jws, Some("RS256"), Some(KID), &keys(Some(KID))),
+            "unsupported_algorithm"
+        );
+    }
+
+    #[test]
+    fn tampered_cards_are_invalid() {
+        let card = card();
+        let (header, rest) = card.split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let tampered = format!("{header}.{}.{signature}", base64url(&deflate(b"{}")));
+
+        let jws = Jws::parse(&tampered).unwrap();
+        assert_eq!(
+            verify(&jws, Some("ES256"), Some(KID), &keys(Some(KID))),
+            "invalid"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use std::cell::{Cell, RefCell};
+
+use js_sys::{Object, Reflect};
+use wasm_bindgen::JsValue;
+
+use crate::event::{invoke_on_stats, stats_interval_ms};
+
+/// Number of most recent decode timings kept for percentile calculation.
+const MAX_DECODE_SAMPLES: usize = 1000;
+
+#[derive(Default)]
+struct ScanStats {
+    started_at: f64,
+    ended_at: Option<f64>,
+    frames_captured: u32,
+    frames_decoded: u32,
+    detections: u32,
+    first_detection_at: Option<f64>,
+    width: u32,
+    height: u32,
+    decode_times: Vec<f64>,
+    next_sample: usize,
+}
+
+thread_local! {
+    static STATS: RefCell<Option<ScanStats>> = const { RefCell::new(None) };
+    static LAST_EMIT_MS: Cell<f64> = const { Cell::new(0.0) };
+}
+
+/// High-resolution timestamp in milliseconds, falling back to `Date.now()`
+/// when the Performance API is unavailable.
+pub fn now_precise() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map(|performance| performance.now())
+        .unwrap_or_else(js_sys::Date::now)
+}
+
+/// Starts a new stream session, discarding the stats of the previous one.
+pub fn begin_session() {
+    let now = now_precise();
+    STATS.with(|stats| {
+        *stats.borrow_mut() = Some(ScanStats {
+            started_at: now,
+            ..Default::default()
+        });
+    });
+    LAST_EMIT_MS.with(|last| last.set(now));
+}
+
+/// Records a captured frame and how long it took to decode.
+pub fn record_frame(width: u32, height: u32, decode_ms: f64, decoded: bool) {
+    STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        let Some(stats) = stats.as_mut() else {
+            return;
+        };
+
+        stats.frames_captured += 1;
+        if decoded {
+            stats.frames_decoded += 1;
+        }
+        stats.width = width;
+        stats.height = height;
+
+        if stats.decode_times.len() < MAX_DECODE_SAMPLES {
+            stats.decode_times.push(decode_ms);
+        } else {
+            stats.decode_times[stats.next_sample] = decode_ms;
+        }
+        stats.next_sample = (stats.next_sample + 1) % MAX_DECODE_SAMPLES;
+    });
+}
+
+/// Records a confirmed detection reported through `on_detect`.
+pub fn record_detection() {
+    let now = now_precise();
+    STATS.with(|stats| {
+        if let Some(stats) = stats.borrow_mut().as_mut() {
+            stats.detections += 1;
+            stats.first_detection_at.get_or_insert(now);
+        }
+    });
+}
+
+/// Calls `on_stats` if the configured interval has elapsed since the last call.
+pub fn emit_if_due() {
+    let Some(interval) = stats_interval_ms() else {
+        return;
+    };
+
+    let now = now_precise();
+    if now - LAST_EMIT_MS.with(|last| last.get()) < interval as f64 {
+        return;
+    }
+    LAST_EMIT_MS.with(|last| last.set(now));
+
+    invoke_on_stats(&get_scan_stats());
+}
+
+/// Freezes the stats of the current session and reports them one last time.
+pub fn end_session() {
+    let now = now_precise();
+    let ended = STATS.with(|stats| match stats.borrow_mut().as_mut() {
+        Some(stats) if stats.ended_at.is_none() => {
+            stats.ended_at = Some(now);
+            true
+        }
+        _ => false,
+    });
+
+    if ended && stats_interval_ms().is_some() {
+        invoke_on_stats(&get_scan_stats());
+    }
+}
+
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let rank = (p / 100.0 * (sorted.len() - 1) as f64).round() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+fn set_number(obj: &Object, key: &str, value: f64) {
+    let _ = Reflect::set(obj, &JsValue::from_str(key), &JsValue::from_f64(value));
+}
+
+/// Returns the stats of the current or most recent stream session, or `null`
+/// if no session has been started.
+pub fn get_scan_stats() -> JsValue {
+    STATS.with(|stats| {
+        let stats = stats.borrow();
+        let Some(stats) = stats.as_ref() else {
+            return JsValue::NULL;
+        };
+
+        let elapsed_ms = stats.ended_at.unwrap_or_else(now_precise) - stats.started_at;
+        let mut sorted = stats.decode_times.clone();
+        sorted.sort_by(f64::total_cmp);
+
+        let obj = Object::new();
+        set_number(&obj, "framesCaptured", stats.frames_captured as f64);
+        set_number(&obj, "framesDecoded", stats.frames_decoded as f64);
+        set_number(&obj, "detections", stats.detections as f64);
+        set_number(&obj, "durationMs", elapsed_ms);
+        set_number(
+            &obj,
+            "effectiveFps",
+            if elapsed_ms > 0.0 {
+                stats.frames_captured as f64 * 1000.0 / elapsed_ms
+            } else {
+                0.0
+            },
+        );
+        let _ = Reflect::set(
+            &obj,
+            &JsValue::from_str("timeToFirstDetectionMs"),
+            &stats
+                .first_detection_at
+                .map_or(JsValue::NULL, |at| JsValue::from_f64(at - stats.started_at)),
+        );
+
+        let decode = Object::new();
+        set_number(&decode, "p50", percentile(&sorted, 50.0));
+        set_number(&decode, "p90", percentile(&sorted, 90.0));
+        set_number(&decode, "p99", percentile(&sorted, 99.0));
+        set_number(&decode, "max", sorted.last().copied().unwrap_or(0.0));
+        let _ = Reflect::set(&obj, &JsValue::from_str("decodeTimeMs"), &decode);
+
+        let resolution = Object::new();
+        set_number(&resolution, "width", stats.width as f64);
+        set_number(&resolution, "height", stats.height as f64);
+        let _ = Reflect::set(&obj, &JsValue::from_str("resolution"), &resolution);
+
+        obj.into()
+    })
+}
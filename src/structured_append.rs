@@ -0,0 +1,504 @@
+use std::cell::{Cell, RefCell};
+
+use js_sys::{Array, Date, Object, Reflect};
+use rxing::{RXingResult, RXingResultMetadataType, RXingResultMetadataValue};
+use wasm_bindgen::JsValue;
+
+use crate::{
+    detector::Detection, error::Error, event::invoke_on_structured_append_progress, options,
+    validator,
+};
+
+/// Sequences buffered at once; the least recently updated one is dropped to
+/// make room for a new sequence.
+const MAX_PENDING_SEQUENCES: usize = 4;
+
+/// Position of a QR Code symbol within a structured append sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StructuredAppend {
+    /// 0-based position of the symbol.
+    pub index: u8,
+    /// Number of symbols in the sequence, 1 to 16.
+    pub total: u8,
+    /// XOR of every byte of the complete message, shared by all symbols.
+    pub parity: u8,
+}
+
+impl StructuredAppend {
+    /// Reads the structured append header from a decoded QR Code.
+    pub fn from_result(res: &RXingResult) -> Option<Self> {
+        let metadata = res.getRXingResultMetadata();
+        let Some(RXingResultMetadataValue::StructuredAppendSequence(sequence)) =
+            metadata.get(&RXingResultMetadataType::STRUCTURED_APPEND_SEQUENCE)
+        else {
+            return None;
+        };
+        let Some(RXingResultMetadataValue::StructuredAppendParity(parity)) =
+            metadata.get(&RXingResultMetadataType::STRUCTURED_APPEND_PARITY)
+        else {
+            return None;
+        };
+
+        // The sequence indicator holds the position in the high nibble and
+        // the number of symbols minus one in the low nibble.
+        let (index, total) = ((*sequence >> 4) as u8 & 0x0f, (*sequence & 0x0f) as u8 + 1);
+        (*sequence >= 0 && index < total).then_some(StructuredAppend {
+            index,
+            total,
+            parity: *parity as u8,
+        })
+    }
+
+    /// Builds the `{ index, total, parity }` object describing the symbol.
+    pub fn to_js(self) -> Object {
+        let obj = Object::new();
+        for (key, value) in [
+            ("index", self.index),
+            ("total", self.total),
+            ("parity", self.parity),
+        ] {
+            let _ = Reflect::set(
+                &obj,
+                &JsValue::from_str(key),
+                &JsValue::from_f64(value as f64),
+            );
+        }
+        obj
+    }
+}
+
+#[derive(Clone, Copy)]
+struct StructuredAppendOptions {
+    enabled: bool,
+    timeout_ms: Option<u32>,
+}
+
+impl Default for StructuredAppendOptions {
+    fn default() -> Self {
+        StructuredAppendOptions {
+            enabled: true,
+            timeout_ms: None,
+        }
+    }
+}
+
+/// The symbols of one sequence read so far.
+struct PendingSequence {
+    total: u8,
+    parity: u8,
+    parts: Vec<Option<Detection>>,
+    updated_at: f64,
+}
+
+impl PendingSequence {
+    fn received(&self) -> usize {
+        self.parts.iter().filter(|part| part.is_some()).count()
+    }
+}
+
+thread_local! {
+    static STRUCTURED_APPEND_OPTIONS: Cell<StructuredAppendOptions> =
+        Cell::new(StructuredAppendOptions::default());
+    static PENDING: RefCell<Vec<PendingSequence>> = const { RefCell::new(Vec::new()) };
+    /// The most recently completed sequence, whose symbols are ignored while
+    /// they stay in view so they do not start the same sequence over.
+    static COMPLETED: Cell<Option<(u8, u8)>> = const { Cell::new(None) };
+}
+
+pub fn set_structured_append_options(options: &JsValue) -> Result<(), Error> {
+    let obj = options::as_object(options)?;
+    let defaults = StructuredAppendOptions::default();
+
+    let parsed = StructuredAppendOptions {
+        enabled: options::get_bool(&obj, "enabled")?.unwrap_or(defaults.enabled),
+        timeout_ms: options::get_u32(&obj, "timeoutMs")?.filter(|ms| *ms > 0),
+    };
+
+    STRUCTURED_APPEND_OPTIONS.with(|opts| opts.set(parsed));
+    if !parsed.enabled {
+        clear_structured_append();
+    }
+
+    Ok(())
+}
+
+/// Returns true if structured append symbols are buffered and reassembled
+/// rather than reported one by one.
+pub fn enabled() -> bool {
+    STRUCTURED_APPEND_OPTIONS.with(|opts| opts.get().enabled)
+}
+
+/// Discards every buffered sequence.
+pub fn clear_structured_append() {
+    PENDING.with(|pending| pending.borrow_mut().clear());
+    COMPLETED.with(|completed| completed.set(None));
+}
+
+/// Outcome of passing a detection through the structured append buffer.
+pub enum Assembly {
+    /// A detection that is not part of a structured append sequence.
+    Single(Detection),
+    /// The symbol was buffered; the sequence is not complete yet.
+    Pending,
+    /// The last missing symbol was read; the concatenated message.
+    Complete(Detection),
+}
+
+/// Returns true if the XOR of the message's bytes matches `parity`, with the
+/// message encoded as UTF-8 or, when it fits, ISO-8859-1.
+fn parity_matches(value: &str, parity: u8) -> bool {
+    let xor = |bytes: &mut dyn Iterator<Item = u8>| bytes.fold(0, |acc, b| acc ^ b);
+
+    xor(&mut value.bytes()) == parity
+        || (value.chars().all(|c| (c as u32) <= 0xff)
+            && xor(&mut value.chars().map(|c| c as u8)) == parity)
+}
+
+/// A sequence's state after a newly read symbol.
+#[derive(Debug, PartialEq)]
+struct Progress {
+    index: u8,
+    received: usize,
+    total: u8,
+    parity: u8,
+    missing: Vec<usize>,
+}
+
+impl Progress {
+    fn new(sequence: &PendingSequence, index: u8) -> Self {
+        Progress {
+            index,
+            received: sequence.received(),
+            total: sequence.total,
+            parity: sequence.parity,
+            missing: sequence
+                .parts
+                .iter()
+                .enumerate()
+                .filter(|(_, part)| part.is_none())
+                .map(|(i, _)| i)
+                .collect(),
+        }
+    }
+
+    fn to_js(&self) -> JsValue {
+        let obj = Object::new();
+        let missing: Array = self
+            .missing
+            .iter()
+            .map(|&i| JsValue::from_f64(i as f64))
+            .collect();
+        for (key, value) in [
+            ("index", JsValue::from_f64(self.index as f64)),
+            ("received", JsValue::from_f64(self.received as f64)),
+            ("total", JsValue::from_f64(self.total as f64)),
+            ("parity", JsValue::from_f64(self.parity as f64)),
+            ("missing", missing.into()),
+        ] {
+            let _ = Reflect::set(&obj, &JsValue::from_str(key), &value);
+        }
+        obj.into()
+    }
+}
+
+/// Buffers a structured append symbol, returning the concatenated message
+/// once every symbol of its sequence has been read.
+///
+/// Symbols are grouped into sequences by their count and parity. Each newly
+/// read symbol is reported through `on_structured_append_progress`; symbols
+/// read again are ignored. A complete message whose parity does not match
+/// fails with `SequenceParityMismatch`, and one rejected by the validation
+/// options with `Rejected`. `now` is the current time in milliseconds, used
+/// for the timeout.
+pub fn collect(detection: Detection, now: f64) -> Result<Assembly, Error> {
+    let (progress, assembly) = buffer(detection, now);
+    // Called outside the borrow so the callback may clear the buffer.
+    if let Some(progress) = progress {
+        invoke_on_structured_append_progress(&progress.to_js());
+    }
+    assembly
+}
+
+/// Does the work of `collect`, also returning the progress to report.
+fn buffer(detection: Detection, now: f64) -> (Option<Progress>, Result<Assembly, Error>) {
+    let Some(part) = detection.structured_append.filter(|_| enabled()) else {
+        return (None, Ok(Assembly::Single(detection)));
+    };
+
+    let key = (part.total, part.parity);
+    if COMPLETED.with(|completed| completed.get()) == Some(key) {
+        return (None, Ok(Assembly::Pending));
+    }
+
+    let timeout_ms = STRUCTURED_APPEND_OPTIONS.with(|opts| opts.get().timeout_ms);
+    let (progress, complete) = PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        if let Some(timeout_ms) = timeout_ms {
+            pending.retain(|sequence| now - sequence.updated_at <= timeout_ms as f64);
+        }
+
+        let position = match pending
+            .iter()
+            .position(|sequence| (sequence.total, sequence.parity) == key)
+        {
+            Some(position) => position,
+            None => {
+                COMPLETED.with(|completed| completed.set(None));
+                if pending.len() >= MAX_PENDING_SEQUENCES
+                    && let Some(oldest) = pending
+                        .iter()
+                        .enumerate()
+                        .min_by(|(_, a), (_, b)| a.updated_at.total_cmp(&b.updated_at))
+                        .map(|(i, _)| i)
+                {
+                    pending.remove(oldest);
+                }
+                pending.push(PendingSequence {
+                    total: part.total,
+                    parity: part.parity,
+                    parts: vec![None; part.total as usize],
+                    updated_at: now,
+                });
+                pending.len() - 1
+            }
+        };
+
+        let sequence = &mut pending[position];
+        let slot = &mut sequence.parts[part.index as usize];
+        if slot.is_some() {
+            return (None, None);
+        }
+        *slot = Some(detection);
+        sequence.updated_at = now;
+
+        let progress = Progress::new(sequence, part.index);
+        let complete =
+            (sequence.received() == sequence.parts.len()).then(|| pending.remove(position));
+        (Some(progress), complete)
+    });
+
+    let Some(sequence) = complete else {
+        return (progress, Ok(Assembly::Pending));
+    };
+    COMPLETED.with(|completed| completed.set(Some(key)));
+    (progress, complete_message(sequence))
+}
+
+/// Concatenates the symbols of a complete sequence.
+fn complete_message(sequence: PendingSequence) -> Result<Assembly, Error> {
+    let parts: Vec<Detection> = sequence.parts.into_iter().flatten().collect();
+    let value: String = parts.iter().map(|part| part.value.as_str()).collect();
+    if !parity_matches(&value, sequence.parity) {
+        return Err(Error::SequenceParityMismatch);
+    }
+
+    let format = parts[0].format;
+    let value = validator::validate(&format, &value).ok_or(Error::Rejected)?;
+    Ok(Assembly::Complete(Detection {
+        value,
+        format,
+        points: parts
+            .into_iter()
+            .last()
+            .map(|last| last.points)
+            .unwrap_or_default(),
+        structured_append: None,
+        linked_composite: false,
+        add_on: None,
+    }))
+}
+
+/// Passes a still-image result through the structured append buffer. A
+/// symbol that leaves its sequence incomplete yields `SequenceIncomplete`.
+pub fn assemble(result: Result<Detection, Error>) -> Result<Detection, Error> {
+    // Images are scanned on purpose, so reading a completed sequence again
+    // starts it over.
+    COMPLETED.with(|completed| completed.set(None));
+
+    let detection = result?;
+    if detection.structured_append.is_none() {
+        return Ok(detection);
+    }
+    match collect(detection, Date::now())? {
+        Assembly::Single(detection) | Assembly::Complete(detection) => Ok(detection),
+        Assembly::Pending => Err(Error::SequenceIncomplete),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rxing::BarcodeFormat;
+
+    use super::*;
+    use crate::detector::Format;
+
+    /// XOR of the bytes of `message`.
+    fn parity(message: &str) -> u8 {
+        message.bytes().fold(0, |acc, b| acc ^ b)
+    }
+
+    fn symbol(value: &str, index: u8, total: u8, parity: u8) -> Detection {
+        Detection {
+            value: value.into(),
+            format: Format::Rxing(BarcodeFormat::QR_CODE),
+            points: vec![(index as f32, 0.0)],
+            structured_append: Some(StructuredAppend {
+                index,
+                total,
+                parity,
+            }),
+            linked_composite: false,
+            add_on: None,
+        }
+    }
+
+    fn complete(result: Result<Assembly, Error>) -> Detection {
+        match result {
+            Ok(Assembly::Complete(detection)) => detection,
+            _ => panic!("sequence not complete"),
+        }
+    }
+
+    fn is_pending(result: &Result<Assembly, Error>) -> bool {
+        matches!(result, Ok(Assembly::Pending))
+    }
+
+    #[test]
+    fn parity_accepts_utf_8_and_latin_1_messages() {
+        assert!(parity_matches("AB", 0x41 ^ 0x42));
+        assert!(!parity_matches("AB", 0x41));
+        // U+00E9 is C3 A9 in UTF-8 and E9 in ISO-8859-1.
+        assert!(parity_matches("é", 0xc3 ^ 0xa9));
+        assert!(parity_matches("é", 0xe9));
+        // Outside ISO-8859-1 only the UTF-8 bytes count.
+        assert!(!parity_matches("€", 0xac));
+    }
+
+    #[test]
+    fn symbols_without_a_header_pass_through() {
+        let mut detection = symbol("plain", 0, 1, 0);
+        detection.structured_append = None;
+        let (progress, result) = buffer(detection, 0.0);
+        assert_eq!(progress, None);
+        assert!(matches!(result, Ok(Assembly::Single(d)) if d.value == "plain"));
+    }
+
+    #[test]
+    fn completes_in_index_order_whatever_the_read_order() {
+        let p = parity("Hello, world");
+        let (progress, result) = buffer(symbol("world", 2, 3, p), 0.0);
+        assert!(is_pending(&result));
+        assert_eq!(
+            progress,
+            Some(Progress {
+                index: 2,
+                received: 1,
+                total: 3,
+                parity: p,
+                missing: vec![0, 1],
+            })
+        );
+        assert!(is_pending(&buffer(symbol("Hello", 0, 3, p), 1.0).1));
+
+        let (progress, result) = buffer(symbol(", ", 1, 3, p), 2.0);
+        assert_eq!(progress.map(|p| (p.received, p.missing)), Some((3, vec![])));
+        let message = complete(result);
+        assert_eq!(message.value, "Hello, world");
+        assert_eq!(message.structured_append, None);
+        // The last symbol of the sequence supplies the position.
+        assert_eq!(message.points, [(2.0, 0.0)]);
+
+        // Symbols of the completed sequence still in view are ignored.
+        let (progress, result) = buffer(symbol("Hello", 0, 3, p), 3.0);
+        assert_eq!(progress, None);
+        assert!(is_pending(&result));
+    }
+
+    #[test]
+    fn duplicate_symbols_are_ignored() {
+        let p = parity("abcd");
+        assert!(buffer(symbol("ab", 0, 2, p), 0.0).0.is_some());
+        let (progress, result) = buffer(symbol("ab", 0, 2, p), 1.0);
+        assert_eq!(progress, None);
+        assert!(is_pending(&result));
+        assert_eq!(complete(buffer(symbol("cd", 1, 2, p), 2.0).1).value, "abcd");
+    }
+
+    #[test]
+    fn sequences_are_grouped_by_count_and_parity() {
+        let (first, second) = (parity("abcd"), parity("wxyz"));
+        assert_ne!(first, second);
+        assert!(is_pending(&buffer(symbol("ab", 0, 2, first), 0.0).1));
+        assert!(is_pending(&buffer(symbol("wx", 0, 2, second), 1.0).1));
+        // Same parity, different count: a third sequence.
+        assert!(is_pending(&buffer(symbol("a", 0, 3, first), 2.0).1));
+
+        assert_eq!(
+            complete(buffer(symbol("yz", 1, 2, second), 3.0).1).value,
+            "wxyz"
+        );
+        assert_eq!(
+            complete(buffer(symbol("cd", 1, 2, first), 4.0).1).value,
+            "abcd"
+        );
+    }
+
+    #[test]
+    fn parity_mismatch_fails_the_sequence() {
+        let p = parity("abcd") ^ 1;
+        assert!(is_pending(&buffer(symbol("ab", 0, 2, p), 0.0).1));
+        assert!(matches!(
+            buffer(symbol("cd", 1, 2, p), 1.0).1,
+            Err(Error::SequenceParityMismatch)
+        ));
+    }
+
+    #[test]
+    fn stale_sequences_are_evicted() {
+        STRUCTURED_APPEND_OPTIONS.with(|opts| {
+            opts.set(StructuredAppendOptions {
+                enabled: true,
+                timeout_ms: Some(1000),
+            })
+        });
+        let p = parity("abcd");
+        assert!(is_pending(&buffer(symbol("ab", 0, 2, p), 0.0).1));
+        // Too late: the first symbol was dropped, so this one starts over.
+        let (progress, result) = buffer(symbol("cd", 1, 2, p), 1001.0);
+        assert!(is_pending(&result));
+        assert_eq!(progress.map(|p| p.missing), Some(vec![0]));
+        assert_eq!(
+            complete(buffer(symbol("ab", 0, 2, p), 2000.0).1).value,
+            "abcd"
+        );
+    }
+
+    #[test]
+    fn the_least_recently_updated_sequence_makes_room() {
+        for total in 2..=MAX_PENDING_SEQUENCES as u8 + 2 {
+            let value = "x".repeat(total as usize);
+            assert!(is_pending(
+                &buffer(symbol("x", 0, total, parity(&value)), total as f64).1
+            ));
+        }
+        // The sequence of two symbols was the oldest and has been dropped.
+        let (progress, _) = buffer(symbol("x", 1, 2, parity("xx")), 10.0);
+        assert_eq!(progress.map(|p| p.missing), Some(vec![0]));
+        // That evicted the sequence of three; the one of four remains.
+        let (progress, _) = buffer(symbol("x", 1, 4, parity("xxxx")), 11.0);
+        assert_eq!(progress.map(|p| p.missing), Some(vec![2, 3]));
+    }
+
+    #[test]
+    fn nothing_is_buffered_while_disabled() {
+        STRUCTURED_APPEND_OPTIONS.with(|opts| {
+            opts.set(StructuredAppendOptions {
+                enabled: false,
+                timeout_ms: None,
+            })
+        });
+        let (progress, result) = buffer(symbol("ab", 0, 2, 0), 0.0);
+        assert_eq!(progress, None);
+        assert!(matches!(result, Ok(Assembly::Single(d)) if d.structured_append.is_some()));
+    }
+}
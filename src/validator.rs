@@ -0,0 +1,491 @@
+use std::cell::RefCell;
+
+use js_sys::{Array, Function, Reflect, RegExp};
+use rxing::{BarcodeFormat, oned::convertUPCEtoUPCA};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::console;
+
+use crate::{
+    detector::{Detection, Format},
+    error::Error,
+    event::detection_to_js,
+    options,
+};
+
+const GTIN_13_LENGTH: usize = 13;
+const GTIN_14_LENGTH: usize = 14;
+
+/// The form UPC/EAN values are emitted in.
+#[derive(Clone, Copy)]
+enum Normalization {
+    /// UPC-E expanded to UPC-A.
+    UpcA,
+    /// UPC-E, UPC-A and EAN-8 padded to a 13-digit EAN-13.
+    Ean13,
+    /// Every UPC/EAN/ITF-14 value as a 14-digit GTIN.
+    Gtin14,
+}
+
+#[derive(Clone)]
+struct ValidationOptions {
+    check_digit: bool,
+    normalize: Option<Normalization>,
+    min_length: Option<u32>,
+    max_length: Option<u32>,
+    pattern: Option<RegExp>,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        Self {
+            check_digit: true,
+            normalize: None,
+            min_length: None,
+            max_length: None,
+            pattern: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Filter {
+    Predicate(Function),
+    Rules {
+        pattern: Option<RegExp>,
+        prefix: Option<String>,
+        formats: Option<Vec<String>>,
+    },
+}
+
+thread_local! {
+    static VALIDATION_OPTIONS: RefCell<Option<ValidationOptions>> = const { RefCell::new(None) };
+    static FILTER: RefCell<Option<Filter>> = const { RefCell::new(None) };
+}
+
+/// Parses a `RegExp` object or a pattern string into a `RegExp`.
+pub fn parse_pattern(value: &JsValue) -> Result<RegExp, Error> {
+    if let Some(re) = value.dyn_ref::<RegExp>() {
+        return Ok(re.clone());
+    }
+
+    let source = value.as_string().ok_or(Error::InvalidOptions)?;
+    let ctor: Function = Reflect::get(&js_sys::global(), &JsValue::from_str("RegExp"))
+        .ok()
+        .and_then(|v| v.dyn_into().ok())
+        .ok_or(Error::Internal)?;
+
+    // `new RegExp(source)` throws a SyntaxError on invalid patterns, which
+    // `RegExp::new` would surface as a panic.
+    Reflect::construct(&ctor, &Array::of1(&JsValue::from_str(&source)))
+        .map_err(|_| Error::InvalidOptions)?
+        .dyn_into::<RegExp>()
+        .map_err(|_| Error::Internal)
+}
+
+/// Tests `value` against `re`, resetting `lastIndex` so global/sticky flags
+/// do not make consecutive calls alternate between matching and not matching.
+pub fn matches_pattern(re: &RegExp, value: &str) -> bool {
+    re.set_last_index(0);
+    re.test(value)
+}
+
+pub fn set_validation_options(options: &JsValue) -> Result<(), Error> {
+    let obj = options::as_object(options)?;
+    let defaults = ValidationOptions::default();
+
+    let normalize = match options::get_string(&obj, "normalize")?.as_deref() {
+        Some("upc_a") => Some(Normalization::UpcA),
+        Some("ean_13") => Some(Normalization::Ean13),
+        Some("gtin_14") => Some(Normalization::Gtin14),
+        Some(_) => return Err(Error::InvalidOptions),
+        None => defaults.normalize,
+    };
+
+    let parsed = ValidationOptions {
+        check_digit: options::get_bool(&obj, "checkDigit")?.unwrap_or(defaults.check_digit),
+        normalize,
+        min_length: options::get_u32(&obj, "minLength")?,
+        max_length: options::get_u32(&obj, "maxLength")?,
+        pattern: options::get(&obj, "pattern")
+            .map(|v| parse_pattern(&v))
+            .transpose()?,
+    };
+
+    if let (Some(min), Some(max)) = (parsed.min_length, parsed.max_length)
+        && min > max
+    {
+        return Err(Error::InvalidOptions);
+    }
+
+    VALIDATION_OPTIONS.with(|slot| *slot.borrow_mut() = Some(parsed));
+
+    Ok(())
+}
+
+/// Computes the GS1 mod-10 check digit for `data`, which must not include
+/// the check digit itself.
+pub fn gs1_check_digit(data: &str) -> Option<u8> {
+    let mut sum = 0u32;
+    for (i, c) in data.chars().rev().enumerate() {
+        let digit = c.to_digit(10)?;
+        sum += if i % 2 == 0 { digit * 3 } else { digit };
+    }
+
+    Some(((10 - sum % 10) % 10) as u8)
+}
+
+/// Returns true if the last digit of `digits` is a valid GS1 check digit.
+pub fn has_valid_check_digit(digits: &str) -> bool {
+    if digits.len() < 2 || !digits.is_ascii() {
+        return false;
+    }
+
+    let (data, check) = digits.split_at(digits.len() - 1);
+    let Some(check) = check.chars().next().and_then(|c| c.to_digit(10)) else {
+        return false;
+    };
+
+    gs1_check_digit(data) == Some(check as u8)
+}
+
+fn is_gtin(format: &Format, value: &str) -> bool {
+    let Format::Rxing(format) = format else {
+        return false;
+    };
+    let expected = match format {
+        BarcodeFormat::UPC_A => 12,
+        BarcodeFormat::EAN_13 => 13,
+        BarcodeFormat::EAN_8 | BarcodeFormat::UPC_E => 8,
+        BarcodeFormat::ITF => GTIN_14_LENGTH,
+        _ => return false,
+    };
+
+    value.len() == expected && value.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Returns the digits whose last one is the GS1 check digit: the UPC-A
+/// expansion of a UPC-E value, whose own check digit is that of the
+/// expansion, or the value itself.
+fn check_digit_data(format: &Format, value: &str) -> Option<String> {
+    match format {
+        Format::Rxing(BarcodeFormat::UPC_E) => {
+            // UPC-E carries number system 0 or 1.
+            matches!(value.as_bytes().first(), Some(b'0' | b'1'))
+                .then(|| convertUPCEtoUPCA(value))
+                .flatten()
+        }
+        _ => Some(value.to_string()),
+    }
+}
+
+/// Converts a UPC/EAN/ITF-14 value to the requested form. Values already
+/// longer than that form are left as they are.
+fn normalize_gtin(format: &Format, value: &str, normalization: Normalization) -> Option<String> {
+    if !is_gtin(format, value) {
+        return None;
+    }
+    let digits = check_digit_data(format, value)?;
+
+    Some(match normalization {
+        Normalization::UpcA => digits,
+        Normalization::Ean13 => format!("{digits:0>GTIN_13_LENGTH$}"),
+        Normalization::Gtin14 => format!("{digits:0>GTIN_14_LENGTH$}"),
+    })
+}
+
+/// Runs the configured validation on a decoded value.
+///
+/// Returns the (possibly normalized) value to emit, or `None` if the value
+/// must be rejected as a misread.
+pub fn validate(format: &Format, value: &str) -> Option<String> {
+    let opts = VALIDATION_OPTIONS.with(|slot| slot.borrow().clone().unwrap_or_default());
+
+    let gtin = is_gtin(format, value);
+    if opts.check_digit
+        && gtin
+        && !check_digit_data(format, value).is_some_and(|digits| has_valid_check_digit(&digits))
+    {
+        return None;
+    }
+
+    let len = value.chars().count() as u32;
+    if opts.min_length.is_some_and(|min| len < min) || opts.max_length.is_some_and(|max| len > max)
+    {
+        return None;
+    }
+
+    if let Some(re) = &opts.pattern
+        && !matches_pattern(re, value)
+    {
+        return None;
+    }
+
+    if let Some(normalization) = opts.normalize
+        && gtin
+    {
+        return normalize_gtin(format, value, normalization);
+    }
+
+    Some(value.to_string())
+}
+
+pub fn set_filter(filter: &JsValue) -> Result<(), Error> {
+    if filter.is_undefined() || filter.is_null() {
+        FILTER.with(|slot| *slot.borrow_mut() = None);
+        return Ok(());
+    }
+
+    let parsed = if let Some(predicate) = filter.dyn_ref::<Function>() {
+        Filter::Predicate(predicate.clone())
+    } else {
+        let obj = options::as_object(filter)?;
+        let formats = match options::get(&obj, "formats") {
+            Some(v) => {
+                let arr = v.dyn_into::<Array>().map_err(|_| Error::InvalidOptions)?;
+                let names = arr
+                    .iter()
+                    .map(|f| f.as_string().ok_or(Error::InvalidOptions))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Some(names)
+            }
+            None => None,
+        };
+
+        Filter::Rules {
+            pattern: options::get(&obj, "pattern")
+                .map(|v| parse_pattern(&v))
+                .transpose()?,
+            prefix: match options::get(&obj, "prefix") {
+                Some(v) => Some(v.as_string().ok_or(Error::InvalidOptions)?),
+                None => None,
+            },
+            formats,
+        }
+    };
+
+    FILTER.with(|slot| *slot.borrow_mut() = Some(parsed));
+
+    Ok(())
+}
+
+/// Returns true if `detection` is accepted by the caller-supplied filter.
+///
+/// A predicate that throws is logged and treated as a rejection. The filter
+/// is cloned out of its slot first, so a predicate may call `setFilter`.
+pub fn passes_filter(detection: &Detection) -> bool {
+    let Some(filter) = FILTER.with(|slot| slot.borrow().clone()) else {
+        return true;
+    };
+
+    match filter {
+        Filter::Predicate(predicate) => {
+            match predicate.call1(&JsValue::NULL, &detection_to_js(detection)) {
+                Ok(accepted) => accepted.is_truthy(),
+                Err(e) => {
+                    console::error_1(&e);
+                    false
+                }
+            }
+        }
+        Filter::Rules {
+            pattern,
+            prefix,
+            formats,
+        } => {
+            let format = detection.format.name();
+
+            formats
+                .as_ref()
+                .is_none_or(|names| names.iter().any(|name| name == format))
+                && prefix
+                    .as_ref()
+                    .is_none_or(|p| detection.value.starts_with(p.as_str()))
+                && pattern
+                    .as_ref()
+                    .is_none_or(|re| matches_pattern(re, &detection.value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_options(opts: ValidationOptions) {
+        VALIDATION_OPTIONS.with(|slot| *slot.borrow_mut() = Some(opts));
+    }
+
+    fn upc_a() -> Format {
+        Format::Rxing(BarcodeFormat::UPC_A)
+    }
+
+    fn upc_e() -> Format {
+        Format::Rxing(BarcodeFormat::UPC_E)
+    }
+
+    #[test]
+    fn check_digit_of_known_gtins() {
+        assert_eq!(gs1_check_digit("03600029145"), Some(2));
+        assert_eq!(gs1_check_digit("400638133393"), Some(1));
+        assert_eq!(gs1_check_digit("9638507"), Some(4));
+        assert_eq!(gs1_check_digit("1001234567890"), Some(2));
+        assert_eq!(gs1_check_digit(""), Some(0));
+        assert_eq!(gs1_check_digit("12a4"), None);
+    }
+
+    #[test]
+    fn valid_check_digits() {
+        assert!(has_valid_check_digit("036000291452"));
+        assert!(has_valid_check_digit("4006381333931"));
+        assert!(has_valid_check_digit("96385074"));
+        assert!(has_valid_check_digit("10012345678902"));
+        assert!(!has_valid_check_digit("036000291453"));
+        assert!(!has_valid_check_digit("4006381333930"));
+        assert!(!has_valid_check_digit("0"));
+        assert!(!has_valid_check_digit(""));
+        assert!(!has_valid_check_digit("03600029145x"));
+        assert!(!has_valid_check_digit("٣٦"));
+    }
+
+    #[test]
+    fn upc_e_check_digit_uses_expansion() {
+        assert_eq!(
+            check_digit_data(&upc_e(), "04252614").as_deref(),
+            Some("042100005264")
+        );
+        assert!(has_valid_check_digit("042100005264"));
+        // Number systems other than 0 and 1 have no UPC-E form.
+        assert_eq!(check_digit_data(&upc_e(), "24252614"), None);
+        assert_eq!(validate(&upc_e(), "04252614").as_deref(), Some("04252614"));
+        assert_eq!(validate(&upc_e(), "04252615"), None);
+    }
+
+    #[test]
+    fn upc_e_expansion_by_last_digit() {
+        for (upc_e, upc_a) in [
+            ("01234505", "012000003455"),
+            ("01234514", "012100003454"),
+            ("01234523", "012200003453"),
+            ("01234531", "012300000451"),
+            ("01234543", "012340000053"),
+            ("01234558", "012345000058"),
+            ("01234596", "012345000096"),
+        ] {
+            let expanded = check_digit_data(&Format::Rxing(BarcodeFormat::UPC_E), upc_e);
+            assert_eq!(expanded.as_deref(), Some(upc_a), "{upc_e}");
+            assert!(has_valid_check_digit(upc_a), "{upc_a}");
+        }
+    }
+
+    #[test]
+    fn normalization_pads_to_requested_length() {
+        let ean_8 = Format::Rxing(BarcodeFormat::EAN_8);
+        let ean_13 = Format::Rxing(BarcodeFormat::EAN_13);
+        let itf = Format::Rxing(BarcodeFormat::ITF);
+
+        let cases = [
+            (&upc_e(), "04252614", Normalization::UpcA, "042100005264"),
+            (&upc_e(), "04252614", Normalization::Ean13, "0042100005264"),
+            (
+                &upc_e(),
+                "04252614",
+                Normalization::Gtin14,
+                "00042100005264",
+            ),
+            (
+                &upc_a(),
+                "036000291452",
+                Normalization::UpcA,
+                "036000291452",
+            ),
+            (
+                &upc_a(),
+                "036000291452",
+                Normalization::Gtin14,
+                "00036000291452",
+            ),
+            (&ean_8, "96385074", Normalization::Ean13, "0000096385074"),
+            (
+                &ean_13,
+                "4006381333931",
+                Normalization::UpcA,
+                "4006381333931",
+            ),
+            (
+                &ean_13,
+                "4006381333931",
+                Normalization::Gtin14,
+                "04006381333931",
+            ),
+            (
+                &itf,
+                "10012345678902",
+                Normalization::Ean13,
+                "10012345678902",
+            ),
+        ];
+        for (format, value, normalization, expected) in cases {
+            assert_eq!(
+                normalize_gtin(format, value, normalization).as_deref(),
+                Some(expected),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn non_gtin_values_are_not_normalized() {
+        let qr = Format::Rxing(BarcodeFormat::QR_CODE);
+        assert_eq!(
+            normalize_gtin(&qr, "036000291452", Normalization::Gtin14),
+            None
+        );
+        assert_eq!(
+            normalize_gtin(&upc_a(), "03600029145", Normalization::Gtin14),
+            None
+        );
+        assert_eq!(
+            normalize_gtin(&Format::Pharmacode, "1234", Normalization::Gtin14),
+            None
+        );
+
+        with_options(ValidationOptions {
+            normalize: Some(Normalization::Gtin14),
+            ..ValidationOptions::default()
+        });
+        assert_eq!(
+            validate(&qr, "036000291452").as_deref(),
+            Some("036000291452")
+        );
+    }
+
+    #[test]
+    fn length_rules_apply_to_the_scanned_value() {
+        with_options(ValidationOptions {
+            normalize: Some(Normalization::Gtin14),
+            min_length: Some(12),
+            max_length: Some(12),
+            ..ValidationOptions::default()
+        });
+        assert_eq!(
+            validate(&upc_a(), "036000291452").as_deref(),
+            Some("00036000291452")
+        );
+        assert_eq!(validate(&upc_e(), "04252614"), None);
+    }
+
+    #[test]
+    fn check_digit_rule_can_be_disabled() {
+        assert_eq!(validate(&upc_a(), "036000291453"), None);
+
+        with_options(ValidationOptions {
+            check_digit: false,
+            normalize: Some(Normalization::Ean13),
+            ..ValidationOptions::default()
+        });
+        assert_eq!(
+            validate(&upc_a(), "036000291453").as_deref(),
+            Some("0036000291453")
+        );
+    }
+}
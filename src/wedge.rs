@@ -0,0 +1,590 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rxing::BarcodeFormat;
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::{
+    EventTarget, HtmlDocument, HtmlElement, HtmlInputElement, HtmlTextAreaElement, KeyboardEvent,
+};
+
+use crate::{
+    detector::{Detection, Format},
+    error::Error,
+    event::invoke_on_detect_tagged,
+    options, validator,
+};
+
+/// ASCII group separator, the character GS1 uses for FNC1.
+const GROUP_SEPARATOR: char = '\u{1d}';
+
+const DEFAULT_MAX_INTER_KEY_MS: f64 = 35.0;
+const DEFAULT_MIN_LENGTH: usize = 4;
+
+#[derive(Clone)]
+struct WedgeOptions {
+    max_inter_key_ms: f64,
+    min_length: usize,
+    prefix: String,
+    suffix: String,
+    terminator: String,
+    fnc1: Option<char>,
+    aim_ids: bool,
+    prevent_default: bool,
+}
+
+/// Keys of a possible burst held back from the focused field until the
+/// terminator shows a scanner typed them, or a pause shows a person did.
+struct PendingKeys {
+    text: String,
+    target: EventTarget,
+    timeout_id: i32,
+}
+
+/// Keystrokes received since the last pause longer than `max_inter_key_ms`.
+#[derive(Default)]
+struct Burst {
+    chars: String,
+    last_key_ms: f64,
+    pending: Option<PendingKeys>,
+}
+
+struct Capture {
+    target: EventTarget,
+    listener: Closure<dyn FnMut(KeyboardEvent)>,
+    burst: Rc<RefCell<Burst>>,
+    _replay: Closure<dyn FnMut()>,
+}
+
+thread_local! {
+    static CAPTURE: RefCell<Option<Capture>> = const { RefCell::new(None) };
+}
+
+fn parse_options(options: &JsValue) -> Result<WedgeOptions, Error> {
+    let obj = options::as_object(options)?;
+
+    let fnc1 = match options::get_string(&obj, "fnc1")? {
+        Some(s) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => return Err(Error::InvalidOptions),
+            }
+        }
+        None => None,
+    };
+
+    Ok(WedgeOptions {
+        max_inter_key_ms: options::get_u32(&obj, "maxInterKeyMs")?
+            .map_or(DEFAULT_MAX_INTER_KEY_MS, |ms| ms as f64),
+        min_length: options::get_u32(&obj, "minLength")?
+            .map_or(DEFAULT_MIN_LENGTH, |len| len.max(1) as usize),
+        prefix: options::get_string(&obj, "prefix")?.unwrap_or_default(),
+        suffix: options::get_string(&obj, "suffix")?.unwrap_or_default(),
+        terminator: options::get_string(&obj, "terminator")?.unwrap_or_else(|| "Enter".into()),
+        fnc1,
+        aim_ids: options::get_bool(&obj, "aimIds")?.unwrap_or(true),
+        prevent_default: options::get_bool(&obj, "preventDefault")?.unwrap_or(false),
+    })
+}
+
+/// Maps an AIM symbology identifier (`]` + code character + modifier) to a
+/// barcode format. GS1 modifiers mark values whose FNC1 separators matter.
+fn aim_format(code: char, modifier: char) -> BarcodeFormat {
+    match code {
+        'A' => BarcodeFormat::CODE_39,
+        'C' => BarcodeFormat::CODE_128,
+        'E' if modifier == '4' => BarcodeFormat::EAN_8,
+        'E' => BarcodeFormat::EAN_13,
+        'F' => BarcodeFormat::CODABAR,
+        'G' => BarcodeFormat::CODE_93,
+        'I' => BarcodeFormat::ITF,
+        'L' => BarcodeFormat::PDF_417,
+        'Q' => BarcodeFormat::QR_CODE,
+        'U' => BarcodeFormat::MAXICODE,
+        'd' => BarcodeFormat::DATA_MATRIX,
+        'e' => BarcodeFormat::RSS_14,
+        'z' => BarcodeFormat::AZTEC,
+        _ => BarcodeFormat::UNSUPORTED_FORMAT,
+    }
+}
+
+/// Guesses the symbology of a value typed without an AIM identifier from
+/// its GTIN length and check digit.
+fn guess_format(value: &str) -> BarcodeFormat {
+    if !value.bytes().all(|b| b.is_ascii_digit()) || !validator::has_valid_check_digit(value) {
+        return BarcodeFormat::UNSUPORTED_FORMAT;
+    }
+
+    match value.len() {
+        8 => BarcodeFormat::EAN_8,
+        12 => BarcodeFormat::UPC_A,
+        13 => BarcodeFormat::EAN_13,
+        14 => BarcodeFormat::ITF,
+        _ => BarcodeFormat::UNSUPORTED_FORMAT,
+    }
+}
+
+/// Strips the configured prefix/suffix and the AIM identifier from a burst.
+///
+/// Returns `None` if a configured prefix or suffix is missing, which means
+/// the burst did not come from the scanner.
+fn decode_burst(raw: &str, opts: &WedgeOptions) -> Option<(String, BarcodeFormat)> {
+    let value = raw.strip_prefix(opts.prefix.as_str())?;
+    let value = value.strip_suffix(opts.suffix.as_str())?;
+
+    let mut chars = value.chars();
+    if opts.aim_ids
+        && chars.next() == Some(']')
+        && let (Some(code), Some(modifier)) = (chars.next(), chars.next())
+    {
+        let data = chars.as_str();
+        // GS1 symbols begin with an implied FNC1, which scanners do not type.
+        let data = data.strip_prefix(GROUP_SEPARATOR).unwrap_or(data);
+        return Some((data.to_string(), aim_format(code, modifier)));
+    }
+
+    Some((value.to_string(), guess_format(value)))
+}
+
+fn emit(raw: &str, opts: &WedgeOptions) {
+    let Some((value, format)) = decode_burst(raw, opts) else {
+        return;
+    };
+    if value.chars().count() < opts.min_length {
+        return;
+    }
+
+    let tags = [("source", JsValue::from_str("keyboard"))];
+    let format = Format::Rxing(format);
+    let detection = validator::validate(&format, &value).map(|value| Detection {
+        value,
+        format,
+        points: Vec::new(),
+        structured_append: None,
+        linked_composite: false,
+        add_on: None,
+    });
+
+    match detection {
+        Some(detection) if validator::passes_filter(&detection) => {
+            invoke_on_detect_tagged(Ok(&detection), &tags)
+        }
+        _ => invoke_on_detect_tagged(Err(&Error::Rejected), &tags),
+    }
+}
+
+/// Returns the character a keystroke adds to a burst, or `None` for named
+/// keys and shortcuts.
+fn key_char(key: &str, ctrl: bool, other_modifier: bool, fnc1: Option<char>) -> Option<char> {
+    if ctrl && key == "]" {
+        // Scanners in keyboard mode often send GS as Ctrl+].
+        return Some(GROUP_SEPARATOR);
+    }
+
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if Some(c) == fnc1 => Some(GROUP_SEPARATOR),
+        (Some(c), None) if !ctrl && !other_modifier => Some(c),
+        _ => None,
+    }
+}
+
+/// What the browser should do with a character keystroke.
+#[derive(Debug, PartialEq)]
+enum KeyAction {
+    /// Let the key reach the page as typed.
+    PassThrough,
+    /// Keep the key from its field until the burst is known to be typing.
+    Hold,
+    /// Keep the key from the page; it belongs to a scan.
+    Swallow,
+}
+
+/// Decides a character keystroke's fate. Keys are only ever held back or
+/// swallowed when `preventDefault` is on, since replaying them cannot
+/// reproduce IME composition or every input type.
+fn key_action(opts: &WedgeOptions, in_burst: bool, pending: bool, editable: bool) -> KeyAction {
+    if !opts.prevent_default {
+        KeyAction::PassThrough
+    } else if pending || (!in_burst && editable) {
+        KeyAction::Hold
+    } else if in_burst {
+        KeyAction::Swallow
+    } else {
+        KeyAction::PassThrough
+    }
+}
+
+/// Returns true for elements a keystroke types into.
+fn is_editable(target: &EventTarget) -> bool {
+    target.is_instance_of::<HtmlInputElement>()
+        || target.is_instance_of::<HtmlTextAreaElement>()
+        || target
+            .dyn_ref::<HtmlElement>()
+            .is_some_and(HtmlElement::is_content_editable)
+}
+
+/// Types held-back keys into the field they were aimed at, as the browser
+/// would have done.
+fn replay(key: PendingKeys) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    window.clear_timeout_with_handle(key.timeout_id);
+
+    let document = window.document();
+    let focused = document
+        .as_ref()
+        .and_then(|doc| doc.active_element())
+        .is_some_and(|active| JsValue::from(active) == JsValue::from(&key.target));
+    if !focused || key.text.is_empty() {
+        return;
+    }
+
+    // `insertText` keeps the undo history and fires `input` like real typing.
+    let inserted = document
+        .and_then(|doc| doc.dyn_into::<HtmlDocument>().ok())
+        .and_then(|doc| {
+            doc.exec_command_with_show_ui_and_value("insertText", false, &key.text)
+                .ok()
+        })
+        .unwrap_or(false);
+    if inserted {
+        return;
+    }
+
+    if let Some(input) = key.target.dyn_ref::<HtmlInputElement>() {
+        input.set_range_text(&key.text).ok();
+        if let Ok(Some(end)) = input.selection_end() {
+            input.set_selection_range(end, end).ok();
+        }
+    } else if let Some(textarea) = key.target.dyn_ref::<HtmlTextAreaElement>() {
+        textarea.set_range_text(&key.text).ok();
+        if let Ok(Some(end)) = textarea.selection_end() {
+            textarea.set_selection_range(end, end).ok();
+        }
+    }
+}
+
+/// Returns the held-back keys, if any, for the caller to replay once the
+/// burst is no longer borrowed.
+fn take_pending(burst: &RefCell<Burst>) -> Option<PendingKeys> {
+    burst.borrow_mut().pending.take()
+}
+
+fn handle_key(
+    evt: &KeyboardEvent,
+    state: &RefCell<Burst>,
+    opts: &WedgeOptions,
+    replay_callback: &js_sys::Function,
+) {
+    let now = evt.time_stamp();
+    let key = evt.key();
+    let in_burst = {
+        let burst = state.borrow();
+        !burst.chars.is_empty() && now - burst.last_key_ms <= opts.max_inter_key_ms
+    };
+
+    if key == opts.terminator {
+        let pending = take_pending(state);
+        let raw = std::mem::take(&mut *state.borrow_mut()).chars;
+        if in_burst && raw.chars().count() >= opts.min_length {
+            if let (Some(window), Some(pending)) = (web_sys::window(), pending) {
+                window.clear_timeout_with_handle(pending.timeout_id);
+            }
+            if opts.prevent_default {
+                evt.prevent_default();
+            }
+            emit(&raw, opts);
+        } else if let Some(pending) = pending {
+            // Typed before the terminator reaches the field.
+            replay(pending);
+        }
+        return;
+    }
+
+    let Some(ch) = key_char(
+        &key,
+        evt.ctrl_key(),
+        evt.meta_key() || evt.alt_key(),
+        opts.fnc1,
+    ) else {
+        // Modifier keys are part of typing shifted characters; any other
+        // named key (arrows, Backspace, ...) means a human is typing.
+        if !matches!(
+            key.as_str(),
+            "Shift" | "Control" | "Alt" | "AltGraph" | "CapsLock"
+        ) {
+            let pending = take_pending(state);
+            *state.borrow_mut() = Burst::default();
+            if let Some(pending) = pending {
+                replay(pending);
+            }
+        }
+        return;
+    };
+
+    if !in_burst {
+        // A late timer may not have replayed the previous keys yet.
+        if let Some(pending) = take_pending(state) {
+            replay(pending);
+        }
+        state.borrow_mut().chars.clear();
+    }
+
+    // The first keystrokes cannot be told apart from typing, so keys bound
+    // for a field are held back and typed in unless a terminator follows
+    // before the next pause.
+    let target = evt.target();
+    let action = key_action(
+        opts,
+        in_burst,
+        state.borrow().pending.is_some(),
+        target.as_ref().is_some_and(is_editable),
+    );
+    if action != KeyAction::PassThrough
+        && let Some(window) = web_sys::window()
+    {
+        let mut burst = state.borrow_mut();
+        // Ctrl+] types nothing into a field.
+        let text = if evt.ctrl_key() { "" } else { key.as_str() };
+        let held = match (&action, burst.pending.take()) {
+            (KeyAction::Hold, Some(mut pending)) => {
+                window.clear_timeout_with_handle(pending.timeout_id);
+                pending.text.push_str(text);
+                Some((pending.text, pending.target))
+            }
+            (KeyAction::Hold, None) => target.map(|target| (text.to_string(), target)),
+            _ => None,
+        };
+
+        if let Some((text, target)) = held
+            && let Ok(timeout_id) = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                replay_callback,
+                opts.max_inter_key_ms.ceil() as i32,
+            )
+        {
+            burst.pending = Some(PendingKeys {
+                text,
+                target,
+                timeout_id,
+            });
+        }
+        if burst.pending.is_some() || in_burst {
+            evt.prevent_default();
+        }
+    }
+
+    let mut burst = state.borrow_mut();
+    burst.chars.push(ch);
+    burst.last_key_ms = now;
+}
+
+pub fn start_keyboard_wedge_capture(
+    target: &EventTarget,
+    options: &JsValue,
+) -> Result<(), JsValue> {
+    let opts = parse_options(options)?;
+    stop_keyboard_wedge_capture();
+
+    let burst = Rc::new(RefCell::new(Burst::default()));
+    let replay_burst = burst.clone();
+    let replay_closure = Closure::wrap(Box::new(move || {
+        if let Some(pending) = take_pending(&replay_burst) {
+            replay(pending);
+        }
+    }) as Box<dyn FnMut()>);
+
+    let listener_burst = burst.clone();
+    let replay_callback: js_sys::Function = replay_closure.as_ref().clone().unchecked_into();
+    let listener = Closure::wrap(Box::new(move |evt: KeyboardEvent| {
+        handle_key(&evt, &listener_burst, &opts, &replay_callback);
+    }) as Box<dyn FnMut(KeyboardEvent)>);
+
+    // Capture phase, so that page handlers cannot swallow scanner keys.
+    target.add_event_listener_with_callback_and_bool(
+        "keydown",
+        listener.as_ref().unchecked_ref(),
+        true,
+    )?;
+
+    CAPTURE.with(|capture| {
+        *capture.borrow_mut() = Some(Capture {
+            target: target.clone(),
+            listener,
+            burst,
+            _replay: replay_closure,
+        });
+    });
+
+    Ok(())
+}
+
+pub fn stop_keyboard_wedge_capture() {
+    let Some(capture) = CAPTURE.with(|capture| capture.borrow_mut().take()) else {
+        return;
+    };
+
+    capture
+        .target
+        .remove_event_listener_with_callback_and_bool(
+            "keydown",
+            capture.listener.as_ref().unchecked_ref(),
+            true,
+        )
+        .ok();
+
+    if let Some(pending) = take_pending(&capture.burst) {
+        replay(pending);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> WedgeOptions {
+        WedgeOptions {
+            max_inter_key_ms: DEFAULT_MAX_INTER_KEY_MS,
+            min_length: DEFAULT_MIN_LENGTH,
+            prefix: String::new(),
+            suffix: String::new(),
+            terminator: "Enter".into(),
+            fnc1: None,
+            aim_ids: true,
+            prevent_default: false,
+        }
+    }
+
+    #[test]
+    fn typing_passes_through_unless_keys_are_held_on_request() {
+        let opts = options();
+        // By default no keystroke is held back or swallowed, in a field or
+        // out of one, at typing pace or at scanner pace.
+        for in_burst in [false, true] {
+            for editable in [false, true] {
+                assert_eq!(
+                    key_action(&opts, in_burst, false, editable),
+                    KeyAction::PassThrough
+                );
+            }
+        }
+
+        let opts = WedgeOptions {
+            prevent_default: true,
+            ..options()
+        };
+        // Outside a field, only keys arriving at scanner pace are swallowed.
+        assert_eq!(
+            key_action(&opts, false, false, false),
+            KeyAction::PassThrough
+        );
+        assert_eq!(key_action(&opts, true, false, false), KeyAction::Swallow);
+        // In a field, a burst's keys are held until it proves to be a scan.
+        assert_eq!(key_action(&opts, false, false, true), KeyAction::Hold);
+        assert_eq!(key_action(&opts, true, true, true), KeyAction::Hold);
+    }
+
+    #[test]
+    fn aim_identifiers_set_the_format() {
+        let opts = options();
+        let cases = [
+            ("]C1ABC-123", "ABC-123", BarcodeFormat::CODE_128),
+            ("]E00036000291452", "0036000291452", BarcodeFormat::EAN_13),
+            ("]E496385074", "96385074", BarcodeFormat::EAN_8),
+            (
+                "]Q1https://example.com",
+                "https://example.com",
+                BarcodeFormat::QR_CODE,
+            ),
+            (
+                "]d2010001234567890",
+                "010001234567890",
+                BarcodeFormat::DATA_MATRIX,
+            ),
+            ("]X0payload", "payload", BarcodeFormat::UNSUPORTED_FORMAT),
+        ];
+        for (raw, value, format) in cases {
+            assert_eq!(
+                decode_burst(raw, &opts),
+                Some((value.to_string(), format)),
+                "{raw}"
+            );
+        }
+    }
+
+    #[test]
+    fn gs1_values_drop_the_leading_fnc1_and_keep_separators() {
+        let raw = format!("]C1{GROUP_SEPARATOR}0100012345678905{GROUP_SEPARATOR}10LOT");
+        assert_eq!(
+            decode_burst(&raw, &options()),
+            Some((
+                format!("0100012345678905{GROUP_SEPARATOR}10LOT"),
+                BarcodeFormat::CODE_128
+            ))
+        );
+    }
+
+    #[test]
+    fn values_without_identifiers_are_guessed_from_gtins() {
+        let opts = WedgeOptions {
+            aim_ids: false,
+            ..options()
+        };
+        assert_eq!(
+            decode_burst("036000291452", &opts),
+            Some(("036000291452".into(), BarcodeFormat::UPC_A))
+        );
+        assert_eq!(
+            decode_burst("10012345678902", &opts),
+            Some(("10012345678902".into(), BarcodeFormat::ITF))
+        );
+        assert_eq!(
+            decode_burst("036000291453", &opts),
+            Some(("036000291453".into(), BarcodeFormat::UNSUPORTED_FORMAT))
+        );
+        // With identifiers off, a leading `]` is data.
+        assert_eq!(
+            decode_burst("]C1ABC", &opts),
+            Some(("]C1ABC".into(), BarcodeFormat::UNSUPORTED_FORMAT))
+        );
+        // A truncated identifier is left as data.
+        assert_eq!(
+            decode_burst("]C", &options()),
+            Some(("]C".into(), BarcodeFormat::UNSUPORTED_FORMAT))
+        );
+    }
+
+    #[test]
+    fn fnc1_keys_map_to_the_group_separator() {
+        assert_eq!(key_char("]", true, false, None), Some(GROUP_SEPARATOR));
+        assert_eq!(
+            key_char("~", false, false, Some('~')),
+            Some(GROUP_SEPARATOR)
+        );
+        assert_eq!(key_char("~", false, false, None), Some('~'));
+        assert_eq!(key_char("]", false, false, None), Some(']'));
+        assert_eq!(key_char("a", true, false, None), None);
+        assert_eq!(key_char("a", false, true, None), None);
+        assert_eq!(key_char("Tab", false, false, None), None);
+        assert_eq!(key_char("é", false, false, None), Some('é'));
+    }
+
+    #[test]
+    fn prefix_and_suffix_are_required_and_stripped() {
+        let opts = WedgeOptions {
+            prefix: "#".into(),
+            suffix: "$".into(),
+            ..options()
+        };
+        assert_eq!(
+            decode_burst("#]C1ABC$", &opts),
+            Some(("ABC".into(), BarcodeFormat::CODE_128))
+        );
+        assert_eq!(decode_burst("]C1ABC$", &opts), None);
+        assert_eq!(decode_burst("#]C1ABC", &opts), None);
+        assert_eq!(
+            decode_burst("#$", &opts),
+            Some((String::new(), BarcodeFormat::UNSUPORTED_FORMAT))
+        );
+    }
+}